@@ -0,0 +1,83 @@
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+};
+
+use crate::{
+    lib::{airports, db, output, providers::Place, utils},
+    Database,
+};
+
+pub fn parse_airports(place: &Place, radius: f64) -> String {
+    let (lat, lon) = (place.latitude, place.longitude);
+    let stations = airports::nearest(lat, lon, radius);
+
+    if stations.is_empty() {
+        return format!("`No stations found within {radius} miles of {place}`");
+    }
+
+    let mut list = String::new();
+
+    for (airport, distance, bearing) in stations.iter().take(10) {
+        let name: String = airport.name.chars().take(36).collect();
+
+        list.push_str(&format!(
+            "{:<6}{:<38}{:>5.0} mi  {:<3} ({:03.0}\u{b0})\n",
+            airport.ident,
+            name,
+            distance,
+            utils::cardinal_direction(*bearing),
+            bearing
+        ));
+    }
+
+    format!(
+        "```
+Nearest Airports => {place} (lat: {lat:.2}, lon: {lon:.2})
+
+{list}
+Showing {} of {} stations within {radius} miles
+```",
+        stations.len().min(10),
+        stations.len()
+    )
+}
+
+#[command]
+pub async fn airports(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+    let (query, radius) = utils::split_count(args.message());
+    let query = if query.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        query
+    };
+    let radius = match radius {
+        Some(val) => match val.parse::<f64>() {
+            Ok(val) if (1.0..=500.0).contains(&val) => val,
+            _ => {
+                msg.channel_id
+                    .say(&ctx.http, "`The radius provided must be between 1 and 500 miles`")
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => 50.0,
+    };
+
+    match utils::resolve_location(ctx, msg, &query).await {
+        Ok(place) => {
+            let data = parse_airports(&place, radius);
+            output::say(ctx, msg, &data).await?
+        }
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+        }
+    };
+
+    Ok(())
+}
@@ -1,38 +1,77 @@
+use chrono::prelude::*;
 use serenity::{
     framework::standard::{macros::command, Args, CommandResult},
     model::prelude::*,
     prelude::*,
+    utils::Colour,
 };
+use sqlx::sqlite::SqlitePool;
+use std::{collections::HashSet, fmt};
 
 use crate::{
-    commands::wx,
-    lib::{config, error::Error, utils, utils::GeocodeResponse},
+    lib::{
+        config, db,
+        error::Error,
+        output::{self, Report},
+        providers::{Place, Registry},
+        utils,
+    },
+    Database, Providers,
 };
 
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct AlertResponse {
     features: Vec<AlertFeature>,
+    #[serde(default)]
     title: String,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct AlertFeature {
+    #[serde(default)]
+    id: String,
+    /// GeoJSON polygon for storm-based warnings; zone-based alerts have none.
+    geometry: Option<serde_json::Value>,
     properties: AlertProperties,
 }
 
-#[allow(dead_code)]
+#[allow(non_snake_case)]
 #[derive(Debug, Deserialize)]
 struct AlertProperties {
-    headline: String,
+    id: String,
+    #[serde(default)]
+    areaDesc: String,
+    headline: Option<String>,
+    event: String,
     severity: String,
+    urgency: String,
+    certainty: String,
+    onset: Option<DateTime<FixedOffset>>,
+    expires: Option<DateTime<FixedOffset>>,
+    description: Option<String>,
+    instruction: Option<String>,
+    messageType: String,
+    #[serde(default)]
+    references: Vec<AlertReference>,
+}
+
+impl AlertProperties {
+    /// The NWS headline, or the event for alerts issued without one.
+    fn headline(&self) -> &str {
+        self.headline.as_deref().unwrap_or(&self.event)
+    }
 }
 
-async fn fetch_alerts(alert_zone: &str) -> Result<AlertResponse, Error> {
+/// An earlier alert that an update or cancellation replaces.
+#[derive(Debug, Deserialize)]
+struct AlertReference {
+    identifier: String,
+}
+
+async fn fetch_point_alerts(lat: f64, lon: f64) -> Result<AlertResponse, Error> {
     let config = config::Config::load_config()?;
-    let alert_zone = alert_zone.to_uppercase();
-    let url = format!("https://api.weather.gov/alerts/active/zone/{alert_zone}");
+    let url = format!("https://api.weather.gov/alerts/active?point={lat:.4},{lon:.4}");
     let client = reqwest::ClientBuilder::new().user_agent(config.user_agent).build()?;
     let resp = client.get(&url).send().await?.json().await;
 
@@ -41,59 +80,529 @@ async fn fetch_alerts(alert_zone: &str) -> Result<AlertResponse, Error> {
             let resp: AlertResponse = data;
             Ok(resp)
         }
-        Err(_) => Err(Error::NotFound("The alert zone provided does not exist".into())),
+        Err(_) => Err(Error::NotFound("Alerts are not available for the location provided".into())),
+    }
+}
+
+/// Ranks a CAP severity so the most severe alert sets the report color.
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "Extreme" => 4,
+        "Severe" => 3,
+        "Moderate" => 2,
+        "Minor" => 1,
+        _ => 0,
     }
 }
 
-pub async fn parse_alerts(zip_code: i32, data: GeocodeResponse) -> String {
-    let (lat, lon) = (data.results[0].latitude, data.results[0].longitude);
+/// Returns the CAP severity for a case-insensitive name.
+fn parse_severity(val: &str) -> Result<&'static str, Error> {
+    match val.trim().to_lowercase().as_str() {
+        "extreme" => Ok("Extreme"),
+        "severe" => Ok("Severe"),
+        "moderate" => Ok("Moderate"),
+        "minor" => Ok("Minor"),
+        _ => Err(Error::Invalid("Severity must be minor, moderate, severe or extreme".into())),
+    }
+}
 
-    match wx::fetch_wx(lat, lon).await {
-        Ok(data) => {
-            let alert_zone = data.location.zone;
-            match fetch_alerts(&alert_zone).await {
-                Ok(data) => {
-                    if data.features.is_empty() {
-                        format!("`No active alerts for {zip_code}`")
-                    } else {
-                        let mut alerts = String::new();
-
-                        for alert in data.features.iter().rev() {
-                            alerts.push_str(&format!(
-                                "- {} ({})\n",
-                                alert.properties.headline, alert.properties.severity,
-                            ));
-                        }
-                        format!("```{}\n\n{}\nRead more here: https://alerts.weather.gov/cap/wwaatmget.php?x={}&y=1```", data.title, alerts, alert_zone)
-                    }
+fn severity_color(severity: &str) -> Colour {
+    match severity {
+        "Extreme" => Colour::new(0x8B_00_00),
+        "Severe" => Colour::RED,
+        "Moderate" => Colour::ORANGE,
+        "Minor" => Colour::GOLD,
+        _ => Colour::LIGHT_GREY,
+    }
+}
+
+/// Returns the forecast zone for a place and its active alerts, oldest first as
+/// numbered by `!alerts`, or None outside NWS coverage. Alerts are queried for
+/// the point, the same as the alerts sent to subscribers, so both include
+/// storm-based warnings that only cover part of the zone.
+async fn fetch_place_alerts(
+    providers: &Registry,
+    place: &Place,
+) -> Result<Option<(String, AlertResponse)>, Error> {
+    let data = providers.fetch_weather(place.latitude, place.longitude).await?;
+    let alert_zone = match data.zone {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+    let mut data = fetch_point_alerts(place.latitude, place.longitude).await?;
+
+    data.features.reverse();
+
+    Ok(Some((alert_zone, data)))
+}
+
+pub async fn parse_alerts(providers: &Registry, place: &Place) -> Result<Report, Error> {
+    let (alert_zone, data) = match fetch_place_alerts(providers, place).await? {
+        Some(val) => val,
+        None => {
+            return Ok(Report::new(format!("Alerts => {place}"))
+                .description(format!("Alerts are not available for {place}")))
+        }
+    };
+
+    if data.features.is_empty() {
+        return Ok(Report::new(data.title)
+            .description(format!("No active alerts for {place}"))
+            .color(Colour::DARK_GREEN));
+    }
+
+    let url = format!("https://alerts.weather.gov/cap/wwaatmget.php?x={alert_zone}&y=1");
+    let alerts: Vec<String> = data
+        .features
+        .iter()
+        .enumerate()
+        .map(|(i, x)| format!("{}. {} ({})", i + 1, x.properties.headline(), x.properties.severity))
+        .collect();
+    let severity = data
+        .features
+        .iter()
+        .map(|x| x.properties.severity.as_str())
+        .max_by_key(|x| severity_rank(x))
+        .unwrap_or_default();
+
+    Ok(Report::new(data.title)
+        .description(alerts.join("\n"))
+        .footer(format!("Use !alerts detail <n> for details. Read more here: {url}"))
+        .url(url)
+        .color(severity_color(severity)))
+}
+
+/// Formats a CAP time in the offset it was issued with, which is local to the
+/// alert area.
+fn format_time(time: Option<DateTime<FixedOffset>>) -> String {
+    match time {
+        Some(val) => utils::format_local(val, "%a %I:%M %p"),
+        None => String::from("-"),
+    }
+}
+
+/// Returns the area-weighted centroid (lat, lon) of the outer rings of a
+/// GeoJSON Polygon or MultiPolygon.
+fn centroid(geometry: &serde_json::Value) -> Option<(f64, f64)> {
+    let polygons: Vec<&serde_json::Value> = match geometry["type"].as_str()? {
+        "Polygon" => vec![&geometry["coordinates"]],
+        "MultiPolygon" => geometry["coordinates"].as_array()?.iter().collect(),
+        _ => return None,
+    };
+    let (mut area, mut lon, mut lat) = (0.0, 0.0, 0.0);
+
+    for polygon in polygons {
+        let ring: Vec<(f64, f64)> = polygon
+            .get(0)?
+            .as_array()?
+            .iter()
+            .filter_map(|x| Some((x.get(0)?.as_f64()?, x.get(1)?.as_f64()?)))
+            .collect();
+
+        for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            let cross = a.0 * b.1 - b.0 * a.1;
+
+            area += cross;
+            lon += (a.0 + b.0) * cross;
+            lat += (a.1 + b.1) * cross;
+        }
+    }
+
+    if area.abs() < f64::EPSILON {
+        return None;
+    }
+
+    Some((lat / (3.0 * area), lon / (3.0 * area)))
+}
+
+/// Returns the full text of the nth alert listed by `parse_alerts`.
+pub async fn parse_alert_detail(
+    providers: &Registry,
+    place: &Place,
+    n: usize,
+) -> Result<Report, Error> {
+    let data = match fetch_place_alerts(providers, place).await? {
+        Some((_, data)) => data,
+        None => return Err(Error::NotFound(format!("Alerts are not available for {place}"))),
+    };
+    let feature = match n.checked_sub(1).and_then(|x| data.features.get(x)) {
+        Some(val) => val,
+        None if data.features.is_empty() => {
+            return Err(Error::NotFound(format!("No active alerts for {place}")))
+        }
+        None => {
+            return Err(Error::Invalid(format!(
+                "There are {} active alerts for {place}",
+                data.features.len()
+            )))
+        }
+    };
+    let alert = &feature.properties;
+    let centroid = match feature.geometry.as_ref().and_then(centroid) {
+        Some((lat, lon)) => format!("{lat:.2}, {lon:.2}"),
+        None => String::from("- (zone-based alert)"),
+    };
+    let mut report = Report::new(alert.headline())
+        .description(alert.description.as_deref().unwrap_or("No description provided"))
+        .field("Event", &alert.event)
+        .field("Severity", &alert.severity)
+        .field("Urgency", &alert.urgency)
+        .field("Certainty", &alert.certainty)
+        .field("Onset", format_time(alert.onset))
+        .field("Expires", format_time(alert.expires))
+        .field("Centroid", centroid);
+
+    if let Some(instruction) = &alert.instruction {
+        report = report.section("Instructions", instruction);
+    }
+
+    report = report
+        .section("Areas", &alert.areaDesc)
+        .footer(format!("Alert {n} of {} for {place}", data.features.len()))
+        .color(severity_color(&alert.severity));
+
+    if feature.id.starts_with("https://") {
+        report = report.url(&feature.id);
+    }
+
+    Ok(report)
+}
+
+fn alert_report(title: &str, place: &Place, alert: &AlertProperties) -> Report {
+    Report::new(format!("{title} => {place}"))
+        .description(alert.headline())
+        .field("Event", &alert.event)
+        .field("Severity", &alert.severity)
+        .field("Urgency", &alert.urgency)
+        .field("Certainty", &alert.certainty)
+        .field("Onset", format_time(alert.onset))
+        .field("Expires", format_time(alert.expires))
+        .field("Areas", &alert.areaDesc)
+        .color(severity_color(&alert.severity))
+}
+
+/// Limits the alerts sent to a subscriber to a minimum severity and to events
+/// containing any of the given names (e.g., "tornado" matches Tornado Watch
+/// and Tornado Warning).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlertFilter {
+    pub severity: Option<String>,
+    pub events: Vec<String>,
+}
+
+impl AlertFilter {
+    /// Splits filters such as `--severity severe --event Tornado Warning, Flash
+    /// Flood Warning` from the end of a location query.
+    pub fn parse(args: &str) -> Result<(String, Self), Error> {
+        let args = format!(" {args}");
+        let mut parts = args.split(" --");
+        let query = parts.next().unwrap_or_default().trim().to_string();
+        let mut filter = Self::default();
+
+        for part in parts {
+            let (name, value) = part.trim().split_once(' ').unwrap_or((part.trim(), ""));
+            let value = value.trim();
+
+            if value.is_empty() {
+                return Err(Error::Invalid(format!("Provide a value for --{name}")));
+            }
+
+            match name {
+                "severity" => filter.severity = Some(parse_severity(value)?.to_string()),
+                "event" | "events" => filter.events.extend(
+                    value.split(',').map(str::trim).filter(|x| !x.is_empty()).map(String::from),
+                ),
+                _ => {
+                    return Err(Error::Invalid(format!(
+                        "Unknown filter --{name}. Use --severity or --event"
+                    )))
                 }
-                Err(e) => format!("`There was an error retrieving data: {e}`"),
             }
         }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
+
+        Ok((query, filter))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.severity.is_none() && self.events.is_empty()
+    }
+
+    pub fn matches(&self, event: &str, severity: &str) -> bool {
+        let event = event.to_lowercase();
+
+        self.severity.as_deref().is_none_or(|x| severity_rank(severity) >= severity_rank(x))
+            && (self.events.is_empty()
+                || self.events.iter().any(|x| event.contains(&x.to_lowercase())))
     }
 }
 
+/// Formats filters the way they are typed, so saved filters can be parsed back.
+impl fmt::Display for AlertFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+
+        if let Some(severity) = &self.severity {
+            parts.push(format!("--severity {severity}"));
+        }
+
+        if !self.events.is_empty() {
+            parts.push(format!("--event {}", self.events.join(", ")));
+        }
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Consecutive polls an unexpired alert must be missing from before it is
+/// reported as ended, so a poll that briefly drops it doesn't end it early.
+/// Alerts are polled every minute.
+const MISSED_POLLS: i64 = 5;
+
+/// What to do with a sent alert that is missing from the active alerts.
+#[derive(Debug, PartialEq, Eq)]
+enum Missing {
+    /// Past its expiration time, so it expired.
+    Expired,
+    /// Missing from enough consecutive polls to be considered ended.
+    Ended,
+    /// Missing from this many consecutive polls so far.
+    Missed(i64),
+}
+
+fn check_missing(alert: &db::Alert, now: DateTime<Utc>) -> Missing {
+    let expired = alert
+        .expires
+        .as_deref()
+        .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+        .is_none_or(|x| x <= now);
+
+    if expired {
+        Missing::Expired
+    } else if alert.missed + 1 >= MISSED_POLLS {
+        Missing::Ended
+    } else {
+        Missing::Missed(alert.missed + 1)
+    }
+}
+
+/// A change to an alert, along with the event and severity subscriber filters
+/// are matched against.
+pub struct AlertChange {
+    pub event: String,
+    pub severity: String,
+    pub report: Report,
+}
+
+/// Compares the active alerts for a place with those already sent for the
+/// location and returns the alerts that are new, updated, cancelled, expired or
+/// ended, recording the alerts sent. Updates and cancellations replace the
+/// alerts they reference, so each alert is only sent once. Unexpired alerts
+/// that are missing without a cancellation end after `MISSED_POLLS` polls.
+pub async fn fetch_changes(
+    pool: &SqlitePool,
+    location: &str,
+    place: &Place,
+) -> Result<Vec<AlertChange>, Error> {
+    let data = fetch_point_alerts(place.latitude, place.longitude).await?;
+    let known = db::fetch_alerts(pool, location).await?;
+    let active: HashSet<&str> = data.features.iter().map(|x| x.properties.id.as_str()).collect();
+    let referenced: HashSet<&str> = data
+        .features
+        .iter()
+        .flat_map(|x| x.properties.references.iter().map(|x| x.identifier.as_str()))
+        .collect();
+    let mut changes = Vec::new();
+
+    for alert in data.features.iter().map(|x| &x.properties) {
+        if let Some(x) = known.iter().find(|x| x.alert_id == alert.id) {
+            if x.missed > 0 {
+                db::update_alert_missed(pool, x.id, 0).await?;
+            }
+            continue;
+        }
+
+        if referenced.contains(alert.id.as_str()) {
+            continue;
+        }
+
+        let replaced: Vec<&db::Alert> = known
+            .iter()
+            .filter(|x| alert.references.iter().any(|y| y.identifier == x.alert_id))
+            .collect();
+
+        for x in &replaced {
+            db::delete_alert(pool, x.id).await?;
+        }
+
+        // Cancellations are only sent for alerts that were sent, and aren't
+        // recorded since they have nothing left to expire
+        if alert.messageType == "Cancel" {
+            if !replaced.is_empty() {
+                changes.push(AlertChange {
+                    event: alert.event.clone(),
+                    severity: alert.severity.clone(),
+                    report: alert_report("Alert Cancelled", place, alert),
+                });
+            }
+            continue;
+        }
+
+        let title = if replaced.is_empty() { "New Alert" } else { "Alert Updated" };
+        let expires = alert.expires.map(|x| x.to_rfc3339());
+
+        changes.push(AlertChange {
+            event: alert.event.clone(),
+            severity: alert.severity.clone(),
+            report: alert_report(title, place, alert),
+        });
+        db::insert_alert(
+            pool,
+            location,
+            &alert.id,
+            &alert.event,
+            &alert.severity,
+            alert.headline(),
+            expires.as_deref(),
+        )
+        .await?;
+    }
+
+    for alert in known.iter().filter(|x| {
+        !active.contains(x.alert_id.as_str()) && !referenced.contains(x.alert_id.as_str())
+    }) {
+        let title = match check_missing(alert, Utc::now()) {
+            Missing::Expired => "Alert Expired",
+            Missing::Ended => "Alert Ended",
+            Missing::Missed(missed) => {
+                db::update_alert_missed(pool, alert.id, missed).await?;
+                continue;
+            }
+        };
+
+        changes.push(AlertChange {
+            event: alert.event.clone(),
+            severity: alert.severity.clone(),
+            report: Report::new(format!("{title} => {place}"))
+                .description(&alert.headline)
+                .field("Event", &alert.event)
+                .color(Colour::LIGHT_GREY),
+        });
+        db::delete_alert(pool, alert.id).await?;
+    }
+
+    Ok(changes)
+}
+
 #[command]
 pub async fn alerts(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
-        Ok(val) => val,
-        Err(_) => String::new(),
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
     };
-    let args: Vec<&str> = args.split(' ').collect();
-
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => match utils::fetch_location(zip_code).await {
-                Ok(data) => {
-                    let data = parse_alerts(zip_code, data).await;
-                    msg.channel_id.say(&ctx.http, data).await?
-                }
-                Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-            },
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(place) => {
+                let report = parse_alerts(&providers, &place).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
         };
     }
 
     Ok(())
 }
+
+#[command]
+#[aliases("detail")]
+pub async fn alerts_detail(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (providers, pool) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Providers>().expect("Error retrieving providers").clone(),
+            data.get::<Database>().expect("Error retrieving database pool").clone(),
+        )
+    };
+    let (n, query) = args.message().split_once(' ').unwrap_or((args.message(), ""));
+    let n = match n.parse::<usize>() {
+        Ok(val) if val >= 1 => val,
+        _ => {
+            msg.channel_id
+                .say(&ctx.http, "`Provide the number of an alert listed by !alerts`")
+                .await?;
+            return Ok(());
+        }
+    };
+    let query = if query.trim().is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        query.trim().to_string()
+    };
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    match utils::resolve_location(ctx, msg, &query).await {
+        Ok(place) => match parse_alert_detail(&providers, &place, n).await {
+            Ok(report) => output::send_report(ctx, msg, &report, style).await?,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
+        },
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(expires: Option<&str>, missed: i64) -> db::Alert {
+        db::Alert {
+            id: 1,
+            location: String::from("Denver"),
+            alert_id: String::from("urn:oid:2.49.0.1.840.0.1"),
+            event: String::from("Winter Storm Warning"),
+            severity: String::from("Moderate"),
+            headline: String::from("Winter Storm Warning issued January 5"),
+            expires: expires.map(String::from),
+            missed,
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn formats_times_in_their_own_offset() {
+        let time = DateTime::parse_from_rfc3339("2024-01-06T05:00:00-07:00").ok();
+
+        assert_eq!(format_time(time), "Sat 05:00 AM (UTC-07:00)");
+        assert_eq!(format_time(None), "-");
+    }
+
+    #[test]
+    fn waits_for_missing_alerts_to_end() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 18, 0, 0).unwrap();
+        let expires = Some("2024-01-06T05:00:00-07:00");
+
+        assert_eq!(check_missing(&sent(expires, 0), now), Missing::Missed(1));
+        assert_eq!(check_missing(&sent(expires, MISSED_POLLS - 2), now), Missing::Missed(4));
+        assert_eq!(check_missing(&sent(expires, MISSED_POLLS - 1), now), Missing::Ended);
+    }
+
+    #[test]
+    fn expires_missing_alerts_past_their_time() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 6, 13, 0, 0).unwrap();
+
+        assert_eq!(
+            check_missing(&sent(Some("2024-01-06T05:00:00-07:00"), 0), now),
+            Missing::Expired
+        );
+        assert_eq!(check_missing(&sent(None, 0), now), Missing::Expired);
+    }
+}
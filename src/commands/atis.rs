@@ -3,15 +3,24 @@ use serenity::{
     model::prelude::*,
     prelude::*,
 };
+use sqlx::sqlite::SqlitePool;
 
-use crate::lib::{error::Error, utils};
+use crate::{
+    lib::{
+        atis::Atis,
+        db,
+        error::Error,
+        output::{self, Report},
+        utils,
+    },
+    Database,
+};
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct AtisResponse {
     airport: String,
     #[serde(rename = "type")]
-    _type: String,
+    kind: String,
     code: String,
     datis: String,
 }
@@ -23,49 +32,181 @@ async fn fetch_atis(station: &str) -> Result<Vec<AtisResponse>, Error> {
     match resp {
         Ok(data) => {
             let resp: Vec<AtisResponse> = data;
-            Ok(resp)
+
+            if resp.is_empty() {
+                Err(Error::NotFound("The station code provided has no D-ATIS".into()))
+            } else {
+                Ok(resp)
+            }
         }
         Err(_) => Err(Error::NotFound("The station code provided does not exist".into())),
     }
 }
 
-async fn parse_atis(station: &str) -> String {
+fn format_lines(items: &[String]) -> String {
+    if items.is_empty() {
+        String::from("-")
+    } else {
+        items.join("\n")
+    }
+}
+
+fn format_atis(data: &AtisResponse) -> Report {
+    let atis = Atis::parse(&data.kind, &data.code, &data.datis);
+    let runways = |x: &[String]| if x.is_empty() { String::from("-") } else { x.join(", ") };
+
+    Report::new(format!(
+        "{} {} ATIS => Information {} ({})",
+        data.airport.to_uppercase(),
+        atis.kind,
+        atis.code,
+        atis.time.as_deref().unwrap_or("time unknown")
+    ))
+    .field("Weather", atis.weather.as_deref().unwrap_or("-"))
+    .field("Landing", runways(&atis.landing_runways))
+    .field("Departing", runways(&atis.departing_runways))
+    .field("Approaches", format_lines(&atis.approaches))
+    .field("NOTAMs", format_lines(&atis.notams))
+    .field("Remarks", format_lines(&atis.remarks))
+}
+
+fn atis_code(data: &[AtisResponse]) -> String {
+    let codes: Vec<String> = data.iter().map(|x| x.code.to_uppercase()).collect();
+
+    codes.join("/")
+}
+
+/// Returns the current information letter(s) for a station along with the
+/// formatted ATIS, for use by the watch background task.
+pub async fn fetch_update(station: &str) -> Result<(String, String), Error> {
+    let data = fetch_atis(station).await?;
+    let atis: Vec<String> = data.iter().map(|x| format_atis(x).to_code_block()).collect();
+
+    Ok((atis_code(&data), atis.join("\n")))
+}
+
+pub async fn parse_atis(station: &str) -> Result<Vec<Report>, Error> {
+    let data = fetch_atis(station).await?;
+
+    Ok(data.iter().map(format_atis).collect())
+}
+
+#[command]
+pub async fn atis(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        let station = match utils::check_station(ctx, msg, &arg).await {
+            Ok(val) => val,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+                continue;
+            }
+        };
+
+        match parse_atis(&station).await {
+            Ok(reports) => {
+                for report in reports {
+                    output::send_report(ctx, msg, &report, style).await?;
+                }
+            }
+            Err(e) => {
+                msg.channel_id
+                    .say(&ctx.http, format!("`There was an error retrieving data: {e}`"))
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the stations a user is watching.
+pub async fn list_watches(pool: &SqlitePool, user_id: UserId) -> String {
+    match db::fetch_user_atis_watches(pool, user_id).await {
+        Ok(watches) if watches.is_empty() => String::from("`You are not watching any stations`"),
+        Ok(watches) => {
+            let stations: Vec<String> =
+                watches.iter().map(|x| format!("{} ({})", x.station, x.code)).collect();
+
+            format!("`You are watching {}`", stations.join(", "))
+        }
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+/// Starts watching a station for a user, recording its current information
+/// letter so only later changes are sent.
+pub async fn watch_station(pool: &SqlitePool, user_id: UserId, station: &str) -> String {
+    let watches = match db::fetch_user_atis_watches(pool, user_id).await {
+        Ok(val) => val,
+        Err(e) => return format!("`{e}`"),
+    };
+
+    if watches.iter().any(|x| x.station == station) {
+        return format!("`You are already watching {station}`");
+    }
+
     match fetch_atis(station).await {
-        Ok(data) => {
-            if data.len() == 1 {
-                format!(
-                    "```
-{}
-```",
-                    data[0].datis
-                )
-            } else {
-                format!(
-                    "```
-{}
-
-{}
-```",
-                    data[0].datis, data[1].datis,
-                )
+        Ok(data) => match db::insert_atis_watch(pool, user_id, station, &atis_code(&data)).await {
+            Ok(()) => format!("`You will be messaged when the ATIS for {station} changes`"),
+            Err(e) => format!("`{e}`"),
+        },
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+pub async fn unwatch_station(pool: &SqlitePool, user_id: UserId, station: &str) -> String {
+    let station = station.to_uppercase();
+
+    match db::fetch_user_atis_watches(pool, user_id).await {
+        Ok(watches) if watches.iter().any(|x| x.station == station) => {
+            match db::delete_atis_watch(pool, user_id, &station).await {
+                Ok(()) => format!("`You are no longer watching {station}`"),
+                Err(e) => format!("`{e}`"),
             }
         }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
+        Ok(_) => format!("`You are not watching {station}`"),
+        Err(e) => format!("`{e}`"),
     }
 }
 
 #[command]
-pub async fn atis(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args: Vec<String> = args.message().split(' ').map(str::to_uppercase).collect();
+#[aliases("watch")]
+pub async fn atis_watch(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
 
-    for arg in args {
-        match utils::check_station_code(&arg) {
-            Ok(()) => {
-                let data = parse_atis(&arg).await;
-                msg.channel_id.say(&ctx.http, data).await?
-            }
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
+    if args.message().is_empty() {
+        msg.channel_id.say(&ctx.http, list_watches(&pool, msg.author.id).await).await?;
+        return Ok(());
+    }
+
+    for arg in utils::split_locations(args.message()) {
+        let data = match utils::check_station(ctx, msg, &arg).await {
+            Ok(station) => watch_station(&pool, msg.author.id, &station).await,
+            Err(e) => format!("`{e}`"),
         };
+
+        msg.channel_id.say(&ctx.http, data).await?;
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases("unwatch")]
+pub async fn atis_unwatch(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+
+    for arg in args.message().split(' ') {
+        msg.channel_id.say(&ctx.http, unwatch_station(&pool, msg.author.id, arg).await).await?;
     }
 
     Ok(())
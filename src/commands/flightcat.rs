@@ -0,0 +1,140 @@
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+    utils::Colour,
+};
+
+use crate::{
+    commands::metar,
+    lib::{
+        error::Error,
+        metar::{thousands, FlightCategory},
+        output::{self, Report, Style},
+        utils,
+    },
+};
+
+fn ansi_color(category: Option<FlightCategory>) -> &'static str {
+    match category {
+        Some(FlightCategory::Vfr) => "\u{1b}[0;32m",
+        Some(FlightCategory::Mvfr) => "\u{1b}[0;34m",
+        Some(FlightCategory::Ifr) => "\u{1b}[0;31m",
+        Some(FlightCategory::Lifr) => "\u{1b}[0;35m",
+        None => "\u{1b}[0;37m",
+    }
+}
+
+/// The color for a flight category, shared by embeds and TAF graphs.
+pub fn category_color(category: Option<FlightCategory>) -> Colour {
+    match category {
+        Some(FlightCategory::Vfr) => Colour::from_rgb(0, 170, 0),
+        Some(FlightCategory::Mvfr) => Colour::from_rgb(0, 90, 255),
+        Some(FlightCategory::Ifr) => Colour::from_rgb(220, 0, 0),
+        Some(FlightCategory::Lifr) => Colour::from_rgb(200, 0, 200),
+        None => Colour::from_rgb(160, 160, 160),
+    }
+}
+
+pub struct Conditions {
+    category: Option<FlightCategory>,
+    ceiling: String,
+    visibility: String,
+    time: String,
+}
+
+async fn fetch_conditions(station: &str) -> Result<Conditions, Error> {
+    let data = metar::fetch_report(station).await?;
+    let ceiling = match data.ceiling() {
+        Some(val) => format!("{} ft", thousands(val)),
+        None => String::from("None"),
+    };
+    let visibility = match data.visibility {
+        Some(val) => {
+            let val = format!("{:.2}", val.statute_miles());
+            format!("{} SM", val.trim_end_matches('0').trim_end_matches('.'))
+        }
+        None => String::from("-"),
+    };
+    let time = data.time.map_or(String::from("-"), |x| format!("{:02}{:02}Z", x.hour, x.minute));
+
+    Ok(Conditions { category: data.flight_category(), ceiling, visibility, time })
+}
+
+fn format_line(station: &str, conditions: &Result<Conditions, Error>) -> String {
+    match conditions {
+        Ok(data) => format!(
+            "{}{:<5} {:<5}\u{1b}[0m Ceiling: {:<9} Visibility: {:<8} {}",
+            ansi_color(data.category),
+            station,
+            data.category.map_or(String::from("-"), |x| x.to_string()),
+            data.ceiling,
+            data.visibility,
+            data.time
+        ),
+        Err(e) => format!("{}{station:<5} -    \u{1b}[0m {e}", ansi_color(None)),
+    }
+}
+
+pub fn create_report(stations: &[(String, Result<Conditions, Error>)]) -> Report {
+    let mut report = Report::new("Flight Categories");
+
+    for (station, conditions) in stations {
+        let value = match conditions {
+            Ok(data) => format!(
+                "{}\nCeiling: {}\nVisibility: {}\n{}",
+                data.category.map_or(String::from("-"), |x| x.to_string()),
+                data.ceiling,
+                data.visibility,
+                data.time
+            ),
+            Err(e) => e.to_string(),
+        };
+        report = report.field(station, value);
+    }
+
+    let category =
+        stations.iter().filter_map(|(_, x)| x.as_ref().ok().and_then(|x| x.category)).max();
+
+    report.color(category_color(category))
+}
+
+/// Fetches conditions for each station code in `args`, keeping errors for
+/// unknown codes alongside the results.
+pub async fn fetch_stations(args: &str) -> Vec<(String, Result<Conditions, Error>)> {
+    let mut stations = Vec::new();
+
+    for arg in args.split_whitespace().map(str::to_uppercase) {
+        let conditions = match utils::check_station_code(&arg).await {
+            Ok(()) => fetch_conditions(&arg).await,
+            Err(e) => Err(e),
+        };
+        stations.push((arg, conditions));
+    }
+
+    stations
+}
+
+/// Colors each station by flight category in an ANSI code block.
+pub fn format_lines(stations: &[(String, Result<Conditions, Error>)]) -> String {
+    let lines: Vec<String> = stations.iter().map(|(x, y)| format_line(x, y)).collect();
+
+    format!("```ansi\n{}\n```", lines.join("\n"))
+}
+
+#[command]
+pub async fn flightcat(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let stations = fetch_stations(args.message()).await;
+
+    if stations.is_empty() {
+        msg.channel_id.say(&ctx.http, "`Provide one or more station codes`").await?;
+        return Ok(());
+    }
+
+    match utils::fetch_style(ctx, msg.guild_id).await {
+        Style::Code => output::say(ctx, msg, &format_lines(&stations)).await?,
+        style => output::send_report(ctx, msg, &create_report(&stations), style).await?,
+    }
+
+    Ok(())
+}
@@ -3,41 +3,71 @@ use serenity::{
     model::prelude::*,
     prelude::*,
 };
+use sqlx::sqlite::SqlitePool;
 
 use crate::{
     lib::{db, utils},
     Database,
 };
 
+/// Saves a location for a user who doesn't already have one.
+pub async fn save_location(pool: &SqlitePool, user_id: UserId, location: &str) -> String {
+    if let Ok(val) = db::fetch_location(pool, user_id).await {
+        return format!("`You can only have one location set. Your current location is {val}`");
+    }
+
+    match db::insert_location(pool, user_id, location).await {
+        Ok(()) => format!("`Your location has been set to {location}`"),
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+pub async fn show_location(pool: &SqlitePool, user_id: UserId) -> String {
+    match db::fetch_location(pool, user_id).await {
+        Ok(val) => format!("`Your current location is {val}`"),
+        Err(_) => String::from("`You don't have a location set`"),
+    }
+}
+
+pub async fn remove_location(pool: &SqlitePool, user_id: UserId) -> String {
+    match db::fetch_location(pool, user_id).await {
+        Ok(_) => match db::delete_location(pool, user_id).await {
+            Ok(()) => String::from("`Your location has been deleted`"),
+            Err(e) => format!("`{e}`"),
+        },
+        Err(_) => String::from("`You don't have a location set`"),
+    }
+}
+
 #[command]
 #[aliases("set", "add")]
 pub async fn location_set(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let zip_code = args.message();
+    let query = args.message();
     let pool = {
         let data = ctx.data.read().await;
         data.get::<Database>().expect("Error retrieving database pool").clone()
     };
 
-    match db::fetch_location(&pool, msg).await {
-        Ok(val) => {
-            msg.channel_id
-                .say(
-                    &ctx.http,
-                    format!("`You can only have one location set. Your current location is {val}`"),
-                )
-                .await?;
-        }
-        Err(_) => {
-            match utils::check_zip_code(zip_code) {
-                Ok(zip_code) => match db::insert_location(&pool, msg, zip_code).await {
-                    Ok(()) => msg.channel_id.say(&ctx.http, "`Your location has been set`").await?,
-                    Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-                },
-                Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-            };
-        }
+    // Check first so users with a location aren't asked to pick a place
+    if let Ok(val) = db::fetch_location(&pool, msg.author.id).await {
+        msg.channel_id
+            .say(
+                &ctx.http,
+                format!("`You can only have one location set. Your current location is {val}`"),
+            )
+            .await?;
+        return Ok(());
     }
 
+    let data = match utils::resolve_location(ctx, msg, query).await {
+        Ok(place) => {
+            save_location(&pool, msg.author.id, &utils::stored_location(query, &place)).await
+        }
+        Err(e) => format!("`{e}`"),
+    };
+
+    msg.channel_id.say(&ctx.http, data).await?;
+
     Ok(())
 }
 
@@ -49,14 +79,7 @@ pub async fn location_list(ctx: &Context, msg: &Message) -> CommandResult {
         data.get::<Database>().expect("Error retrieving database pool").clone()
     };
 
-    match db::fetch_location(&pool, msg).await {
-        Ok(val) => {
-            msg.channel_id.say(&ctx.http, format!("`Your current location is {val}`")).await?;
-        }
-        Err(_) => {
-            msg.channel_id.say(&ctx.http, "`You don't have a location set`").await?;
-        }
-    }
+    msg.channel_id.say(&ctx.http, show_location(&pool, msg.author.id).await).await?;
 
     Ok(())
 }
@@ -69,17 +92,7 @@ pub async fn location_delete(ctx: &Context, msg: &Message) -> CommandResult {
         data.get::<Database>().expect("Error retrieving database pool").clone()
     };
 
-    match db::fetch_location(&pool, msg).await {
-        Ok(_) => {
-            match db::delete_location(&pool, msg).await {
-                Ok(()) => msg.channel_id.say(&ctx.http, "`Your location has been deleted`").await?,
-                Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-            };
-        }
-        Err(_) => {
-            msg.channel_id.say(&ctx.http, "`You don't have a location set`").await?;
-        }
-    }
+    msg.channel_id.say(&ctx.http, remove_location(&pool, msg.author.id).await).await?;
 
     Ok(())
 }
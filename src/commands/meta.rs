@@ -5,9 +5,64 @@ use serenity::{
     model::prelude::*,
     prelude::*,
 };
-use tokio::fs::File;
 
-use crate::{lib::db, BotAdmin, Database, Uptime};
+use crate::{
+    lib::{db, output},
+    BotAdmin, Database, Uptime,
+};
+
+pub const HELP: &str = "```
+Bot Commands
+
+Return current weather                  !wx current <location>
+Return weather forecast                 !wx forecast <location>
+Return hourly forecast with graph       !wx hourly <location> [hours]
+Return hourly feels-like outlook        !wx feels <location> [hours]
+Return temp forecast in graph format    !wx graph <location> [--precip] [--wind]
+Return METAR report                     !metar <station code|location>
+Return decoded METAR report             !metar decode <station code|location>
+Return archived METAR reports           !metar history <station code|location> [hours]
+Return METAR trends as graph            !metar graph <station code|location> [hours]
+Return TAF report                       !taf <station code|location>
+Return decoded TAF report               !taf decode <station code|location>
+Return TAF flight categories as graph   !taf graph <station code|location>
+Return ATIS information                 !atis <station code|location>
+Watch for ATIS information changes      !atis watch <station code>
+Stop watching ATIS information          !atis unwatch <station code>
+Return flight categories                !flightcat <station codes>
+Return nearest airports                 !airports <location> [radius]
+Return current UV index                 !uv current <location>
+Return UV index forecast                !uv forecast <location>
+Return current weather alerts           !alerts <location>
+Return full text of an alert            !alerts detail <n> [location]
+Subscribe to weather alerts             !subscribe alerts <location> [--severity <level>] [--event <events>]
+Subscribe to daily UV forecast          !subscribe uv <location> [time]
+Subscribe to daily weather forecast     !subscribe forecast <location> [time]
+Return subscriptions                    !subscribe
+Remove subscriptions                    !unsubscribe <alerts|uv|forecast> [location]
+Set default location                    !location set <location>
+Return default location                 !location list
+Delete default location                 !location delete
+Set preferred units                     !units set <metric|imperial|mixed>
+Return preferred units                  !units
+Set server output (manage server)       !output set <code|embed>
+Return server output                    !output
+Return time in UTC                      !utc
+Return bot uptime                       !uptime
+Return bot logs (admin only)            !logs
+This help menu                          !help
+
+Commands are also available as slash commands (e.g., /wx current)
+```";
+
+pub fn format_uptime(start_time: DateTime<Local>, current_time: DateTime<Local>) -> String {
+    let mut formatter = timeago::Formatter::new();
+
+    formatter.num_items(3);
+    formatter.ago("");
+
+    format!("`{}`", formatter.convert_chrono(start_time, current_time))
+}
 
 #[command]
 pub async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
@@ -37,14 +92,8 @@ pub async fn uptime(ctx: &Context, msg: &Message) -> CommandResult {
             None => return Err(CommandError::from("Error retrieving uptime data")),
         }
     };
-    let mut formatter = timeago::Formatter::new();
 
-    formatter.num_items(3);
-    formatter.ago("");
-
-    let uptime = formatter.convert_chrono(start_time, current_time);
-
-    msg.channel_id.say(&ctx.http, format!("`{uptime}`")).await?;
+    msg.channel_id.say(&ctx.http, format_uptime(start_time, current_time)).await?;
 
     Ok(())
 }
@@ -72,29 +121,18 @@ pub async fn logs(ctx: &Context, msg: &Message) -> CommandResult {
 
         match db::fetch_log(&pool).await {
             Ok(logs) => {
-                let timestamp: DateTime<Utc> = Utc::now();
-                let file_name =
-                    format!("./attachments/{}_logs.csv", timestamp.format("%y_%m_%d_%H%M%S"));
-                let mut writer = WriterBuilder::new().from_path(&file_name)?;
+                let mut writer = WriterBuilder::new().from_writer(Vec::new());
 
                 for log in logs {
                     writer.serialize(log)?;
                 }
 
-                writer.flush()?;
-
-                let file = match File::open(file_name).await {
-                    Ok(f) => f,
-                    Err(e) => {
-                        msg.channel_id
-                            .say(&ctx.http, format!("`Error reading csv file: {e}`"))
-                            .await?;
-                        return Ok(());
-                    }
+                let file = AttachmentType::Bytes {
+                    data: writer.into_inner()?.into(),
+                    filename: String::from("logs.csv"),
                 };
-                let file = vec![(&file, "logs.csv")];
 
-                msg.channel_id.send_files(&ctx.http, file, |m| m.content("")).await?
+                msg.channel_id.send_files(&ctx.http, vec![file], |m| m.content("")).await?
             }
             Err(e) => {
                 msg.channel_id
@@ -113,32 +151,22 @@ pub async fn logs(ctx: &Context, msg: &Message) -> CommandResult {
 
 #[command]
 pub async fn help(ctx: &Context, msg: &Message) -> CommandResult {
-    msg.channel_id
-        .say(
-            &ctx.http,
-            "```
-Bot Commands
-
-Return current weather                  !wx current <zip code>
-Return weather forecast                 !wx forecast <zip code>
-Return temp forecast in graph format    !wx graph <zip code>
-Return METAR report                     !metar <station code>
-Return TAF report                       !taf <station code>
-Return ATIS information                 !atis <station code>
-Return current UV index                 !uv current <zip code>
-Return UV index forecast                !uv forecast <zip code>
-Return current weather alerts           !alerts <zip code>
-Set default location                    !location set <zip code>
-Return default location                 !location list
-Delete default location                 !location delete
-Return time in UTC                      !utc
-Return bot uptime                       !uptime
-Return bot logs (admin only)            !logs
-This help menu                          !help
-```"
-            .to_string(),
-        )
-        .await?;
+    output::say(ctx, msg, HELP).await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_help_into_messages_discord_accepts() {
+        let messages = output::split_message(HELP, 2000);
+
+        assert!(HELP.chars().count() > 2000);
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|x| x.chars().count() <= 2000));
+        assert!(messages.iter().all(|x| x.starts_with("```") && x.ends_with("```")));
+    }
+}
@@ -4,9 +4,24 @@ use serenity::{
     prelude::*,
 };
 
-use crate::lib::{error::Error, utils};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use plotters::{backend::BitMapBackend, coord::Shift, drawing::IntoDrawingArea, prelude::*};
+use sqlx::sqlite::SqlitePool;
 
-async fn fetch_metar(station: &str) -> Result<String, Error> {
+use crate::{
+    commands::flightcat,
+    lib::{
+        db,
+        error::Error,
+        metar::{celsius_to_fahrenheit, thousands, Altimeter, Metar, Visibility, Wind},
+        output::{self, Report},
+        units::Units,
+        utils,
+    },
+    Database,
+};
+
+pub async fn fetch_metar(station: &str) -> Result<String, Error> {
     let url = format!("https://tgftp.nws.noaa.gov/data/observations/metar/stations/{station}.TXT");
     let resp = reqwest::get(&url).await?.text().await?;
 
@@ -17,24 +32,384 @@ async fn fetch_metar(station: &str) -> Result<String, Error> {
     }
 }
 
-async fn parse_metar(station: &str) -> String {
+pub async fn fetch_report(station: &str) -> Result<Metar, Error> {
+    let data = fetch_metar(station).await?;
+
+    match data.split('\n').find(|x| x.contains(station)) {
+        Some(line) => Metar::parse(line),
+        None => Err(Error::NotFound("The station has no current METAR report".into())),
+    }
+}
+
+/// Returns the values archived for a report observed at the given time.
+fn observation(metar: &Metar, observed: String) -> db::Observation {
+    db::Observation {
+        station: metar.station.clone(),
+        observed,
+        raw: metar.raw.clone(),
+        category: metar.flight_category().map(|x| x.to_string()),
+        wind_direction: metar.wind.as_ref().and_then(|x| x.direction).map(i64::from),
+        wind_speed: metar.wind.as_ref().map(|x| x.knots(x.speed)),
+        wind_gust: metar.wind.as_ref().and_then(|x| x.gust.map(|val| x.knots(val))),
+        visibility: metar.visibility.map(|x| x.statute_miles()),
+        ceiling: metar.ceiling().map(i64::from),
+        temperature: metar.temperature.map(i64::from),
+        dewpoint: metar.dewpoint.map(i64::from),
+        altimeter: metar.altimeter.map(|x| x.hpa()),
+    }
+}
+
+/// Fetches the current report for a station and stores it in the observation
+/// archive. Reports that were already archived are ignored.
+pub async fn archive_report(pool: &SqlitePool, station: &str) -> Result<(), Error> {
+    let metar = fetch_report(station).await?;
+    let observed = match metar.time.and_then(|x| x.resolve(Utc::now())) {
+        Some(val) => val.to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => return Err(Error::Invalid("The METAR report has no observation time".into())),
+    };
+
+    db::insert_observation(pool, observation(&metar, observed)).await
+}
+
+pub async fn parse_metar(station: &str) -> String {
     match fetch_metar(station).await {
         Ok(data) => {
-            let data: Vec<&str> = data.split('\n').filter(|x| x.contains(station)).collect();
-            format!("`{}`", data[0])
+            let line = match data.split('\n').find(|x| x.contains(station)) {
+                Some(val) => val.trim(),
+                None => return format!("`No METAR report found for {station}`"),
+            };
+
+            match Metar::parse(line).ok().and_then(|x| x.flight_category()) {
+                Some(category) => format!("`[{category}] {line}`"),
+                None => format!("`{line}`"),
+            }
         }
         Err(e) => format!("`There was an error retrieving data: {e}`"),
     }
 }
 
+/// Formats a temperature in the user's units, followed by the other scale.
+fn format_temperature(val: Option<i32>, units: Units) -> String {
+    match val {
+        Some(val) if units == Units::Imperial => {
+            format!("{:.0}\u{b0}F ({val}\u{b0}C)", celsius_to_fahrenheit(f64::from(val)))
+        }
+        Some(val) => {
+            format!("{val}\u{b0}C ({:.0}\u{b0}F)", celsius_to_fahrenheit(f64::from(val)))
+        }
+        None => String::from("-"),
+    }
+}
+
+/// Formats the reported wind with its speed converted to the user's units.
+fn format_wind(wind: Option<&Wind>, units: Units) -> String {
+    match wind {
+        Some(wind) if wind.speed > 0 => {
+            let speed = units.format_knots(Some(wind.knots(wind.speed)));
+
+            match wind.gust {
+                Some(gust) => format!(
+                    "{wind} ({speed}, gusting {})",
+                    units.format_knots(Some(wind.knots(gust)))
+                ),
+                None => format!("{wind} ({speed})"),
+            }
+        }
+        Some(wind) => wind.to_string(),
+        None => String::from("-"),
+    }
+}
+
+/// Formats the reported visibility, adding the distance in the user's units
+/// when it was reported in other units.
+fn format_visibility(visibility: Option<Visibility>, units: Units) -> String {
+    match visibility {
+        Some(val @ Visibility::StatuteMiles { miles, .. }) if units == Units::Metric => {
+            format!("{val} ({})", units.format_distance(Some(miles)))
+        }
+        Some(val @ Visibility::Meters(meters)) if units != Units::Metric && meters < 9999 => {
+            format!("{val} ({})", units.format_distance(Some(val.statute_miles())))
+        }
+        Some(val) => val.to_string(),
+        None => String::from("-"),
+    }
+}
+
+/// Formats the altimeter setting in the user's units, followed by the other.
+fn format_altimeter(altimeter: Option<Altimeter>, units: Units) -> String {
+    match altimeter {
+        Some(val) if units == Units::Imperial => val.to_string(),
+        Some(val) => format!("{:.0} hPa ({:.2} inHg)", val.hpa(), val.in_hg()),
+        None => String::from("-"),
+    }
+}
+
+fn format_lines<T: ToString>(items: &[T]) -> String {
+    if items.is_empty() {
+        String::from("-")
+    } else {
+        let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+        items.join("\n")
+    }
+}
+
+pub async fn parse_metar_decoded(station: &str, units: Units) -> Result<Report, Error> {
+    let metar = fetch_report(station).await?;
+    let category = metar.flight_category();
+    let report = Report::new(format!(
+        "Decoded METAR => {} ({})",
+        metar.station,
+        metar.time.map_or(String::from("time unknown"), |x| x.to_string())
+    ))
+    .description(&metar.raw)
+    .field("Category", category.map_or(String::from("-"), |x| x.to_string()))
+    .field("Wind", format_wind(metar.wind.as_ref(), units))
+    .field("Visibility", format_visibility(metar.visibility, units))
+    .field("RVR", format_lines(&metar.rvr))
+    .field("Weather", format_lines(&metar.weather))
+    .field("Clouds", format_lines(&metar.clouds))
+    .field("Temperature", format_temperature(metar.temperature, units))
+    .field("Dew Point", format_temperature(metar.dewpoint, units))
+    .field("Altimeter", format_altimeter(metar.altimeter, units))
+    .field("Trend", metar.trend.as_deref().unwrap_or("-"))
+    .color(flightcat::category_color(category));
+
+    match &metar.remarks {
+        Some(remarks) => Ok(report.section("Remarks", remarks.decoded.join("\n"))),
+        None => Ok(report),
+    }
+}
+
+/// Formats an archived wind as direction/speed with any gust, in knots.
+fn format_archived_wind(obs: &db::Observation) -> String {
+    let direction = obs.wind_direction.map_or(String::from("VRB"), |x| format!("{x:03}"));
+
+    match (obs.wind_speed, obs.wind_gust) {
+        (Some(speed), Some(gust)) => format!("{direction}/{speed:.0}G{gust:.0}kt"),
+        (Some(speed), None) => format!("{direction}/{speed:.0}kt"),
+        _ => String::from("-"),
+    }
+}
+
+/// Formats archived reports as a table of their category, wind, visibility
+/// and ceiling followed by the report, newest first.
+fn format_history(station: &str, hours: i64, data: &[db::Observation]) -> String {
+    let mut block = format!(
+        "METAR History => {station} (last {hours} hours, {} reports)\n\n{:<6} {:<13} {:>8} {:>9}  Report\n",
+        data.len(),
+        "Cat",
+        "Wind",
+        "Vis",
+        "Ceiling"
+    );
+
+    for obs in data.iter().rev() {
+        block.push_str(&format!(
+            "{:<6} {:<13} {:>8} {:>9}  {}\n",
+            format!("[{}]", obs.category.as_deref().unwrap_or("-")),
+            format_archived_wind(obs),
+            obs.visibility.map_or(String::from("-"), |x| format!("{x:.1} SM")),
+            obs.ceiling.map_or(String::from("-"), |x| format!("{} ft", thousands(x as u32))),
+            obs.raw
+        ));
+    }
+
+    format!("```\n{}```", block)
+}
+
+/// Returns the archived reports as a single table, newest first.
+pub async fn parse_history(pool: &SqlitePool, station: &str, hours: i64) -> String {
+    let since = (Utc::now() - Duration::hours(hours)).to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    match db::fetch_observations(pool, station, &since).await {
+        Ok(data) if data.is_empty() => {
+            format!("`There are no archived reports for {station} in the last {hours} hours`")
+        }
+        Ok(data) => format_history(station, hours, &data),
+        Err(e) => format!("`There was an error retrieving data: {e}`"),
+    }
+}
+
+/// A labeled series drawn either as a line or as individual points.
+type Series = (&'static str, RGBColor, bool, Vec<(f64, f64)>);
+
+fn draw_observation_panel(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    start: DateTime<Utc>,
+    total: f64,
+    y_desc: &str,
+    series: &[Series],
+) -> Result<(), Error> {
+    let values: Vec<f64> = series.iter().flat_map(|(_, _, _, x)| x.iter().map(|x| x.1)).collect();
+    let (min, max) =
+        match (values.iter().copied().reduce(f64::min), values.iter().copied().reduce(f64::max)) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Ok(()),
+        };
+    let pad = if max > min { (max - min) * 0.1 } else { 1.0 };
+
+    let mut chart = ChartBuilder::on(area)
+        .margin(10)
+        .margin_right(30)
+        .set_label_area_size(LabelAreaPosition::Left, 80)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .build_cartesian_2d(0.0..total, (min - pad)..(max + pad))?;
+
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|x: &f64| {
+            (start + Duration::minutes((x * 60.0) as i64)).format("%d/%H%MZ").to_string()
+        })
+        .y_desc(y_desc)
+        .label_style(("sans-serif", 18))
+        .draw()?;
+
+    for (label, color, line, data) in series {
+        let color = *color;
+
+        if *line {
+            chart.draw_series(
+                LineSeries::new(data.iter().copied(), color.stroke_width(2)).point_size(3),
+            )?
+        } else {
+            chart.draw_series(data.iter().map(|x| Circle::new(*x, 4, color.filled())))?
+        }
+        .label(*label)
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
+    }
+
+    chart
+        .configure_series_labels()
+        .label_font(("sans-serif", 16))
+        .position(SeriesLabelPosition::UpperLeft)
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.8))
+        .draw()?;
+
+    Ok(())
+}
+
+fn create_observation_graph(
+    station: &str,
+    hours: i64,
+    data: &[db::Observation],
+    units: Units,
+) -> Result<Vec<u8>, Error> {
+    let times: Vec<DateTime<Utc>> = data
+        .iter()
+        .filter_map(|x| DateTime::parse_from_rfc3339(&x.observed).ok())
+        .map(|x| x.with_timezone(&Utc))
+        .collect();
+    let (start, end) = match (times.first(), times.last()) {
+        (Some(start), Some(end)) if start < end => (*start, *end),
+        _ => {
+            return Err(Error::NotFound(format!(
+                "There are not enough archived reports for {station} to graph"
+            )))
+        }
+    };
+    let total = (end - start).num_minutes() as f64 / 60.0;
+    let points = |f: &dyn Fn(&db::Observation) -> Option<f64>| -> Vec<(f64, f64)> {
+        data.iter()
+            .zip(&times)
+            .filter_map(|(x, time)| Some(((*time - start).num_minutes() as f64 / 60.0, f(x)?)))
+            .collect()
+    };
+
+    let size = (1024, 1024);
+    let mut buffer = vec![0; size.0 as usize * size.1 as usize * 3];
+
+    {
+        let root_area = BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area();
+
+        root_area.fill(&WHITE)?;
+
+        let root_area = root_area.titled(
+            &format!("Observations for {station} (last {hours} hours)"),
+            ("sans-serif", 36),
+        )?;
+        let panels = root_area.split_evenly((3, 1));
+
+        draw_observation_panel(
+            &panels[0],
+            start,
+            total,
+            &format!("Temperature ({})", units.temperature_unit()),
+            &[
+                (
+                    "Temperature",
+                    RED,
+                    true,
+                    points(&|x| {
+                        x.temperature.map(|x| units.temperature(celsius_to_fahrenheit(x as f64)))
+                    }),
+                ),
+                (
+                    "Dew Point",
+                    GREEN,
+                    true,
+                    points(&|x| {
+                        x.dewpoint.map(|x| units.temperature(celsius_to_fahrenheit(x as f64)))
+                    }),
+                ),
+            ],
+        )?;
+        draw_observation_panel(
+            &panels[1],
+            start,
+            total,
+            &format!("Pressure ({})", units.pressure_unit()),
+            &[(
+                "Altimeter",
+                BLUE,
+                true,
+                points(&|x| x.altimeter.map(|x| units.pressure_from_hpa(x))),
+            )],
+        )?;
+        draw_observation_panel(
+            &panels[2],
+            start,
+            total,
+            &format!("Wind ({})", units.speed_unit()),
+            &[
+                ("Wind", BLACK, true, points(&|x| x.wind_speed.map(|x| units.speed_from_knots(x)))),
+                (
+                    "Gusts",
+                    RGBColor(255, 140, 0),
+                    false,
+                    points(&|x| x.wind_gust.map(|x| units.speed_from_knots(x))),
+                ),
+            ],
+        )?;
+
+        root_area.present()?;
+    }
+
+    utils::encode_png(buffer, size)
+}
+
+/// Draws the archived observations for a station over the last `hours`.
+pub async fn observation_graph(
+    pool: &SqlitePool,
+    station: &str,
+    hours: i64,
+    units: Units,
+) -> Result<Vec<u8>, Error> {
+    let since = (Utc::now() - Duration::hours(hours)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let data = db::fetch_observations(pool, station, &since).await?;
+
+    create_observation_graph(station, hours, &data, units)
+}
+
 #[command]
 pub async fn metar(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args: Vec<String> = args.message().split(' ').map(str::to_uppercase).collect();
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
 
-    for arg in args {
-        match utils::check_station_code(&arg) {
-            Ok(()) => {
-                let data = parse_metar(&arg).await;
+    for arg in utils::split_locations(&args) {
+        match utils::check_station(ctx, msg, &arg).await {
+            Ok(station) => {
+                let data = parse_metar(&station).await;
                 msg.channel_id.say(&ctx.http, data).await?
             }
             Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
@@ -43,3 +418,130 @@ pub async fn metar(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
 
     Ok(())
 }
+
+#[command]
+#[aliases("decode")]
+pub async fn metar_decode(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::check_station(ctx, msg, &arg).await {
+            Ok(station) => {
+                let report = parse_metar_decoded(&station, units).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
+        };
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases("history")]
+pub async fn metar_history(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+    let (station, hours) = utils::split_count(args.message());
+    let station = if station.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        station
+    };
+    let hours = match hours {
+        Some(val) => match val.parse::<i64>() {
+            Ok(val) if (1..=168).contains(&val) => val,
+            _ => {
+                msg.channel_id.say(&ctx.http, "`Hours must be between 1 and 168`").await?;
+                return Ok(());
+            }
+        },
+        None => 24,
+    };
+
+    match utils::check_station(ctx, msg, &station).await {
+        Ok(station) => {
+            output::say(ctx, msg, &parse_history(&pool, &station, hours).await).await?;
+        }
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+#[aliases("graph")]
+pub async fn metar_graph(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+    let (station, hours) = utils::split_count(args.message());
+    let station = if station.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        station
+    };
+    let hours = match hours {
+        Some(val) => match val.parse::<i64>() {
+            Ok(val) if (1..=720).contains(&val) => val,
+            _ => {
+                msg.channel_id.say(&ctx.http, "`Hours must be between 1 and 720`").await?;
+                return Ok(());
+            }
+        },
+        None => 24,
+    };
+    let station = match utils::check_station(ctx, msg, &station).await {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
+    };
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+    let image = match observation_graph(&pool, &station, hours, units).await {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
+    };
+    let file =
+        AttachmentType::Bytes { data: image.into(), filename: String::from("metar_graph.png") };
+
+    msg.channel_id.send_files(&ctx.http, vec![file], |m| m.content("")).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archived(observed: &str, raw: &str) -> db::Observation {
+        observation(&Metar::parse(raw).unwrap(), observed.to_string())
+    }
+
+    #[test]
+    fn shows_archived_visibility_and_ceiling() {
+        let data = [
+            archived("2024-05-12T17:56:00Z", "KSFO 121756Z 29012G20KT 10SM BKN025 16/09 A3001"),
+            archived("2024-05-12T18:56:00Z", "KSFO 121856Z VRB03KT 1 1/2SM BR OVC008 14/12 A3002"),
+        ];
+        let history = format_history("KSFO", 6, &data);
+        let lines: Vec<&str> = history.lines().collect();
+
+        assert_eq!(lines[1], "METAR History => KSFO (last 6 hours, 2 reports)");
+        assert!(lines[4].starts_with("[IFR]  VRB/3kt         1.5 SM    800 ft  KSFO 121856Z"));
+        assert!(lines[5].starts_with("[MVFR] 290/12G20kt    10.0 SM  2,500 ft  KSFO 121756Z"));
+    }
+}
@@ -0,0 +1,50 @@
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+};
+use sqlx::sqlite::SqlitePool;
+
+use crate::{
+    lib::{db, output::Style, utils},
+    Database,
+};
+
+#[command]
+#[only_in(guilds)]
+pub async fn output(ctx: &Context, msg: &Message) -> CommandResult {
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    msg.channel_id.say(&ctx.http, format!("`Output for this server is set to {style}`")).await?;
+
+    Ok(())
+}
+
+pub async fn save_output(pool: &SqlitePool, guild_id: GuildId, value: &str) -> String {
+    match value.parse::<Style>() {
+        Ok(style) => match db::insert_output(pool, guild_id, &style.to_string()).await {
+            Ok(()) => format!("`Output for this server has been set to {style}`"),
+            Err(e) => format!("`{e}`"),
+        },
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+#[command]
+#[aliases("set")]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+pub async fn output_set(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+    let guild_id = match msg.guild_id {
+        Some(val) => val,
+        None => return Ok(()),
+    };
+
+    msg.channel_id.say(&ctx.http, save_output(&pool, guild_id, args.message()).await).await?;
+
+    Ok(())
+}
@@ -0,0 +1,804 @@
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    model::{
+        application::{
+            command::{Command, CommandOptionType},
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                autocomplete::AutocompleteInteraction,
+                InteractionResponseType,
+            },
+        },
+        prelude::*,
+    },
+    prelude::*,
+};
+use sqlx::sqlite::SqlitePool;
+use std::sync::Arc;
+
+use crate::{
+    commands::{
+        airports,
+        alerts::{self, AlertFilter},
+        atis, flightcat, location, meta, metar, output,
+        subscribe::{self, Kind},
+        taf, units, uv, wx,
+    },
+    lib::{
+        airports::search,
+        db,
+        error::Error,
+        output::{followup, followup_report, Style},
+        providers::{Place, Registry},
+        utils,
+    },
+    Database, Providers, Uptime,
+};
+
+/// Discord shows at most 25 autocomplete choices, each at most 100 characters.
+const CHOICE_LIMIT: usize = 25;
+const CHOICE_LENGTH: usize = 100;
+
+fn option(
+    kind: CommandOptionType,
+    name: &str,
+    description: &str,
+    required: bool,
+) -> CreateApplicationCommandOption {
+    let mut option = CreateApplicationCommandOption::default();
+
+    option.kind(kind).name(name).description(description).required(required);
+    option
+}
+
+fn location_option() -> CreateApplicationCommandOption {
+    let mut option = option(
+        CommandOptionType::String,
+        "location",
+        "Place name, zip code or coordinates (defaults to your location)",
+        false,
+    );
+
+    option.set_autocomplete(true);
+    option
+}
+
+fn station_option(required: bool) -> CreateApplicationCommandOption {
+    let mut option = option(
+        CommandOptionType::String,
+        "station",
+        "Station code or location (defaults to your location)",
+        required,
+    );
+
+    option.set_autocomplete(true);
+    option
+}
+
+fn hours_option(max: i64) -> CreateApplicationCommandOption {
+    let mut option = option(CommandOptionType::Integer, "hours", "Number of hours", false);
+
+    option.min_int_value(1).max_int_value(max);
+    option
+}
+
+fn subcommand(
+    name: &str,
+    description: &str,
+    options: Vec<CreateApplicationCommandOption>,
+) -> CreateApplicationCommandOption {
+    let mut subcommand = CreateApplicationCommandOption::default();
+
+    subcommand.kind(CommandOptionType::SubCommand).name(name).description(description);
+
+    for option in options {
+        subcommand.add_sub_option(option);
+    }
+
+    subcommand
+}
+
+fn command(
+    name: &str,
+    description: &str,
+    options: Vec<CreateApplicationCommandOption>,
+) -> CreateApplicationCommand {
+    let mut command = CreateApplicationCommand::default();
+
+    command.name(name).description(description).set_options(options);
+    command
+}
+
+fn commands() -> Vec<CreateApplicationCommand> {
+    let mut units = option(CommandOptionType::String, "units", "Units to use", true);
+    let mut style = option(CommandOptionType::String, "output", "Output to use", true);
+    let mut radius =
+        option(CommandOptionType::Integer, "radius", "Search radius in miles (default 50)", false);
+    let stations =
+        option(CommandOptionType::String, "stations", "Station codes separated by spaces", true);
+    let mut location =
+        option(CommandOptionType::String, "location", "Place name, zip code or coordinates", true);
+
+    units
+        .add_string_choice("imperial", "imperial")
+        .add_string_choice("metric", "metric")
+        .add_string_choice("mixed", "mixed");
+    style.add_string_choice("code", "code").add_string_choice("embed", "embed");
+    radius.min_int_value(1).max_int_value(500);
+    location.set_autocomplete(true);
+
+    let time = || {
+        option(CommandOptionType::String, "time", "Local time to send it (default 08:30)", false)
+    };
+    let mut kind = option(CommandOptionType::String, "kind", "Subscription to remove", true);
+    let mut subscription = option(
+        CommandOptionType::String,
+        "location",
+        "Subscribed location (defaults to all of them)",
+        false,
+    );
+
+    kind.add_string_choice("alerts", "alerts")
+        .add_string_choice("uv", "uv")
+        .add_string_choice("forecast", "forecast");
+    subscription.set_autocomplete(true);
+
+    let mut severity = option(
+        CommandOptionType::String,
+        "severity",
+        "Only send alerts this severe or worse",
+        false,
+    );
+    let events = option(
+        CommandOptionType::String,
+        "events",
+        "Only send these events, separated by commas (e.g., Tornado Warning)",
+        false,
+    );
+
+    let mut detail =
+        option(CommandOptionType::Integer, "detail", "Number of an alert to show in full", false);
+
+    detail.min_int_value(1);
+
+    for x in ["Minor", "Moderate", "Severe", "Extreme"] {
+        severity.add_string_choice(x, x);
+    }
+
+    vec![
+        command(
+            "wx",
+            "Weather conditions and forecasts",
+            vec![
+                subcommand("current", "Return current weather", vec![location_option()]),
+                subcommand("forecast", "Return weather forecast", vec![location_option()]),
+                subcommand(
+                    "hourly",
+                    "Return hourly forecast with graph",
+                    vec![location_option(), hours_option(48)],
+                ),
+                subcommand(
+                    "feels",
+                    "Return hourly feels-like outlook",
+                    vec![location_option(), hours_option(48)],
+                ),
+                subcommand(
+                    "graph",
+                    "Return temp forecast in graph format",
+                    vec![
+                        location_option(),
+                        option(CommandOptionType::Boolean, "precip", "Show precipitation", false),
+                        option(CommandOptionType::Boolean, "wind", "Show wind", false),
+                    ],
+                ),
+            ],
+        ),
+        command(
+            "metar",
+            "METAR reports",
+            vec![
+                subcommand("report", "Return METAR report", vec![station_option(false)]),
+                subcommand("decode", "Return decoded METAR report", vec![station_option(false)]),
+                subcommand(
+                    "history",
+                    "Return archived METAR reports",
+                    vec![station_option(false), hours_option(168)],
+                ),
+                subcommand(
+                    "graph",
+                    "Return METAR trends as graph",
+                    vec![station_option(false), hours_option(720)],
+                ),
+            ],
+        ),
+        command(
+            "taf",
+            "TAF reports",
+            vec![
+                subcommand("report", "Return TAF report", vec![station_option(false)]),
+                subcommand("decode", "Return decoded TAF report", vec![station_option(false)]),
+                subcommand(
+                    "graph",
+                    "Return TAF flight categories as graph",
+                    vec![station_option(false)],
+                ),
+            ],
+        ),
+        command(
+            "atis",
+            "ATIS information",
+            vec![
+                subcommand("report", "Return ATIS information", vec![station_option(false)]),
+                subcommand(
+                    "watch",
+                    "Watch for ATIS information changes",
+                    vec![station_option(false)],
+                ),
+                subcommand("unwatch", "Stop watching ATIS information", vec![station_option(true)]),
+            ],
+        ),
+        command("flightcat", "Return flight categories", vec![stations]),
+        command("airports", "Return nearest airports", vec![location_option(), radius]),
+        command(
+            "uv",
+            "UV index",
+            vec![
+                subcommand("current", "Return current UV index", vec![location_option()]),
+                subcommand("forecast", "Return UV index forecast", vec![location_option()]),
+            ],
+        ),
+        command("alerts", "Return current weather alerts", vec![location_option(), detail]),
+        command(
+            "location",
+            "Default location",
+            vec![
+                subcommand("set", "Set default location", vec![location]),
+                subcommand("list", "Return default location", vec![]),
+                subcommand("delete", "Delete default location", vec![]),
+            ],
+        ),
+        command(
+            "units",
+            "Preferred units",
+            vec![
+                subcommand("show", "Return preferred units", vec![]),
+                subcommand("set", "Set preferred units", vec![units]),
+            ],
+        ),
+        command(
+            "output",
+            "Server output",
+            vec![
+                subcommand("show", "Return server output", vec![]),
+                subcommand("set", "Set server output (manage server)", vec![style]),
+            ],
+        ),
+        command(
+            "subscribe",
+            "Subscriptions sent by direct message",
+            vec![
+                subcommand(
+                    "alerts",
+                    "Subscribe to weather alerts",
+                    vec![location_option(), severity, events],
+                ),
+                subcommand(
+                    "uv",
+                    "Subscribe to a daily UV forecast",
+                    vec![location_option(), time()],
+                ),
+                subcommand(
+                    "forecast",
+                    "Subscribe to a daily weather forecast",
+                    vec![location_option(), time()],
+                ),
+                subcommand("list", "Return subscriptions", vec![]),
+            ],
+        ),
+        command("unsubscribe", "Remove subscriptions", vec![kind, subscription]),
+        command("utc", "Return time in UTC", vec![]),
+        command("uptime", "Return bot uptime", vec![]),
+        command("ping", "Check that the bot is responding", vec![]),
+        command("help", "This help menu", vec![]),
+    ]
+}
+
+/// Replaces the bot's global slash commands with the current set.
+pub async fn register(ctx: &Context) -> serenity::Result<()> {
+    Command::set_global_application_commands(&ctx.http, |c| c.set_application_commands(commands()))
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the subcommand name and its options, or an empty name and the
+/// command's own options for commands without subcommands.
+fn split_subcommand(options: &[CommandDataOption]) -> (&str, &[CommandDataOption]) {
+    match options.first() {
+        Some(val) if val.kind == CommandOptionType::SubCommand => (&val.name, &val.options),
+        _ => ("", options),
+    }
+}
+
+fn string_option(options: &[CommandDataOption], name: &str) -> Option<String> {
+    options
+        .iter()
+        .find(|x| x.name == name)
+        .and_then(|x| x.value.as_ref())
+        .and_then(|x| x.as_str())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+fn int_option(options: &[CommandDataOption], name: &str) -> Option<i64> {
+    options.iter().find(|x| x.name == name).and_then(|x| x.value.as_ref()).and_then(|x| x.as_i64())
+}
+
+fn bool_option(options: &[CommandDataOption], name: &str) -> bool {
+    options
+        .iter()
+        .find(|x| x.name == name)
+        .and_then(|x| x.value.as_ref())
+        .and_then(|x| x.as_bool())
+        .unwrap_or_default()
+}
+
+fn shared_data(data: &TypeMap) -> (Arc<Registry>, SqlitePool) {
+    (
+        data.get::<Providers>().expect("Error retrieving providers").clone(),
+        data.get::<Database>().expect("Error retrieving database pool").clone(),
+    )
+}
+
+/// Returns the option's value, or the user's saved location when it was left
+/// out.
+async fn location_or_saved(
+    pool: &SqlitePool,
+    user_id: UserId,
+    options: &[CommandDataOption],
+    name: &str,
+) -> Result<String, Error> {
+    match string_option(options, name) {
+        Some(val) => Ok(val),
+        None => db::fetch_location(pool, user_id).await,
+    }
+}
+
+async fn find_place(
+    providers: &Registry,
+    pool: &SqlitePool,
+    user_id: UserId,
+    options: &[CommandDataOption],
+) -> Result<Place, Error> {
+    let query = location_or_saved(pool, user_id, options, "location").await?;
+
+    providers.locate(&query).await
+}
+
+async fn find_station(
+    providers: &Registry,
+    pool: &SqlitePool,
+    user_id: UserId,
+    options: &[CommandDataOption],
+) -> Result<String, Error> {
+    let query = location_or_saved(pool, user_id, options, "station").await?;
+
+    utils::find_station(providers, &query).await
+}
+
+async fn followup_image(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    image: Result<Vec<u8>, Error>,
+    filename: &str,
+) -> serenity::Result<()> {
+    match image {
+        Ok(image) => {
+            let file = AttachmentType::Bytes { data: image.into(), filename: filename.to_string() };
+
+            command.create_followup_message(&ctx.http, |f| f.add_file(file)).await?;
+            Ok(())
+        }
+        Err(e) => followup(ctx, command, &format!("`{e}`")).await,
+    }
+}
+
+/// Answers a slash command. Responses are deferred first since most commands
+/// call out to a weather service.
+pub async fn handle_command(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> serenity::Result<()> {
+    command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await?;
+
+    let (providers, pool) = shared_data(&*ctx.data.read().await);
+    let (name, options) = split_subcommand(&command.data.options);
+    let user_id = command.user.id;
+    let units = utils::fetch_units(ctx, user_id).await;
+    let style = utils::fetch_style(ctx, command.guild_id).await;
+
+    match (command.data.name.as_str(), name) {
+        ("wx" | "uv" | "alerts" | "airports", _) => {
+            let place = match find_place(&providers, &pool, user_id, options).await {
+                Ok(val) => val,
+                Err(e) => return followup(ctx, command, &format!("`{e}`")).await,
+            };
+
+            match (command.data.name.as_str(), name) {
+                ("wx", "current") => {
+                    let report = wx::parse_current(&providers, &place, units).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("wx", "forecast") => {
+                    let report = wx::parse_forecast(&providers, &place, units).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("wx", "hourly" | "feels") => {
+                    let default = if name == "hourly" { 24 } else { 12 };
+                    let hours = int_option(options, "hours").map_or(default, |x| x as usize);
+                    let data = match providers.fetch_hourly(place.latitude, place.longitude).await {
+                        Ok(mut val) => {
+                            val.truncate(hours);
+                            val
+                        }
+                        Err(e) => {
+                            let text = format!("`There was an error retrieving data: {e}`");
+                            return followup(ctx, command, &text).await;
+                        }
+                    };
+
+                    if name == "feels" {
+                        return followup(ctx, command, &wx::parse_feels(&place, &data, units))
+                            .await;
+                    }
+
+                    followup(ctx, command, &wx::parse_hourly(&place, &data, units)).await?;
+
+                    let image = wx::create_hourly_graph(&place, &data, units);
+                    followup_image(ctx, command, image, "hourly_graph.png").await
+                }
+                ("wx", _) => {
+                    let options = wx::GraphOptions {
+                        precip: bool_option(options, "precip"),
+                        wind: bool_option(options, "wind"),
+                    };
+                    let image = wx::forecast_graph(&providers, &place, &options, units).await;
+
+                    followup_image(ctx, command, image, "forecast_graph.png").await
+                }
+                ("uv", "current") => {
+                    let report = uv::parse_current(&providers, &place).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("uv", _) => {
+                    let report = uv::parse_forecast(&providers, &place).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("alerts", _) => {
+                    let report = match int_option(options, "detail") {
+                        Some(n) => alerts::parse_alert_detail(&providers, &place, n as usize).await,
+                        None => alerts::parse_alerts(&providers, &place).await,
+                    };
+                    followup_report(ctx, command, report, style).await
+                }
+                _ => {
+                    let radius = int_option(options, "radius").unwrap_or(50) as f64;
+                    followup(ctx, command, &airports::parse_airports(&place, radius)).await
+                }
+            }
+        }
+        ("metar" | "taf", _) | ("atis", "report" | "watch") => {
+            let station = match find_station(&providers, &pool, user_id, options).await {
+                Ok(val) => val,
+                Err(e) => return followup(ctx, command, &format!("`{e}`")).await,
+            };
+
+            match (command.data.name.as_str(), name) {
+                ("metar", "report") => {
+                    followup(ctx, command, &metar::parse_metar(&station).await).await
+                }
+                ("metar", "decode") => {
+                    let report = metar::parse_metar_decoded(&station, units).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("metar", "history") => {
+                    let hours = int_option(options, "hours").unwrap_or(24);
+                    let data = metar::parse_history(&pool, &station, hours).await;
+
+                    followup(ctx, command, &data).await
+                }
+                ("metar", _) => {
+                    let hours = int_option(options, "hours").unwrap_or(24);
+                    let image = metar::observation_graph(&pool, &station, hours, units).await;
+
+                    followup_image(ctx, command, image, "metar_graph.png").await
+                }
+                ("taf", "report") => followup(ctx, command, &taf::parse_taf(&station).await).await,
+                ("taf", "decode") => {
+                    let report = taf::parse_taf_decoded(&station).await;
+                    followup_report(ctx, command, report, style).await
+                }
+                ("taf", _) => {
+                    let image = taf::forecast_graph(&station).await;
+                    followup_image(ctx, command, image, "taf_graph.png").await
+                }
+                ("atis", "report") => match atis::parse_atis(&station).await {
+                    Ok(reports) => {
+                        for report in reports {
+                            followup_report(ctx, command, Ok(report), style).await?;
+                        }
+                        Ok(())
+                    }
+                    Err(e) => followup_report(ctx, command, Err(e), style).await,
+                },
+                _ => {
+                    followup(ctx, command, &atis::watch_station(&pool, user_id, &station).await)
+                        .await
+                }
+            }
+        }
+        ("atis", _) => {
+            let station = string_option(options, "station").unwrap_or_default();
+            followup(ctx, command, &atis::unwatch_station(&pool, user_id, &station).await).await
+        }
+        ("flightcat", _) => {
+            let stations = string_option(options, "stations").unwrap_or_default();
+            let stations = flightcat::fetch_stations(&stations).await;
+
+            match style {
+                Style::Code => followup(ctx, command, &flightcat::format_lines(&stations)).await,
+                style => {
+                    let report = flightcat::create_report(&stations);
+                    followup_report(ctx, command, Ok(report), style).await
+                }
+            }
+        }
+        ("location", "set") => {
+            let query = string_option(options, "location").unwrap_or_default();
+            let data = match providers.locate(&query).await {
+                Ok(place) => {
+                    let location = utils::stored_location(&query, &place);
+                    location::save_location(&pool, user_id, &location).await
+                }
+                Err(e) => format!("`{e}`"),
+            };
+
+            followup(ctx, command, &data).await
+        }
+        ("location", "list") => {
+            followup(ctx, command, &location::show_location(&pool, user_id).await).await
+        }
+        ("location", _) => {
+            followup(ctx, command, &location::remove_location(&pool, user_id).await).await
+        }
+        ("units", "set") => {
+            let value = string_option(options, "units").unwrap_or_default();
+            followup(ctx, command, &units::save_units(&pool, user_id, &value).await).await
+        }
+        ("units", _) => followup(ctx, command, &format!("`Your units are set to {units}`")).await,
+        ("output", _) if command.guild_id.is_none() => {
+            followup(ctx, command, "`This command can only be used in servers`").await
+        }
+        ("output", "set") => {
+            let allowed = command
+                .member
+                .as_ref()
+                .and_then(|x| x.permissions)
+                .is_some_and(|x| x.manage_guild());
+            let data = match command.guild_id {
+                Some(guild_id) if allowed => {
+                    let value = string_option(options, "output").unwrap_or_default();
+                    output::save_output(&pool, guild_id, &value).await
+                }
+                _ => String::from("`You need the Manage Server permission to change output`"),
+            };
+
+            followup(ctx, command, &data).await
+        }
+        ("output", _) => {
+            followup(ctx, command, &format!("`Output for this server is set to {style}`")).await
+        }
+        ("utc", _) => {
+            let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S");
+            followup(ctx, command, &format!("`{now}`")).await
+        }
+        ("uptime", _) => {
+            let start_time = {
+                let data = ctx.data.read().await;
+                data.get::<Uptime>().copied()
+            };
+            let data = match start_time {
+                Some(val) => meta::format_uptime(val, chrono::Local::now()),
+                None => String::from("`Error retrieving uptime data`"),
+            };
+
+            followup(ctx, command, &data).await
+        }
+        ("subscribe", "list") => {
+            followup(ctx, command, &subscribe::list_subscriptions(&pool, user_id).await).await
+        }
+        ("subscribe", _) => {
+            let kind = name.parse::<Kind>();
+            let time =
+                string_option(options, "time").map(|x| subscribe::parse_time(&x)).transpose();
+            // Filters are checked the same way as typed prefix filters
+            let filter: Vec<String> = [
+                string_option(options, "severity").map(|x| format!("--severity {x}")),
+                string_option(options, "events").map(|x| format!("--event {x}")),
+            ]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+            let filter = AlertFilter::parse(&filter.join(" ")).map(|(_, filter)| filter);
+            let query = location_or_saved(&pool, user_id, options, "location").await;
+            let data = match (kind, time, filter, query) {
+                (Ok(kind), Ok(time), Ok(filter), Ok(query)) => {
+                    match providers.locate(&query).await {
+                        Ok(place) => {
+                            let location = utils::stored_location(&query, &place);
+                            let offset = subscribe::local_offset(&providers, kind, &place).await;
+                            subscribe::save_subscription(
+                                &pool,
+                                user_id,
+                                kind,
+                                &location,
+                                (time, offset),
+                                &filter,
+                            )
+                            .await
+                        }
+                        Err(e) => format!("`{e}`"),
+                    }
+                }
+                (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                    format!("`{e}`")
+                }
+            };
+
+            followup(ctx, command, &data).await
+        }
+        ("unsubscribe", _) => {
+            let location = string_option(options, "location");
+            let data = match string_option(options, "kind").unwrap_or_default().parse::<Kind>() {
+                Ok(kind) => {
+                    subscribe::remove_subscriptions(
+                        &pool,
+                        &providers,
+                        user_id,
+                        kind,
+                        location.as_deref(),
+                    )
+                    .await
+                }
+                Err(e) => format!("`{e}`"),
+            };
+
+            followup(ctx, command, &data).await
+        }
+        ("help", _) => followup(ctx, command, meta::HELP).await,
+        _ => followup(ctx, command, "`Pong!`").await,
+    }
+}
+
+/// Suggests the user's saved location followed by geocoder matches.
+async fn location_choices(
+    providers: &Registry,
+    pool: &SqlitePool,
+    user_id: UserId,
+    query: &str,
+) -> Vec<(String, String)> {
+    let mut choices = Vec::new();
+
+    if let Ok(saved) = db::fetch_location(pool, user_id).await {
+        if saved.to_lowercase().contains(&query.to_lowercase()) {
+            choices.push((format!("{saved} (saved)"), saved));
+        }
+    }
+
+    if query.chars().count() >= 3 {
+        for place in providers.geocode(query).await.unwrap_or_default() {
+            let name = if place.country.is_empty() {
+                place.to_string()
+            } else {
+                format!("{place}, {}", place.country)
+            };
+
+            choices.push((name, place.to_string()));
+        }
+    }
+
+    choices
+}
+
+/// Suggests the user's saved location followed by stations matching the code
+/// or name typed so far.
+async fn station_choices(pool: &SqlitePool, user_id: UserId, query: &str) -> Vec<(String, String)> {
+    let mut choices = Vec::new();
+
+    if let Ok(saved) = db::fetch_location(pool, user_id).await {
+        if saved.to_lowercase().contains(&query.to_lowercase()) {
+            choices.push((format!("{saved} (saved)"), saved));
+        }
+    }
+
+    for airport in search(query, CHOICE_LIMIT) {
+        choices.push((format!("{} - {}", airport.ident, airport.name), airport.ident.clone()));
+    }
+
+    choices
+}
+
+async fn watch_choices(pool: &SqlitePool, user_id: UserId, query: &str) -> Vec<(String, String)> {
+    let query = query.to_uppercase();
+
+    db::fetch_user_atis_watches(pool, user_id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|x| x.station.starts_with(&query))
+        .map(|x| (format!("{} ({})", x.station, x.code), x.station))
+        .collect()
+}
+
+/// Suggests the user's subscribed locations, limited to the chosen kind.
+async fn subscription_choices(
+    pool: &SqlitePool,
+    user_id: UserId,
+    kind: Option<String>,
+    query: &str,
+) -> Vec<(String, String)> {
+    let query = query.to_lowercase();
+
+    db::fetch_user_subscriptions(pool, user_id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|x| kind.as_ref().is_none_or(|y| &x.kind == y))
+        .filter(|x| x.location.to_lowercase().contains(&query))
+        .map(|x| (format!("{} ({})", x.location, x.kind), x.location))
+        .collect()
+}
+
+/// Suggests values for the option being typed in a slash command.
+pub async fn handle_autocomplete(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+) -> serenity::Result<()> {
+    let (providers, pool) = shared_data(&*ctx.data.read().await);
+    let (name, options) = split_subcommand(&autocomplete.data.options);
+    let user_id = autocomplete.user.id;
+    let focused = match options.iter().find(|x| x.focused) {
+        Some(val) => val,
+        None => return Ok(()),
+    };
+    let query = focused.value.as_ref().and_then(|x| x.as_str()).unwrap_or_default().trim();
+    let choices = match (autocomplete.data.name.as_str(), name, focused.name.as_str()) {
+        ("atis", "unwatch", _) => watch_choices(&pool, user_id, query).await,
+        ("unsubscribe", _, _) => {
+            let kind = string_option(options, "kind");
+            subscription_choices(&pool, user_id, kind, query).await
+        }
+        (_, _, "station") => station_choices(&pool, user_id, query).await,
+        (_, _, "location") => location_choices(&providers, &pool, user_id, query).await,
+        _ => Vec::new(),
+    };
+
+    autocomplete
+        .create_autocomplete_response(&ctx.http, |r| {
+            for (name, value) in choices
+                .iter()
+                .filter(|(_, value)| value.chars().count() <= CHOICE_LENGTH)
+                .take(CHOICE_LIMIT)
+            {
+                let name: String = name.chars().take(CHOICE_LENGTH).collect();
+                r.add_string_choice(name, value);
+            }
+            r
+        })
+        .await
+}
@@ -0,0 +1,599 @@
+use chrono::prelude::*;
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+};
+use sqlx::sqlite::SqlitePool;
+use std::{fmt, str::FromStr};
+
+use crate::{
+    commands::alerts::AlertFilter,
+    lib::{
+        config::Config,
+        db,
+        error::Error,
+        output,
+        providers::{Place, Registry},
+        utils,
+    },
+    Database, Providers,
+};
+
+/// Most subscriptions a user can have, since each one is polled or sent daily.
+const SUBSCRIPTION_LIMIT: usize = 10;
+
+/// What a subscription sends. Alerts are sent as they are issued, updated or
+/// end; the others are sent daily at the subscriber's chosen local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Alerts,
+    Uv,
+    Forecast,
+}
+
+impl Kind {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Alerts => "weather alerts",
+            Self::Uv => "UV index forecast",
+            Self::Forecast => "weather forecast",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "alerts" | "alert" => Ok(Self::Alerts),
+            "uv" => Ok(Self::Uv),
+            "forecast" => Ok(Self::Forecast),
+            _ => Err(Error::Invalid("Subscriptions must be alerts, uv or forecast".into())),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Alerts => write!(f, "alerts"),
+            Self::Uv => write!(f, "uv"),
+            Self::Forecast => write!(f, "forecast"),
+        }
+    }
+}
+
+/// The local time daily subscriptions are sent when none is given.
+pub fn default_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(8, 30, 0).unwrap()
+}
+
+/// Parses a local time such as "07:30", "19:30", "7:30pm" or "7am".
+pub fn parse_time(val: &str) -> Result<NaiveTime, Error> {
+    let input = val.trim();
+    let val = input.to_lowercase();
+    let time = match val.strip_suffix("am").or_else(|| val.strip_suffix("pm")) {
+        Some(hour) if hour.contains(':') => NaiveTime::parse_from_str(&val, "%I:%M%P"),
+        Some(hour) => {
+            NaiveTime::parse_from_str(&format!("{hour}:00{}", &val[hour.len()..]), "%I:%M%P")
+        }
+        None => NaiveTime::parse_from_str(&val, "%H:%M"),
+    };
+
+    time.map_err(|_| {
+        Error::Invalid(format!("The time {input} is invalid. Use a time like 07:30 or 7:30pm"))
+    })
+}
+
+/// Splits a trailing time from a location query.
+pub fn split_time(args: &str) -> (String, Option<NaiveTime>) {
+    let args: Vec<&str> = args.split_whitespace().collect();
+
+    match args.split_last() {
+        Some((last, rest)) => match parse_time(last) {
+            Ok(time) => (rest.join(" "), Some(time)),
+            Err(_) => (args.join(" "), None),
+        },
+        None => (String::new(), None),
+    }
+}
+
+/// Returns the UTC offset at a place from its forecast, which is issued in the
+/// place's local time, or the bot's offset when the forecast is unavailable.
+pub async fn utc_offset(providers: &Registry, place: &Place) -> FixedOffset {
+    match providers.fetch_weather(place.latitude, place.longitude).await {
+        Ok(data) => *data.updated.offset(),
+        Err(_) => *Local::now().offset(),
+    }
+}
+
+/// Returns the offset daily subscriptions of a kind are local to, or None for
+/// alerts, which have no time.
+pub async fn local_offset(providers: &Registry, kind: Kind, place: &Place) -> Option<FixedOffset> {
+    match kind {
+        Kind::Alerts => None,
+        _ => Some(utc_offset(providers, place).await),
+    }
+}
+
+/// Returns the offset a subscription's time is local to. Subscriptions saved
+/// without one are local to the bot.
+pub fn subscription_offset(subscription: &db::Subscription) -> FixedOffset {
+    subscription
+        .utc_offset
+        .and_then(FixedOffset::east_opt)
+        .unwrap_or_else(|| *Local::now().offset())
+}
+
+/// Saves a subscription, or updates the time and filters of an existing
+/// subscription to the same location. Daily times are local to the location,
+/// at the given UTC offset.
+pub async fn save_subscription(
+    pool: &SqlitePool,
+    user_id: UserId,
+    kind: Kind,
+    location: &str,
+    (time, offset): (Option<NaiveTime>, Option<FixedOffset>),
+    filter: &AlertFilter,
+) -> String {
+    let subscriptions = match db::fetch_user_subscriptions(pool, user_id).await {
+        Ok(val) => val,
+        Err(e) => return format!("`{e}`"),
+    };
+    let filter = (!filter.is_empty()).then(|| filter.to_string());
+    let (time, last_sent) = match (kind, time) {
+        (Kind::Alerts, Some(_)) => {
+            return String::from("`Alerts are sent as they are issued, so no time is needed`")
+        }
+        (Kind::Alerts, None) => (None, None),
+        (_, _) if filter.is_some() => {
+            return String::from("`Filters only apply to alert subscriptions`")
+        }
+        (_, time) => {
+            let time = time.unwrap_or_else(default_time);
+            let now = Utc::now().with_timezone(&offset.unwrap_or_else(|| *Local::now().offset()));
+            // Start tomorrow when today's time has already passed
+            let last_sent = (now.time() >= time).then(|| now.date_naive().to_string());
+
+            (Some(time.format("%H:%M").to_string()), last_sent)
+        }
+    };
+    let utc_offset = offset.filter(|_| time.is_some()).map(|x| x.local_minus_utc());
+
+    if let Some(subscription) =
+        subscriptions.iter().find(|x| x.kind == kind.to_string() && x.location == location)
+    {
+        return match db::update_subscription(
+            pool,
+            subscription.id,
+            time.as_deref(),
+            utc_offset,
+            filter.as_deref(),
+        )
+        .await
+        {
+            Ok(()) => format!("`Your {kind} subscription for {location} has been updated`"),
+            Err(e) => format!("`{e}`"),
+        };
+    }
+
+    if subscriptions.len() >= SUBSCRIPTION_LIMIT {
+        return format!("`You can only have {SUBSCRIPTION_LIMIT} subscriptions`");
+    }
+
+    match db::insert_subscription(
+        pool,
+        user_id,
+        &kind.to_string(),
+        location,
+        (time.as_deref(), utc_offset),
+        filter.as_deref(),
+        last_sent.as_deref(),
+    )
+    .await
+    {
+        Ok(()) => match (time, offset) {
+            (Some(time), Some(offset)) => format!(
+                "`You will be sent the {} for {location} daily at {time} local time (UTC{offset})`",
+                kind.describe()
+            ),
+            (Some(time), None) => {
+                format!("`You will be sent the {} for {location} daily at {time}`", kind.describe())
+            }
+            (None, _) => format!(
+                "`You will be messaged when {} are issued, updated or end for {location}`",
+                kind.describe()
+            ),
+        },
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+/// Marks the legacy config fields as imported.
+const LEGACY_IMPORT: &str = "legacy_config_subscriptions";
+
+/// Imports the subscribers of the legacy `alert_users`/`alert_zip_codes` and
+/// `uv_users`/`uv_zip_codes` config fields, which sent every user the alerts and
+/// UV forecast for every ZIP code at 08:30 bot time. The import only runs once,
+/// so users who later unsubscribe stay unsubscribed; the number imported is
+/// returned, or None when it has already run.
+pub async fn import_legacy(pool: &SqlitePool, config: &Config) -> Result<Option<usize>, Error> {
+    if db::has_migration(pool, LEGACY_IMPORT).await? {
+        return Ok(None);
+    }
+
+    let legacy = [
+        (Kind::Alerts, &config.alert_users, &config.alert_zip_codes),
+        (Kind::Uv, &config.uv_users, &config.uv_zip_codes),
+    ];
+    let offset = *Local::now().offset();
+    let mut count = 0;
+
+    for (kind, users, zip_codes) in legacy.iter() {
+        for user in users.iter() {
+            let user_id = UserId(*user);
+            let subscriptions = db::fetch_user_subscriptions(pool, user_id).await?;
+
+            for zip_code in zip_codes.iter() {
+                let location = format!("{zip_code:05}");
+
+                if subscriptions
+                    .iter()
+                    .any(|x| x.kind == kind.to_string() && x.location == location)
+                {
+                    continue;
+                }
+
+                let time = (*kind == Kind::Uv).then(|| default_time().format("%H:%M").to_string());
+                let utc_offset = time.as_ref().map(|_| offset.local_minus_utc());
+
+                db::insert_subscription(
+                    pool,
+                    user_id,
+                    &kind.to_string(),
+                    &location,
+                    (time.as_deref(), utc_offset),
+                    None,
+                    None,
+                )
+                .await?;
+                count += 1;
+            }
+        }
+    }
+
+    db::insert_migration(pool, LEGACY_IMPORT).await?;
+
+    Ok(Some(count))
+}
+
+pub async fn list_subscriptions(pool: &SqlitePool, user_id: UserId) -> String {
+    let subscriptions = match db::fetch_user_subscriptions(pool, user_id).await {
+        Ok(val) => val,
+        Err(e) => return format!("`{e}`"),
+    };
+
+    if subscriptions.is_empty() {
+        return String::from("`You don't have any subscriptions`");
+    }
+
+    let lines: Vec<String> = subscriptions
+        .iter()
+        .map(|x| {
+            let time = match &x.time {
+                Some(time) => format!("{time} UTC{}", subscription_offset(x)),
+                None => String::from("as issued"),
+            };
+            let line = format!(
+                "{:<10}{:<36}{:<18}{}",
+                x.kind,
+                x.location,
+                time,
+                x.filter.as_deref().unwrap_or_default()
+            );
+
+            line.trim_end().to_string()
+        })
+        .collect();
+
+    format!("```\nSubscriptions\n\n{}\n```", lines.join("\n"))
+}
+
+/// Returns whether a saved location is the one a user typed, ignoring case
+/// and, for place names, the region (e.g., "denver" for "Denver, Colorado").
+fn same_location(location: &str, query: &str) -> bool {
+    location.eq_ignore_ascii_case(query)
+        || location.split(',').next().is_some_and(|x| x.trim().eq_ignore_ascii_case(query))
+}
+
+/// Removes a user's subscriptions of a kind, either for one location or for
+/// all of them. Locations that don't match a saved one as typed are resolved
+/// the same way `!subscribe` saves them.
+pub async fn remove_subscriptions(
+    pool: &SqlitePool,
+    providers: &Registry,
+    user_id: UserId,
+    kind: Kind,
+    location: Option<&str>,
+) -> String {
+    let subscriptions = match db::fetch_user_subscriptions(pool, user_id).await {
+        Ok(val) => val,
+        Err(e) => return format!("`{e}`"),
+    };
+    let subscriptions: Vec<&db::Subscription> =
+        subscriptions.iter().filter(|x| x.kind == kind.to_string()).collect();
+    let location = location.map(str::trim).filter(|x| !x.is_empty());
+    let mut matches: Vec<&db::Subscription> = subscriptions
+        .iter()
+        .copied()
+        .filter(|x| location.is_none_or(|y| same_location(&x.location, y)))
+        .collect();
+
+    if let (Some(query), true) = (location, matches.is_empty()) {
+        if let Ok(place) = providers.locate(query).await {
+            let stored = utils::stored_location(query, &place);
+
+            matches = subscriptions
+                .iter()
+                .copied()
+                .filter(|x| x.location.eq_ignore_ascii_case(&stored))
+                .collect();
+        }
+    }
+
+    if matches.is_empty() {
+        return match location {
+            Some(location) => format!("`You are not subscribed to {kind} for {location}`"),
+            None => format!("`You are not subscribed to {kind}`"),
+        };
+    }
+
+    for subscription in &matches {
+        if let Err(e) = db::delete_subscription(pool, subscription.id).await {
+            return format!("`{e}`");
+        }
+    }
+
+    let locations: Vec<&str> = matches.iter().map(|x| x.location.as_str()).collect();
+
+    format!("`You are no longer subscribed to {kind} for {}`", locations.join("; "))
+}
+
+#[command]
+pub async fn subscribe(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (providers, pool) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Providers>().expect("Error retrieving providers").clone(),
+            data.get::<Database>().expect("Error retrieving database pool").clone(),
+        )
+    };
+
+    if args.message().is_empty() {
+        output::say(ctx, msg, &list_subscriptions(&pool, msg.author.id).await).await?;
+        return Ok(());
+    }
+
+    let (kind, args) = args.message().split_once(' ').unwrap_or((args.message(), ""));
+    let kind = match kind.parse::<Kind>() {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
+    };
+    let (query, filter) = match AlertFilter::parse(args) {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
+    };
+    let (query, time) = split_time(&query);
+    let query = if query.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        query
+    };
+    let data = match utils::resolve_location(ctx, msg, &query).await {
+        Ok(place) => {
+            let location = utils::stored_location(&query, &place);
+            let offset = local_offset(&providers, kind, &place).await;
+            save_subscription(&pool, msg.author.id, kind, &location, (time, offset), &filter).await
+        }
+        Err(e) => format!("`{e}`"),
+    };
+
+    msg.channel_id.say(&ctx.http, data).await?;
+
+    Ok(())
+}
+
+#[command]
+pub async fn unsubscribe(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (providers, pool) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Providers>().expect("Error retrieving providers").clone(),
+            data.get::<Database>().expect("Error retrieving database pool").clone(),
+        )
+    };
+    let (kind, location) = match args.message().split_once(' ') {
+        Some((kind, location)) => (kind, Some(location)),
+        None => (args.message(), None),
+    };
+    let data = match kind.parse::<Kind>() {
+        Ok(kind) => remove_subscriptions(&pool, &providers, msg.author.id, kind, location).await,
+        Err(e) => format!("`{e}`"),
+    };
+
+    msg.channel_id.say(&ctx.http, data).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::providers::{fake::Fake, GeocodeProvider};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::{Arc, Mutex};
+
+    async fn pool() -> SqlitePool {
+        let pool =
+            SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+
+        db::create_log_table(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn saves_daily_times_in_the_location_offset() {
+        let pool = pool().await;
+        let offset = FixedOffset::west_opt(6 * 3600);
+        let time = NaiveTime::from_hms_opt(7, 30, 0);
+        let data = save_subscription(
+            &pool,
+            UserId(1),
+            Kind::Uv,
+            "Denver, Colorado",
+            (time, offset),
+            &AlertFilter::default(),
+        )
+        .await;
+        let saved = db::fetch_user_subscriptions(&pool, UserId(1)).await.unwrap();
+
+        assert_eq!(data, "`You will be sent the UV index forecast for Denver, Colorado daily at 07:30 local time (UTC-06:00)`");
+        assert_eq!(saved[0].utc_offset, Some(-6 * 3600));
+        assert_eq!(subscription_offset(&saved[0]), offset.unwrap());
+        assert!(list_subscriptions(&pool, UserId(1)).await.contains("07:30 UTC-06:00"));
+    }
+
+    #[tokio::test]
+    async fn imports_legacy_config_fields() {
+        let pool = pool().await;
+        let config: Config = serde_json::from_str(
+            r#"{
+                "admin": 1,
+                "alert_users": [1, 2],
+                "alert_zip_codes": [48317, 2134],
+                "debug": false,
+                "discord": "",
+                "healthcheck": "",
+                "openuv": "",
+                "user_agent": "",
+                "uv_users": [2],
+                "uv_zip_codes": [58247]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(import_legacy(&pool, &config).await.unwrap(), Some(5));
+
+        let saved = db::fetch_user_subscriptions(&pool, UserId(2)).await.unwrap();
+        let locations: Vec<(&str, &str)> =
+            saved.iter().map(|x| (x.kind.as_str(), x.location.as_str())).collect();
+
+        assert_eq!(locations, vec![("alerts", "02134"), ("alerts", "48317"), ("uv", "58247")]);
+        assert_eq!(saved[2].time.as_deref(), Some("08:30"));
+        assert!(saved[2].utc_offset.is_some());
+    }
+
+    #[tokio::test]
+    async fn imports_legacy_config_fields_once() {
+        let pool = pool().await;
+        let providers = Registry { weather: Vec::new(), geocode: Vec::new(), uv: Vec::new() };
+        let config: Config = serde_json::from_str(
+            r#"{
+                "admin": 1,
+                "alert_users": [1],
+                "alert_zip_codes": [48317],
+                "debug": false,
+                "discord": "",
+                "healthcheck": "",
+                "openuv": "",
+                "user_agent": ""
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(import_legacy(&pool, &config).await.unwrap(), Some(1));
+
+        let data =
+            remove_subscriptions(&pool, &providers, UserId(1), Kind::Alerts, Some("48317")).await;
+
+        assert_eq!(data, "`You are no longer subscribed to alerts for 48317`");
+        assert_eq!(import_legacy(&pool, &config).await.unwrap(), None);
+        assert!(db::fetch_user_subscriptions(&pool, UserId(1)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn saves_alerts_without_an_offset() {
+        let pool = pool().await;
+        let data = save_subscription(
+            &pool,
+            UserId(1),
+            Kind::Alerts,
+            "80202",
+            (None, None),
+            &AlertFilter::default(),
+        )
+        .await;
+        let saved = db::fetch_user_subscriptions(&pool, UserId(1)).await.unwrap();
+
+        assert!(data.contains("issued, updated or end"));
+        assert_eq!(saved[0].utc_offset, None);
+        assert_eq!(saved[0].time, None);
+    }
+
+    async fn subscribe_alerts(pool: &SqlitePool, location: &str) {
+        save_subscription(
+            pool,
+            UserId(1),
+            Kind::Alerts,
+            location,
+            (None, None),
+            &AlertFilter::default(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn removes_subscriptions_by_place_name() {
+        let pool = pool().await;
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let geocode: Vec<Box<dyn GeocodeProvider>> = vec![Box::new(Fake::working("fake", &log))];
+        let providers = Registry { weather: Vec::new(), geocode, uv: Vec::new() };
+
+        subscribe_alerts(&pool, "Denver, CO").await;
+        subscribe_alerts(&pool, "80202").await;
+
+        let data =
+            remove_subscriptions(&pool, &providers, UserId(1), Kind::Alerts, Some("denver")).await;
+
+        assert_eq!(data, "`You are no longer subscribed to alerts for Denver, CO`");
+        assert!(log.lock().unwrap().is_empty());
+
+        subscribe_alerts(&pool, "Denver, CO").await;
+
+        // Names that aren't saved as typed are resolved like !subscribe does
+        let data = remove_subscriptions(
+            &pool,
+            &providers,
+            UserId(1),
+            Kind::Alerts,
+            Some("Mile High City"),
+        )
+        .await;
+
+        assert_eq!(data, "`You are no longer subscribed to alerts for Denver, CO`");
+        assert_eq!(*log.lock().unwrap(), vec![String::from("fake: geocode")]);
+
+        let data =
+            remove_subscriptions(&pool, &providers, UserId(1), Kind::Uv, Some("80202")).await;
+
+        assert_eq!(data, "`You are not subscribed to uv for 80202`");
+    }
+}
@@ -1,10 +1,22 @@
+use chrono::{prelude::*, Duration};
+use plotters::{backend::BitMapBackend, drawing::IntoDrawingArea, prelude::*};
 use serenity::{
     framework::standard::{macros::command, Args, CommandResult},
     model::prelude::*,
     prelude::*,
+    utils::Colour,
 };
 
-use crate::lib::{error::Error, utils};
+use crate::{
+    commands::flightcat,
+    lib::{
+        error::Error,
+        metar::{thousands, FlightCategory},
+        output::{self, Report},
+        taf::{ForecastPeriod, Taf, TimelineSegment},
+        utils,
+    },
+};
 
 async fn fetch_taf(station: &str) -> Result<String, Error> {
     let url = format!("https://tgftp.nws.noaa.gov/data/forecasts/taf/stations/{station}.TXT");
@@ -17,35 +29,285 @@ async fn fetch_taf(station: &str) -> Result<String, Error> {
     }
 }
 
-async fn parse_taf(station: &str) -> String {
+/// Formats a TAF bulletin, dropping its timestamp line and the TAF and AMD
+/// prefixes, with one change group per line.
+fn format_taf(station: &str, data: &str) -> String {
+    let v: Vec<String> =
+        data.split('\n').map(|x| x.replace("TAF", "")).map(|x| x.replace("AMD", "")).collect();
+    let v2: Vec<&str> = v.iter().map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
+
+    match v2.split_first() {
+        Some((_, lines)) if !lines.is_empty() => format!(
+            "```{}\n\nUse !taf decode {station} for the decoded forecast```",
+            lines.join("\n\t")
+        ),
+        _ => format!("`No TAF available for {station}`"),
+    }
+}
+
+pub async fn parse_taf(station: &str) -> String {
     match fetch_taf(station).await {
-        Ok(data) => {
-            let v: Vec<String> = data
-                .split('\n')
-                .map(|x| x.replace("TAF", ""))
-                .map(|x| x.replace("AMD", ""))
-                .collect();
-            let v2: Vec<&str> = v.iter().map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
-            let decoded = format!("https://metar-taf.com/taf/{station}");
-            format!("```{}\n\nDecoded: {}```", v2[1..].join("\n\t"), decoded)
-        }
+        Ok(data) => format_taf(station, &data),
         Err(e) => format!("`There was an error retrieving data: {e}`"),
     }
 }
 
+/// Returns the heading and decoded conditions for a forecast period.
+fn format_period(period: &ForecastPeriod) -> (String, String) {
+    let range = match (period.start, period.end) {
+        (Some(start), Some(end)) => format!("{start} until {end}"),
+        (Some(start), None) => format!("{start}"),
+        _ => String::from("time unknown"),
+    };
+    let mut lines = Vec::new();
+
+    if let Some(wind) = &period.wind {
+        lines.push(format!("Wind:           {wind}"));
+    }
+
+    if let Some(visibility) = &period.visibility {
+        lines.push(format!("Visibility:     {visibility}"));
+    }
+
+    if period.no_significant_weather {
+        lines.push(String::from("Weather:        No significant weather"));
+    } else if !period.weather.is_empty() {
+        let weather: Vec<String> = period.weather.iter().map(ToString::to_string).collect();
+        lines.push(format!("Weather:        {}", weather.join(", ")));
+    }
+
+    if !period.clouds.is_empty() {
+        match period.ceiling() {
+            Some(ceiling) => lines.push(format!("Ceiling:        {} ft", thousands(ceiling))),
+            None => lines.push(String::from("Ceiling:        None")),
+        }
+    }
+
+    if let Some(wind_shear) = &period.wind_shear {
+        lines.push(format!("Wind Shear:     {wind_shear}"));
+    }
+
+    (format!("{} {range}", period.kind), lines.join("\n"))
+}
+
+pub async fn parse_taf_decoded(station: &str) -> Result<Report, Error> {
+    let data = fetch_taf(station).await?;
+    let data: Vec<&str> = data.split('\n').skip(1).collect();
+    let taf = Taf::parse(&data.join(" "))?;
+    let valid = match (taf.valid_from, taf.valid_to) {
+        (Some(from), Some(to)) => format!("Valid from {from} until {to}"),
+        _ => String::from("Validity period unknown"),
+    };
+    let category = taf.timeline(Utc::now()).first().and_then(|x| x.category);
+    let mut report = Report::new(format!(
+        "Decoded TAF => {} ({})",
+        taf.station,
+        taf.issued.map_or(String::from("time unknown"), |x| x.to_string())
+    ))
+    .description(valid)
+    .color(flightcat::category_color(category));
+
+    for period in &taf.periods {
+        let (name, value) = format_period(period);
+        report = report.section(name, value);
+    }
+
+    Ok(report)
+}
+
 #[command]
 pub async fn taf(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args: Vec<String> = args.message().split(' ').map(str::to_uppercase).collect();
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
 
-    for arg in args {
-        match utils::check_station_code(&arg) {
-            Ok(()) => {
-                let data = parse_taf(&arg).await;
-                msg.channel_id.say(&ctx.http, data).await?
+    for arg in utils::split_locations(&args) {
+        match utils::check_station(ctx, msg, &arg).await {
+            Ok(station) => {
+                let data = parse_taf(&station).await;
+                output::say(ctx, msg, &data).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
             }
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
         };
     }
 
     Ok(())
 }
+
+#[command]
+#[aliases("decode")]
+pub async fn taf_decode(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::check_station(ctx, msg, &arg).await {
+            Ok(station) => {
+                let report = parse_taf_decoded(&station).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Converts an embed color for drawing with plotters.
+fn plot_color(color: Colour) -> RGBColor {
+    RGBColor(color.r(), color.g(), color.b())
+}
+
+fn create_taf_graph(station: &str, segments: &[TimelineSegment]) -> Result<Vec<u8>, Error> {
+    let (start, end) =
+        match (segments.iter().map(|x| x.start).min(), segments.iter().map(|x| x.end).max()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(Error::NotFound(
+                    "The TAF report has no forecast periods to graph".into(),
+                ))
+            }
+        };
+    let hours = |x: DateTime<Utc>| (x - start).num_minutes() as f64 / 60.0;
+    let total = hours(end);
+
+    let size = (1024, 480);
+    let mut buffer = vec![0; size.0 as usize * size.1 as usize * 3];
+
+    {
+        let root_area = BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area();
+
+        root_area.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root_area)
+            .margin(30)
+            .margin_right(60)
+            .set_label_area_size(LabelAreaPosition::Left, 140)
+            .set_label_area_size(LabelAreaPosition::Bottom, 64)
+            .caption(format!("Forecast Flight Categories for {station}"), ("sans-serif", 36))
+            .build_cartesian_2d(0.0..total, 0.0..3.0)?;
+
+        chart
+            .configure_mesh()
+            .disable_y_mesh()
+            .x_desc("Time (UTC)")
+            .x_labels(8)
+            .x_label_formatter(&|x: &f64| {
+                (start + Duration::minutes((x * 60.0) as i64)).format("%d/%H%MZ").to_string()
+            })
+            .y_labels(7)
+            .y_label_formatter(&|y: &f64| {
+                if (y - 1.5).abs() < 0.01 {
+                    String::from("Prevailing")
+                } else if (y - 0.5).abs() < 0.01 {
+                    String::from("Temporary")
+                } else {
+                    String::new()
+                }
+            })
+            .label_style(("sans-serif", 20))
+            .draw()?;
+        chart.draw_series(segments.iter().map(|x| {
+            let (low, high) = if x.is_prevailing() { (1.1, 1.9) } else { (0.1, 0.9) };
+
+            Rectangle::new(
+                [(hours(x.start), low), (hours(x.end), high)],
+                plot_color(flightcat::category_color(x.category)).filled(),
+            )
+        }))?;
+        chart.draw_series(segments.iter().filter(|x| hours(x.end) - hours(x.start) >= 2.0).map(
+            |x| {
+                let y = if x.is_prevailing() { 1.6 } else { 0.6 };
+
+                Text::new(
+                    x.category.map_or(String::from("-"), |x| x.to_string()),
+                    (hours(x.start) + 0.3, y),
+                    ("sans-serif", 20).into_font().color(&WHITE),
+                )
+            },
+        ))?;
+
+        for (i, category) in
+            [FlightCategory::Vfr, FlightCategory::Mvfr, FlightCategory::Ifr, FlightCategory::Lifr]
+                .iter()
+                .enumerate()
+        {
+            let x = total * (i as f64) * 0.12;
+
+            chart.draw_series([
+                Rectangle::new(
+                    [(x, 2.3), (x + total * 0.03, 2.7)],
+                    plot_color(flightcat::category_color(Some(*category))).filled(),
+                )
+                .into_dyn(),
+                Text::new(category.to_string(), (x + total * 0.04, 2.65), ("sans-serif", 20))
+                    .into_dyn(),
+            ])?;
+        }
+
+        root_area.present()?;
+    }
+
+    utils::encode_png(buffer, size)
+}
+
+/// Fetches the TAF for a station and draws its flight categories over time.
+pub async fn forecast_graph(station: &str) -> Result<Vec<u8>, Error> {
+    let data = fetch_taf(station).await?;
+    let data: Vec<&str> = data.split('\n').skip(1).collect();
+    let taf = Taf::parse(&data.join(" "))?;
+
+    create_taf_graph(station, &taf.timeline(Utc::now()))
+}
+
+#[command]
+#[aliases("graph")]
+pub async fn taf_graph(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+
+    for arg in utils::split_locations(&args) {
+        let station = match utils::check_station(ctx, msg, &arg).await {
+            Ok(val) => val,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+                continue;
+            }
+        };
+        let image = match forecast_graph(&station).await {
+            Ok(val) => val,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+                continue;
+            }
+        };
+        let file =
+            AttachmentType::Bytes { data: image.into(), filename: String::from("taf_graph.png") };
+
+        msg.channel_id.send_files(&ctx.http, vec![file], |m| m.content("")).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_taf_bulletins() {
+        let data = "2024/05/12 17:20\nTAF KSFO 121720Z 1218/1324 29012KT P6SM FEW008\n      FM122100 30018G25KT P6SM SKC\n";
+
+        assert_eq!(
+            format_taf("KSFO", data),
+            "```KSFO 121720Z 1218/1324 29012KT P6SM FEW008\n\tFM122100 30018G25KT P6SM SKC\n\nUse !taf decode KSFO for the decoded forecast```"
+        );
+    }
+
+    #[test]
+    fn reports_missing_tafs() {
+        assert_eq!(format_taf("KPTK", ""), "`No TAF available for KPTK`");
+        assert_eq!(format_taf("KPTK", "2024/05/12 17:20\n"), "`No TAF available for KPTK`");
+    }
+}
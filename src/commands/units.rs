@@ -0,0 +1,43 @@
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::prelude::*,
+    prelude::*,
+};
+use sqlx::sqlite::SqlitePool;
+
+use crate::{
+    lib::{db, units::Units, utils},
+    Database,
+};
+
+#[command]
+pub async fn units(ctx: &Context, msg: &Message) -> CommandResult {
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+
+    msg.channel_id.say(&ctx.http, format!("`Your units are set to {units}`")).await?;
+
+    Ok(())
+}
+
+pub async fn save_units(pool: &SqlitePool, user_id: UserId, value: &str) -> String {
+    match value.parse::<Units>() {
+        Ok(units) => match db::insert_units(pool, user_id, &units.to_string()).await {
+            Ok(()) => format!("`Your units have been set to {units}`"),
+            Err(e) => format!("`{e}`"),
+        },
+        Err(e) => format!("`{e}`"),
+    }
+}
+
+#[command]
+#[aliases("set")]
+pub async fn units_set(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+
+    msg.channel_id.say(&ctx.http, save_units(&pool, msg.author.id, args.message()).await).await?;
+
+    Ok(())
+}
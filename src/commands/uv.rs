@@ -3,252 +3,125 @@ use serenity::{
     framework::standard::{macros::command, Args, CommandResult},
     model::prelude::*,
     prelude::*,
+    utils::Colour,
 };
 
-use crate::lib::{config, error::Error, utils};
-
-#[derive(Debug, Deserialize)]
-struct CurrentResult {
-    result: UVCurrent,
-}
-
-#[derive(Debug, Deserialize)]
-struct UVCurrent {
-    uv: f64,
-    uv_time: chrono::DateTime<Utc>,
-    uv_max: f64,
-    uv_max_time: chrono::DateTime<Utc>,
-    safe_exposure_time: SafeExposureTime,
-    sun_info: SunInfo,
-}
-
-#[derive(Debug, Deserialize)]
-struct SafeExposureTime {
-    st1: Option<i32>,
-    st2: Option<i32>,
-    st3: Option<i32>,
-}
-
-#[derive(Debug, Deserialize)]
-struct SunInfo {
-    sun_times: SunTimes,
-}
-
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize)]
-struct SunTimes {
-    sunrise: chrono::DateTime<Utc>,
-    solarNoon: chrono::DateTime<Utc>,
-    sunset: chrono::DateTime<Utc>,
-}
-
-#[derive(Debug, Deserialize)]
-struct ForecastResult {
-    result: Vec<ForecastPeriod>,
-}
-
-#[derive(Debug, Deserialize)]
-struct ForecastPeriod {
-    uv: f64,
-    uv_time: chrono::DateTime<Utc>,
-}
-
-async fn fetch_current(lat: f64, lon: f64) -> Result<CurrentResult, Error> {
-    let config = config::Config::load_config()?;
-    let url = format!("https://api.openuv.io/api/v1/uv?lat={lat}&lng={lon}");
-    let client = reqwest::Client::new();
-    let resp = client.get(&url).header("x-access-token", config.openuv).send().await?.json().await;
-
-    match resp {
-        Ok(data) => {
-            let resp: CurrentResult = data;
-            Ok(resp)
-        }
-        Err(_) => Err(Error::NotFound("The zip code provided does not match a location".into())),
-    }
-}
-async fn fetch_forecast(lat: f64, lon: f64) -> Result<ForecastResult, Error> {
-    let config = config::Config::load_config()?;
-    let url = format!("https://api.openuv.io/api/v1/forecast?lat={lat}&lng={lon}");
-    let client = reqwest::Client::new();
-    let resp = client.get(&url).header("x-access-token", config.openuv).send().await?.json().await;
+use crate::{
+    lib::{
+        error::Error,
+        output::{self, Report},
+        providers::{Place, Registry},
+        utils,
+    },
+    Providers,
+};
 
-    match resp {
-        Ok(data) => {
-            let resp: ForecastResult = data;
-            Ok(resp)
-        }
-        Err(_) => Err(Error::NotFound("The zip code provided does not match a location".into())),
+/// The EPA UV index color for a reading: green for low through violet for
+/// extreme.
+fn uv_color(uv: f64) -> Colour {
+    match uv {
+        x if x < 3.0 => Colour::new(0x28_95_00),
+        x if x < 6.0 => Colour::new(0xF7_E4_00),
+        x if x < 8.0 => Colour::new(0xF8_59_00),
+        x if x < 11.0 => Colour::new(0xD8_00_1D),
+        _ => Colour::new(0x6B_49_C8),
     }
 }
 
-async fn parse_current(zip_code: i32) -> String {
-    match utils::fetch_location(zip_code).await {
-        Ok(data) => {
-            let (city, state, lat, lon) = (
-                &data.results[0].name,
-                &data.results[0].admin1,
-                data.results[0].latitude,
-                data.results[0].longitude,
-            );
-            match fetch_current(lat, lon).await {
-                Ok(data) => {
-                    #[allow(unused_assignments)]
-                    let mut v = Vec::new();
-                    let (uv_time, uv_max_time, sunrise, sun_noon, sun_set) = {
-                        let v2 = [
-                            data.result.uv_time,
-                            data.result.uv_max_time,
-                            data.result.sun_info.sun_times.sunrise,
-                            data.result.sun_info.sun_times.solarNoon,
-                            data.result.sun_info.sun_times.sunset,
-                        ];
-
-                        v = v2
-                            .iter()
-                            .map(|x| Local.from_utc_datetime(&x.naive_local()).format("%I:%M %p"))
-                            .collect();
-
-                        (&v[0], &v[1], &v[2], &v[3], &v[4])
-                    };
-
-                    format!(
-                        "```
-UV Index => {}, {} (lat: {:.2}, lon: {:.2})
-
-Current UV: {:.2}
-
-Current Safe Exposure Times
----------------------------
-
-Skin Type 1:    {} min.
-Skin Type 2:    {} min.
-Skin Type 3:    {} min.
-
-Max UV for Today: {:.2} at {}
-
-Sun Times
----------
-
-Sunrise:        {}
-Solar Noon:     {}
-Sunset:         {}
-
-Last updated at {}
-```",
-                        city,
-                        state,
-                        lat,
-                        lon,
-                        data.result.uv,
-                        data.result.safe_exposure_time.st1.unwrap_or(0),
-                        data.result.safe_exposure_time.st2.unwrap_or(0),
-                        data.result.safe_exposure_time.st3.unwrap_or(0),
-                        data.result.uv_max,
-                        uv_max_time,
-                        sunrise,
-                        sun_noon,
-                        sun_set,
-                        uv_time
-                    )
-                }
-                Err(e) => format!("`There was an error retrieving data: {e}`"),
-            }
-        }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
-    }
+pub async fn parse_current(providers: &Registry, place: &Place) -> Result<Report, Error> {
+    let (lat, lon) = (place.latitude, place.longitude);
+    let data = providers.fetch_uv_current(lat, lon).await?;
+    let time = |x: DateTime<Utc>| Local.from_utc_datetime(&x.naive_local()).format("%I:%M %p");
+    let exposure = |i: usize| format!("{} min.", data.safe_exposure[i].unwrap_or(0));
+
+    Ok(Report::new(format!("UV Index => {place} (lat: {lat:.2}, lon: {lon:.2})"))
+        .field("Current UV", format!("{:.2}", data.uv))
+        .field("Max UV for Today", format!("{:.2} at {}", data.uv_max, time(data.uv_max_time)))
+        .field("Skin Type 1", exposure(0))
+        .field("Skin Type 2", exposure(1))
+        .field("Skin Type 3", exposure(2))
+        .field("Sunrise", time(data.sunrise).to_string())
+        .field("Solar Noon", time(data.solar_noon).to_string())
+        .field("Sunset", time(data.sunset).to_string())
+        .footer(format!("Last updated at {}", time(data.uv_time)))
+        .color(uv_color(data.uv)))
 }
 
 #[command]
 #[aliases("current")]
 pub async fn uv_current(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
-        Ok(val) => val,
-        Err(_) => String::new(),
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
     };
-    let args: Vec<&str> = args.split(' ').collect();
-
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => {
-                let data = parse_current(zip_code).await;
-                msg.channel_id.say(&ctx.http, data).await?
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(place) => {
+                let report = parse_current(&providers, &place).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
             }
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
         };
     }
 
     Ok(())
 }
 
-pub async fn parse_forecast(zip_code: i32) -> String {
-    match utils::fetch_location(zip_code).await {
-        Ok(data) => {
-            let (city, state, lat, lon) = (
-                &data.results[0].name,
-                &data.results[0].admin1,
-                data.results[0].latitude,
-                data.results[0].longitude,
-            );
-            match fetch_forecast(lat, lon).await {
-                Ok(data) => {
-                    let v: Vec<f64> = data.result.iter().map(|x| x.uv).collect();
-                    let v2: Vec<_> = data
-                        .result
-                        .iter()
-                        .map(|x| {
-                            Local.from_utc_datetime(&x.uv_time.naive_local()).format("%I:%M %p")
-                        })
-                        .collect();
-                    let mut forecast = String::new();
-                    let combined = v.iter().zip(v2.iter());
-
-                    for (val, time) in combined {
-                        let entry = format!("{time}: {val:.2}\n");
-                        forecast.push_str(&entry);
-                    }
-
-                    format!(
-                        "```
-UV Forecast => {}, {} (lat: {:.2}, lon: {:.2})
-
-Forecast for {}:
-
-{}
-```",
-                        city,
-                        state,
-                        lat,
-                        lon,
-                        Local
-                            .from_utc_datetime(&data.result[0].uv_time.naive_local())
-                            .format("%B %d, %Y"),
-                        forecast
-                    )
-                }
-                Err(e) => format!("`There was an error retrieving data: {e}`"),
-            }
-        }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
-    }
+pub async fn parse_forecast(providers: &Registry, place: &Place) -> Result<Report, Error> {
+    let (lat, lon) = (place.latitude, place.longitude);
+    let data = providers.fetch_uv_forecast(lat, lon).await?;
+    let report = Report::new(format!("UV Forecast => {place} (lat: {lat:.2}, lon: {lon:.2})"));
+    let first = match data.first() {
+        Some(val) => val,
+        None => return Ok(report.description("There is no UV forecast available")),
+    };
+    let forecast: Vec<String> = data
+        .iter()
+        .map(|x| {
+            format!(
+                "{}: {:.2}",
+                Local.from_utc_datetime(&x.time.naive_local()).format("%I:%M %p"),
+                x.uv
+            )
+        })
+        .collect();
+    let max = data.iter().map(|x| x.uv).fold(0.0, f64::max);
+
+    Ok(report
+        .section(
+            format!(
+                "Forecast for {}",
+                Local.from_utc_datetime(&first.time.naive_local()).format("%B %d, %Y")
+            ),
+            forecast.join("\n"),
+        )
+        .color(uv_color(max)))
 }
 
 #[command]
 #[aliases("forecast")]
 pub async fn uv_forecast(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
-        Ok(val) => val,
-        Err(_) => String::new(),
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
     };
-    let args: Vec<&str> = args.split(' ').collect();
-
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => {
-                let data = parse_forecast(zip_code).await;
-                msg.channel_id.say(&ctx.http, data).await?
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(place) => {
+                let report = parse_forecast(&providers, &place).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
             }
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
         };
     }
 
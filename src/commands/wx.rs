@@ -5,409 +5,809 @@ use serenity::{
     model::prelude::*,
     prelude::*,
 };
-use tokio::fs::File;
-
-use crate::lib::{config, error::Error, utils, utils::GeocodeResponse};
-
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize)]
-pub struct WeatherResponse {
-    creationDate: chrono::DateTime<Utc>,
-    pub location: LocationData,
-    time: ForecastTime,
-    data: ForecastData,
-    currentobservation: CurrentData,
-}
 
-#[derive(Debug, Deserialize)]
-pub struct LocationData {
-    pub zone: String,
-}
+use crate::{
+    lib::{
+        db,
+        error::Error,
+        output::{self, Report},
+        providers::{Hour, Period, Place, Registry},
+        units::Units,
+        utils,
+    },
+    Database, Providers,
+};
 
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize)]
-pub struct ForecastTime {
-    startPeriodName: Vec<String>,
-    tempLabel: Vec<String>,
+/// Notes the UTC offset that hourly times are given in.
+fn time_note(data: &[Hour]) -> String {
+    match data.first() {
+        Some(hour) => {
+            format!("\nTimes are local to the location (UTC{})\n", hour.time.format("%:z"))
+        }
+        None => String::new(),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ForecastData {
-    temperature: Vec<String>,
-    text: Vec<String>,
+fn format_value(val: Option<f64>, suffix: &str) -> String {
+    match val {
+        Some(val) => format!("{val}{suffix}"),
+        None => String::from("-"),
+    }
 }
 
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize)]
-pub struct CurrentData {
-    pub Temp: String,
-    pub Dewp: String,
-    pub Relh: String,
-    pub Winds: String,
-    pub Windd: String,
-    pub Gust: String,
-    pub Weather: String,
-    pub Visibility: String,
-    pub SLP: String,
-    pub WindChill: String,
+pub async fn parse_current(
+    providers: &Registry,
+    place: &Place,
+    units: Units,
+) -> Result<Report, Error> {
+    let (lat, lon) = (place.latitude, place.longitude);
+    let current = providers.fetch_weather(lat, lon).await?.current;
+    let direction = match current.wind_direction {
+        Some(val) => format!("{} ({val}\u{b0})", utils::cardinal_direction(val)),
+        None => String::from("-"),
+    };
+
+    Ok(Report::new(format!("Current Weather => {place} (lat: {lat:.2}, lon: {lon:.2})"))
+        .field("Temperature", units.format_temperature(current.temperature))
+        .field("Dew", units.format_temperature(current.dewpoint))
+        .field("Humidity", format_value(current.relative_humidity().map(f64::round), "%"))
+        .field("Wind Speed", units.format_speed(current.wind_speed))
+        .field("Wind Direction", direction)
+        .field("Wind Gust", units.format_speed(current.wind_gust))
+        .field("Pressure", units.format_pressure(current.pressure))
+        .field("Weather", current.weather.as_deref().unwrap_or("-"))
+        .field("Visibility", units.format_distance(current.visibility))
+        .field("Wind Chill", units.format_temperature(current.wind_chill()))
+        .field("Heat Index", units.format_temperature(current.heat_index()))
+        .field("Feels Like", units.format_temperature(current.feels_like()))
+        .field("Cloud Base", units.format_height(current.cloud_base()))
+        .thumbnail(current.icon))
 }
 
-pub async fn fetch_wx(lat: f64, lon: f64) -> Result<WeatherResponse, Error> {
-    let config = config::Config::load_config()?;
-    let url = format!(
-        "https://forecast.weather.gov/MapClick.php?lat={lat}&lon={lon}&unit=0&lg=english&FcstType=json");
-    let client = reqwest::ClientBuilder::new().user_agent(config.user_agent).build()?;
-    let resp = client.get(&url).send().await?.json().await;
-
-    match resp {
-        Ok(data) => {
-            let resp: WeatherResponse = data;
-            Ok(resp)
-        }
-        Err(_) => Err(Error::NotFound("The zip code provided does not match a location".into())),
+#[command]
+#[aliases("current")]
+pub async fn wx_current(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
+    };
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(place) => {
+                let report = parse_current(&providers, &place, units).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
+        };
     }
-}
 
-async fn parse_current(data: GeocodeResponse) -> String {
-    let (city, state, lat, lon) = (
-        &data.results[0].name,
-        &data.results[0].admin1,
-        data.results[0].latitude,
-        data.results[0].longitude,
-    );
+    Ok(())
+}
 
-    match fetch_wx(lat, lon).await {
-        Ok(data) => {
+pub async fn parse_forecast(
+    providers: &Registry,
+    place: &Place,
+    units: Units,
+) -> Result<Report, Error> {
+    let (lat, lon) = (place.latitude, place.longitude);
+    let data = providers.fetch_weather(lat, lon).await?;
+    let mut report =
+        Report::new(format!("Weather Forecast => {place} (lat: {lat:.2}, lon: {lon:.2})"))
+            .footer(format!("Last updated at {}", utils::format_local(data.updated, "%I:%M %p")))
+            .thumbnail(data.current.icon);
+
+    for period in data.periods.iter().take(5) {
+        report = report.section(
             format!(
-                "```
-Current Weather => {}, {} (lat: {:.2}, lon: {:.2})
-
-Temperature:        {}
-Dew:                {}
-Humidity:           {}
-Wind Speed:         {}
-Wind Direction:     {} {}
-Wind Gust:          {}
-Pressure:           {}
-Weather:            {}
-Visibility:         {}
-Wind Chill          {}
-```",
-                city,
-                state,
-                lat,
-                lon,
-                if data.currentobservation.Temp == "NA" || data.currentobservation.Temp.is_empty() {
-                    String::from("-")
-                } else {
-                    let temp = data.currentobservation.Temp;
-                    format!("{temp}\u{b0}")
-                },
-                if data.currentobservation.Dewp == "NA" || data.currentobservation.Dewp.is_empty() {
-                    String::from("-")
-                } else {
-                    let dew = data.currentobservation.Dewp;
-                    format!("{dew}\u{b0}")
-                },
-                if data.currentobservation.Relh == "NA" || data.currentobservation.Relh.is_empty() {
-                    String::from("-")
-                } else {
-                    let humidity = data.currentobservation.Relh;
-                    format!("{humidity}%")
-                },
-                if data.currentobservation.Winds == "NA" || data.currentobservation.Winds.is_empty()
-                {
-                    String::from("-")
-                } else {
-                    let wind = data.currentobservation.Winds;
-                    format!("{wind} MPH")
-                },
-                if data.currentobservation.Windd == "NA" || data.currentobservation.Windd.is_empty()
-                {
-                    String::from("-")
-                } else {
-                    utils::cardinal_direction(&data.currentobservation.Windd)
-                },
-                if data.currentobservation.Windd == "NA" || data.currentobservation.Windd.is_empty()
-                {
-                    String::new()
-                } else {
-                    let wind_direction = data.currentobservation.Windd;
-                    format!("({wind_direction}\u{b0})")
-                },
-                if data.currentobservation.Gust == "NA" || data.currentobservation.Gust.is_empty() {
-                    String::from("-")
-                } else {
-                    data.currentobservation.Gust
-                },
-                // TODO: Trim trailing zeroes and decimal point
-                if data.currentobservation.SLP == "NA" || data.currentobservation.SLP.is_empty() {
-                    String::from("-")
-                } else {
-                    let alt = data.currentobservation.SLP;
-                    format!("{alt} inHg")
-                },
-                if data.currentobservation.Weather == "NA"
-                    || data.currentobservation.Weather.is_empty()
-                {
-                    String::from("-")
-                } else {
-                    data.currentobservation.Weather
-                },
-                if data.currentobservation.Visibility == "NA"
-                    || data.currentobservation.Visibility.is_empty()
-                {
-                    String::from("-")
-                } else {
-                    let visibility = data.currentobservation.Visibility;
-                    format!("{visibility} SM")
-                },
-                if data.currentobservation.WindChill == "NA"
-                    || data.currentobservation.WindChill.is_empty()
-                {
-                    String::from("-")
-                } else {
-                    data.currentobservation.WindChill
-                }
-            )
-        }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
+                "{} ({}: {})",
+                period.name,
+                period.label.to_lowercase(),
+                units.format_temperature(period.temperature.map(f64::from))
+            ),
+            &period.text,
+        );
     }
+
+    Ok(report)
 }
 
 #[command]
-#[aliases("current")]
-pub async fn wx_current(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
-        Ok(val) => val,
-        Err(_) => String::new(),
+#[aliases("forecast")]
+pub async fn wx_forecast(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
     };
-    let args: Vec<&str> = args.split(' ').collect();
-
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => match utils::fetch_location(zip_code).await {
-                Ok(data) => {
-                    let data = parse_current(data).await;
-                    msg.channel_id.say(&ctx.http, data).await?
-                }
-                Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-            },
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
+    let args = utils::check_location(ctx, msg, &args).await.unwrap_or_default();
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+    let style = utils::fetch_style(ctx, msg.guild_id).await;
+
+    for arg in utils::split_locations(&args) {
+        match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(place) => {
+                let report = parse_forecast(&providers, &place, units).await;
+                output::send(ctx, msg, report, style).await?
+            }
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            }
         };
     }
 
     Ok(())
 }
 
-pub async fn parse_forecast(zip_code: i32) -> String {
-    match utils::fetch_location(zip_code).await {
-        Ok(data) => {
-            let (city, state, lat, lon) = (
-                &data.results[0].name,
-                &data.results[0].admin1,
-                data.results[0].latitude,
-                data.results[0].longitude,
-            );
-            match fetch_wx(lat, lon).await {
-                Ok(data) => {
-                    let mut forecast = String::new();
-                    let time = Local
-                        .from_utc_datetime(&data.creationDate.naive_local())
-                        .format("%I:%M %p");
-
-                    for i in 0..5 {
-                        forecast.push_str(&format!(
-                            "\n\n{} ({}: {})\n-----------------------\n\n{}",
-                            data.time.startPeriodName[i],
-                            data.time.tempLabel[i].to_lowercase(),
-                            data.data.temperature[i],
-                            data.data.text[i]
-                        ));
-                    }
-
-                    format!(
-                    "```Weather Forecast => {}, {} (lat: {:.2}, lon: {:.2}) {}\n\nLast updated at {}```",
-                    city, state, lat, lon, forecast, time
-                )
-                }
-                Err(e) => format!("`There was an error retrieving data: {e}`"),
+/// Overlays drawn on the forecast graph in addition to highs and lows.
+#[derive(Debug, Default)]
+pub struct GraphOptions {
+    pub precip: bool,
+    pub wind: bool,
+}
+
+/// Splits `--precip` and `--wind` options from the location query.
+fn parse_graph_options(args: &str) -> Result<(String, GraphOptions), Error> {
+    let mut options = GraphOptions::default();
+    let mut query = Vec::new();
+
+    for arg in args.split_whitespace() {
+        match arg.to_lowercase().as_str() {
+            "--precip" => options.precip = true,
+            "--wind" => options.wind = true,
+            val if val.starts_with("--") => {
+                return Err(Error::Invalid(format!(
+                    "Unknown option {arg}. Options are --precip and --wind"
+                )))
             }
+            _ => query.push(arg),
         }
-        Err(e) => format!("`There was an error retrieving data: {e}`"),
     }
+
+    Ok((query.join(" "), options))
 }
 
-#[command]
-#[aliases("forecast")]
-pub async fn wx_forecast(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
-        Ok(val) => val,
-        Err(_) => String::new(),
-    };
-    let args: Vec<&str> = args.split(' ').collect();
+/// A forecast day with its high and low and the daily maximum precipitation
+/// chance and wind speed.
+#[derive(Debug)]
+struct ForecastDay {
+    date: NaiveDate,
+    high: Option<i32>,
+    low: Option<i32>,
+    precipitation: Option<f64>,
+    wind_speed: Option<f64>,
+}
 
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => {
-                let data = parse_forecast(zip_code).await;
-                msg.channel_id.say(&ctx.http, data).await?
+/// Groups day and night periods by the place's local date. Periods without a
+/// date are assumed to follow each other, starting `today`.
+fn forecast_days(periods: &[Period], hours: &[Hour], today: NaiveDate) -> Vec<ForecastDay> {
+    let mut days: Vec<ForecastDay> = Vec::new();
+    let mut date = today;
+
+    for (i, period) in periods.iter().enumerate() {
+        date = match period.date {
+            Some(val) => val,
+            None if i > 0 && period.label == "High" => date + chrono::Duration::days(1),
+            None => date,
+        };
+
+        let day = match days.iter_mut().position(|x| x.date == date) {
+            Some(i) => &mut days[i],
+            None => {
+                days.push(ForecastDay {
+                    date,
+                    high: None,
+                    low: None,
+                    precipitation: None,
+                    wind_speed: None,
+                });
+                days.last_mut().unwrap()
             }
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
+        };
+
+        if period.label == "High" {
+            day.high = day.high.or(period.temperature);
+        } else {
+            day.low = day.low.or(period.temperature);
+        }
+
+        day.precipitation = match (day.precipitation, period.precipitation) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
         };
     }
 
-    Ok(())
+    for day in &mut days {
+        day.wind_speed = hours
+            .iter()
+            .filter(|x| x.time.date_naive() == day.date)
+            .filter_map(|x| x.wind_speed)
+            .reduce(f64::max);
+    }
+
+    days
 }
 
 fn create_forecast_graph(
-    city: &str,
-    state: &str,
-    label: &str,
-    temps: &[i32],
-) -> Result<String, Error> {
-    let timestamp: DateTime<Utc> = Utc::now();
-    let file_name =
-        format!("./attachments/{}_forecast_graph.png", timestamp.format("%y_%m_%d_%H%M%S"));
-
-    let min = temps.iter().min().unwrap();
-    let max = temps.iter().max().unwrap();
-
-    let (temp_highs, temp_lows) = if label.contains("Low") {
-        let highs: Vec<i32> = temps.iter().skip(1).step_by(2).map(|x| x.to_owned()).collect();
-        let lows: Vec<i32> = temps.iter().step_by(2).map(|x| x.to_owned()).collect();
-
-        (highs, lows)
+    place: &Place,
+    days: &[ForecastDay],
+    options: &GraphOptions,
+    units: Units,
+) -> Result<Vec<u8>, Error> {
+    let points = |f: &dyn Fn(&ForecastDay) -> Option<f64>| -> Vec<(f64, f64)> {
+        days.iter().enumerate().filter_map(|(i, x)| Some((i as f64, f(x)?))).collect()
+    };
+    let highs = points(&|x| x.high.map(|x| units.temperature(f64::from(x))));
+    let lows = points(&|x| x.low.map(|x| units.temperature(f64::from(x))));
+    let winds =
+        if options.wind { points(&|x| x.wind_speed.map(|x| units.speed(x))) } else { Vec::new() };
+
+    if highs.is_empty() && lows.is_empty() {
+        return Err(Error::NotFound(format!("There is no temperature forecast for {place}")));
+    }
+
+    let values: Vec<f64> = highs.iter().chain(&lows).chain(&winds).map(|x| x.1).collect();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let y_desc = if options.wind {
+        format!("Temperature ({}) / Wind ({})", units.temperature_unit(), units.speed_unit())
     } else {
-        let highs: Vec<i32> = temps.iter().step_by(2).map(|x| x.to_owned()).collect();
-        let lows: Vec<i32> = temps.iter().skip(1).step_by(2).map(|x| x.to_owned()).collect();
+        format!("Temperature ({})", units.temperature_unit())
+    };
+
+    let x_range = -0.5..(days.len() as f64 - 0.5);
+
+    let size = (1024, 768);
+    let mut buffer = vec![0; size.0 as usize * size.1 as usize * 3];
+
+    {
+        let root_area = BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area();
+
+        root_area.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root_area)
+            .margin(30)
+            .set_label_area_size(LabelAreaPosition::Left, 80)
+            .set_label_area_size(LabelAreaPosition::Right, if options.precip { 80 } else { 0 })
+            .set_label_area_size(LabelAreaPosition::Bottom, 64)
+            .caption(format!("Forecasted Temperatures for {place}"), ("sans-serif", 36))
+            .build_cartesian_2d(x_range.clone(), (min - 10.0)..(max + 10.0))?
+            .set_secondary_coord(x_range, 0.0..100.0);
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(days.len())
+            .x_label_formatter(&|x: &f64| match days.get(x.round() as usize) {
+                Some(day) if (x - x.round()).abs() < 0.01 => {
+                    day.date.format("%a %m/%d").to_string()
+                }
+                _ => String::new(),
+            })
+            .x_desc("Day")
+            .y_desc(&y_desc)
+            .label_style(("sans-serif", 20))
+            .draw()?;
+
+        if options.precip {
+            let precip_color = BLUE.mix(0.3);
+
+            chart
+                .configure_secondary_axes()
+                .y_desc("Precipitation (%)")
+                .label_style(("sans-serif", 20))
+                .draw()?;
+            chart
+                .draw_secondary_series(points(&|x| x.precipitation).into_iter().map(|(x, y)| {
+                    Rectangle::new([(x - 0.3, 0.0), (x + 0.3, y)], precip_color.filled())
+                }))?
+                .label("Precipitation")
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 5), (x + 20, y + 5)], precip_color.filled())
+                });
+        }
+
+        chart
+            .draw_series(LineSeries::new(highs, RED.stroke_width(2)).point_size(4))?
+            .label("High")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED.stroke_width(3)));
+        chart
+            .draw_series(LineSeries::new(lows, BLUE.stroke_width(2)).point_size(4))?
+            .label("Low")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE.stroke_width(3)));
+
+        if options.wind {
+            chart
+                .draw_series(LineSeries::new(winds, BLACK.stroke_width(2)).point_size(4))?
+                .label("Wind")
+                .legend(|(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], BLACK.stroke_width(3))
+                });
+        }
+
+        chart
+            .configure_series_labels()
+            .label_font(("sans-serif", 18))
+            .position(SeriesLabelPosition::MiddleRight)
+            .border_style(BLACK)
+            .background_style(WHITE.mix(0.8))
+            .draw()?;
 
-        (highs, lows)
+        root_area.present()?;
+    }
+
+    utils::encode_png(buffer, size)
+}
+
+/// Fetches the forecast for a place and draws it, with hourly data for the
+/// wind overlay.
+pub async fn forecast_graph(
+    providers: &Registry,
+    place: &Place,
+    options: &GraphOptions,
+    units: Units,
+) -> Result<Vec<u8>, Error> {
+    let data = providers.fetch_weather(place.latitude, place.longitude).await?;
+    let hours = if options.wind {
+        providers.fetch_hourly(place.latitude, place.longitude).await.unwrap_or_default()
+    } else {
+        Vec::new()
     };
 
-    let root_area = BitMapBackend::new(&file_name, (1024, 768)).into_drawing_area();
-
-    root_area.fill(&WHITE).unwrap();
-
-    let mut chart = ChartBuilder::on(&root_area)
-        .margin(30)
-        .set_label_area_size(LabelAreaPosition::Left, 64)
-        .set_label_area_size(LabelAreaPosition::Bottom, 64)
-        .caption(format!("Forecasted Temperatures for {city}, {state}"), ("sans-serif", 36))
-        .build_cartesian_2d(0..6, (min - 10)..(max + 10))
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .x_desc("Day")
-        .y_desc("Temperature")
-        .label_style(("sans-serif", 24))
-        .draw()
-        .unwrap();
-    chart
-        .draw_series(
-            LineSeries::new(
-                temp_highs.iter().enumerate().map(|(i, temp)| {
-                    let x = i as i32;
-                    let y = *temp;
-                    (x, y)
-                }),
-                &RED,
-            )
-            .point_size(2),
-        )
-        .unwrap()
-        .label("High")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
-    chart
-        .draw_series(
-            LineSeries::new(
-                temp_lows.iter().enumerate().map(|(i, temp)| {
-                    let x = i as i32;
-                    let y = *temp;
-                    (x, y)
-                }),
-                &BLUE,
-            )
-            .point_size(2),
-        )
-        .unwrap()
-        .label("Low")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
-    chart
-        .configure_series_labels()
-        .label_font(("sans-serif", 18))
-        .position(SeriesLabelPosition::MiddleRight)
-        .border_style(BLACK)
-        .background_style(WHITE.mix(0.8))
-        .draw()
-        .unwrap();
-
-    Ok(file_name.to_string())
+    let days = forecast_days(&data.periods, &hours, data.updated.date_naive());
+
+    create_forecast_graph(place, &days, options, units)
 }
 
 #[command]
 #[aliases("graph")]
 pub async fn wx_graph(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let args = match utils::check_location(ctx, msg, &args).await {
+    let (providers, pool) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Providers>().expect("Error retrieving providers").clone(),
+            data.get::<Database>().expect("Error retrieving database pool").clone(),
+        )
+    };
+    let (args, options) = match parse_graph_options(args.message()) {
         Ok(val) => val,
-        Err(_) => String::new(),
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
     };
-    let args: Vec<&str> = args.split(' ').collect();
-
-    for arg in args {
-        match utils::check_zip_code(arg) {
-            Ok(zip_code) => match utils::fetch_location(zip_code).await {
-                Ok(data) => {
-                    let (city, state, lat, lon) = (
-                        &data.results[0].name,
-                        &data.results[0].admin1,
-                        data.results[0].latitude,
-                        data.results[0].longitude,
-                    );
-                    match fetch_wx(lat, lon).await {
-                        Ok(data) => {
-                            let temps: Vec<i32> = data
-                                .data
-                                .temperature
-                                .iter()
-                                .map(|x| x.parse::<i32>().unwrap())
-                                .collect();
-                            let file_name = match create_forecast_graph(
-                                city,
-                                state,
-                                &data.time.tempLabel[0],
-                                &temps,
-                            ) {
-                                Ok(val) => val,
-                                Err(e) => {
-                                    msg.channel_id
-                                        .say(&ctx.http, format!("`Error creating chart: {e}`"))
-                                        .await?;
-                                    return Ok(());
-                                }
-                            };
-                            let file = match File::open(file_name).await {
-                                Ok(f) => f,
-                                Err(e) => {
-                                    msg.channel_id
-                                        .say(&ctx.http, format!("`Error opening image file: {e}`"))
-                                        .await?;
-                                    return Ok(());
-                                }
-                            };
-                            let file = vec![(&file, "forecast_graph.png")];
-
-                            msg.channel_id.send_files(&ctx.http, file, |m| m.content("")).await?
-                        }
-                        Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-                    }
-                }
-                Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
-            },
-            Err(e) => msg.channel_id.say(&ctx.http, format!("`{e}`")).await?,
+    let args = if args.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        args
+    };
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+
+    for arg in utils::split_locations(&args) {
+        let place = match utils::resolve_location(ctx, msg, &arg).await {
+            Ok(val) => val,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+                continue;
+            }
+        };
+        let image = match forecast_graph(&providers, &place, &options, units).await {
+            Ok(val) => val,
+            Err(e) => {
+                msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+                continue;
+            }
+        };
+        let file = AttachmentType::Bytes {
+            data: image.into(),
+            filename: String::from("forecast_graph.png"),
+        };
+
+        msg.channel_id.send_files(&ctx.http, vec![file], |m| m.content("")).await?;
+    }
+
+    Ok(())
+}
+
+pub fn parse_hourly(place: &Place, data: &[Hour], units: Units) -> String {
+    let mut block = format!(
+        "Hourly Forecast => {} (lat: {:.2}, lon: {:.2})\n\n{:<10}{:>6}{:>8}  {:<12}{:>5}\n",
+        place, place.latitude, place.longitude, "Time", "Temp", "Precip", "Wind", "Sky"
+    );
+
+    for hour in data {
+        let wind = match (hour.wind_speed, hour.wind_direction) {
+            (Some(_), Some(direction)) => format!(
+                "{} {}",
+                utils::cardinal_direction(direction),
+                units.format_speed(hour.wind_speed)
+            ),
+            (speed, _) => units.format_speed(speed),
         };
+        let line = format!(
+            "{:<10}{:>6}{:>8}  {:<12}{:>5}\n",
+            hour.time.format("%a %I %p"),
+            units.format_temperature(hour.temperature),
+            format_value(hour.precipitation, "%"),
+            wind,
+            format_value(hour.sky_cover, "%")
+        );
+
+        block.push_str(&line);
     }
 
+    block.push_str(&time_note(data));
+
+    format!("```\n{}```", block)
+}
+
+pub fn create_hourly_graph(place: &Place, data: &[Hour], units: Units) -> Result<Vec<u8>, Error> {
+    let start = match data.first() {
+        Some(hour) => hour.time,
+        None => return Err(Error::NotFound(format!("There is no hourly forecast for {place}"))),
+    };
+    let total = (data.len() as f64 - 1.0).max(1.0);
+    let points = |f: &dyn Fn(&Hour) -> Option<f64>| -> Vec<(f64, f64)> {
+        data.iter()
+            .filter_map(|x| Some(((x.time - start).num_minutes() as f64 / 60.0, f(x)?)))
+            .collect()
+    };
+    let temps = points(&|x| x.temperature.map(|x| units.temperature(x)));
+    let winds = points(&|x| x.wind_speed.map(|x| units.speed(x)));
+    let values: Vec<f64> = temps.iter().chain(&winds).map(|x| x.1).collect();
+    let (min, max) =
+        match (values.iter().copied().reduce(f64::min), values.iter().copied().reduce(f64::max)) {
+            (Some(min), Some(max)) => (min.min(0.0), max),
+            _ => (0.0, 100.0),
+        };
+    let pad = if max > min { (max - min) * 0.1 } else { 1.0 };
+
+    let size = (1024, 768);
+    let mut buffer = vec![0; size.0 as usize * size.1 as usize * 3];
+
+    {
+        let root_area = BitMapBackend::with_buffer(&mut buffer, size).into_drawing_area();
+
+        root_area.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root_area)
+            .margin(30)
+            .set_label_area_size(LabelAreaPosition::Left, 64)
+            .set_label_area_size(LabelAreaPosition::Right, 64)
+            .set_label_area_size(LabelAreaPosition::Bottom, 64)
+            .caption(format!("Hourly Forecast for {place}"), ("sans-serif", 36))
+            .build_cartesian_2d(-0.5..(total + 0.5), min..(max + pad))?
+            .set_secondary_coord(-0.5..(total + 0.5), 0.0..100.0);
+
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|x: &f64| {
+                let time = start + chrono::Duration::minutes((x * 60.0) as i64);
+                time.format("%a %I %p").to_string()
+            })
+            .y_desc(format!(
+                "Temperature ({}) / Wind ({})",
+                units.temperature_unit(),
+                units.speed_unit()
+            ))
+            .label_style(("sans-serif", 18))
+            .draw()?;
+        chart
+            .configure_secondary_axes()
+            .y_desc("Precipitation / Sky Cover (%)")
+            .label_style(("sans-serif", 18))
+            .draw()?;
+
+        let precip_color = BLUE.mix(0.3);
+        let sky_color = RGBColor(128, 128, 128);
+
+        chart
+            .draw_secondary_series(points(&|x| x.precipitation).into_iter().map(|(x, y)| {
+                Rectangle::new([(x - 0.4, 0.0), (x + 0.4, y)], precip_color.filled())
+            }))?
+            .label("Precipitation")
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], precip_color.filled())
+            });
+        chart
+            .draw_secondary_series(LineSeries::new(
+                points(&|x| x.sky_cover),
+                sky_color.stroke_width(2),
+            ))?
+            .label("Sky Cover")
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], sky_color.stroke_width(3))
+            });
+        chart
+            .draw_series(LineSeries::new(temps, RED.stroke_width(2)).point_size(3))?
+            .label("Temperature")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED.stroke_width(3)));
+        chart
+            .draw_series(LineSeries::new(winds, BLACK.stroke_width(2)))?
+            .label("Wind")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK.stroke_width(3)));
+        chart
+            .configure_series_labels()
+            .label_font(("sans-serif", 18))
+            .position(SeriesLabelPosition::UpperRight)
+            .border_style(BLACK)
+            .background_style(WHITE.mix(0.8))
+            .draw()?;
+
+        root_area.present()?;
+    }
+
+    utils::encode_png(buffer, size)
+}
+
+/// Sends the hourly table and graph, or the feels-like table, for the hours
+/// and location given or the saved location, as `!wx hourly` and `!wx feels`
+/// share everything but their output.
+async fn send_hours(ctx: &Context, msg: &Message, args: &Args, feels: bool) -> CommandResult {
+    let (providers, pool) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<Providers>().expect("Error retrieving providers").clone(),
+            data.get::<Database>().expect("Error retrieving database pool").clone(),
+        )
+    };
+    let (query, hours) = utils::split_count(args.message());
+    let query = if query.is_empty() {
+        db::fetch_location(&pool, msg.author.id).await.unwrap_or_default()
+    } else {
+        query
+    };
+    let hours = match hours {
+        Some(val) => match val.parse::<usize>() {
+            Ok(val) if (1..=48).contains(&val) => val,
+            _ => {
+                msg.channel_id.say(&ctx.http, "`Hours must be between 1 and 48`").await?;
+                return Ok(());
+            }
+        },
+        None if feels => 12,
+        None => 24,
+    };
+    let units = utils::fetch_units(ctx, msg.author.id).await;
+    let place = match utils::resolve_location(ctx, msg, &query).await {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`{e}`")).await?;
+            return Ok(());
+        }
+    };
+    let data = match providers.fetch_hourly(place.latitude, place.longitude).await {
+        Ok(mut val) => {
+            val.truncate(hours);
+            val
+        }
+        Err(e) => {
+            msg.channel_id
+                .say(&ctx.http, format!("`There was an error retrieving data: {e}`"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if feels {
+        output::say(ctx, msg, &parse_feels(&place, &data, units)).await?;
+        return Ok(());
+    }
+
+    output::say(ctx, msg, &parse_hourly(&place, &data, units)).await?;
+
+    let image = match create_hourly_graph(&place, &data, units) {
+        Ok(val) => val,
+        Err(e) => {
+            msg.channel_id.say(&ctx.http, format!("`Error creating chart: {e}`")).await?;
+            return Ok(());
+        }
+    };
+    let file =
+        AttachmentType::Bytes { data: image.into(), filename: String::from("hourly_graph.png") };
+
+    msg.channel_id.send_files(&ctx.http, vec![file], |m| m.content("")).await?;
+
     Ok(())
 }
+
+#[command]
+#[aliases("hourly")]
+pub async fn wx_hourly(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    send_hours(ctx, msg, &args, false).await
+}
+
+/// The NWS heat index or wind chill hazard category for an apparent
+/// temperature in degrees F, if any.
+fn feels_category(hour: &Hour, feels: f64) -> &'static str {
+    match hour.temperature {
+        Some(temp) if temp >= 80.0 => match feels {
+            x if x >= 125.0 => "Extreme Danger",
+            x if x >= 103.0 => "Danger",
+            x if x >= 90.0 => "Extreme Caution",
+            x if x >= 80.0 => "Caution",
+            _ => "",
+        },
+        Some(temp) if temp <= 50.0 => match feels {
+            x if x <= -48.0 => "Frostbite 5 min",
+            x if x <= -35.0 => "Frostbite 10 min",
+            x if x <= -18.0 => "Frostbite 30 min",
+            _ => "",
+        },
+        _ => "",
+    }
+}
+
+pub fn parse_feels(place: &Place, data: &[Hour], units: Units) -> String {
+    let mut block = format!(
+        "Feels Like Outlook => {} (lat: {:.2}, lon: {:.2})\n\n{:<10}{:>6}{:>6}{:>9}{:>7}  {}\n",
+        place, place.latitude, place.longitude, "Time", "Temp", "RH", "Wind", "Feels", "Risk"
+    );
+
+    for hour in data {
+        let feels = hour.feels_like();
+        let line = format!(
+            "{}\n",
+            format!(
+                "{:<10}{:>6}{:>6}{:>9}{:>7}  {}",
+                hour.time.format("%a %I %p"),
+                units.format_temperature(hour.temperature),
+                format_value(hour.humidity.map(f64::round), "%"),
+                units.format_speed(hour.wind_speed),
+                units.format_temperature(feels),
+                feels.map_or("", |x| feels_category(hour, x))
+            )
+            .trim_end()
+        );
+
+        block.push_str(&line);
+    }
+
+    block.push_str(&time_note(data));
+
+    format!("```\n{}```", block)
+}
+
+#[command]
+#[aliases("feels")]
+pub async fn wx_feels(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    send_hours(ctx, msg, &args, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::lib::providers::{
+        fake::{self, Fake},
+        WeatherProvider,
+    };
+
+    fn registry(weather: Vec<Fake>) -> Registry {
+        Registry {
+            weather: weather.into_iter().map(|x| Box::new(x) as Box<dyn WeatherProvider>).collect(),
+            geocode: Vec::new(),
+            uv: Vec::new(),
+        }
+    }
+
+    fn field<'a>(report: &'a Report, name: &str) -> &'a str {
+        match report.fields.iter().find(|x| x.name == name) {
+            Some(field) => &field.value,
+            None => panic!("the report has no {} field", name),
+        }
+    }
+
+    #[tokio::test]
+    async fn formats_current_conditions() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let providers = registry(vec![Fake::working("nws", &log)]);
+        let report = parse_current(&providers, &fake::place(), Units::Imperial).await.unwrap();
+
+        assert!(report.title.starts_with("Current Weather => Denver, CO"));
+        assert_eq!(field(&report, "Temperature"), "72\u{b0}F");
+        assert_eq!(field(&report, "Dew"), "50\u{b0}F");
+        assert_eq!(field(&report, "Humidity"), "46%");
+        assert_eq!(field(&report, "Wind Speed"), "10 mph");
+        assert_eq!(field(&report, "Wind Direction"), "W (270\u{b0})");
+        assert_eq!(field(&report, "Wind Gust"), "18 mph");
+        assert_eq!(field(&report, "Pressure"), "30.12 inHg");
+        assert_eq!(field(&report, "Weather"), "Partly Cloudy");
+        assert_eq!(field(&report, "Visibility"), "10 mi");
+        assert_eq!(field(&report, "Wind Chill"), "-");
+        assert_eq!(field(&report, "Heat Index"), "-");
+        assert_eq!(field(&report, "Feels Like"), "72\u{b0}F");
+        assert_eq!(field(&report, "Cloud Base"), "5000 ft");
+        assert_eq!(report.thumbnail, fake::weather().current.icon);
+    }
+
+    #[tokio::test]
+    async fn converts_current_conditions_to_metric() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let providers = registry(vec![Fake::working("nws", &log)]);
+        let report = parse_current(&providers, &fake::place(), Units::Metric).await.unwrap();
+
+        assert_eq!(field(&report, "Temperature"), "22\u{b0}C");
+        assert_eq!(field(&report, "Wind Speed"), "16 km/h");
+        assert_eq!(field(&report, "Pressure"), "1020 hPa");
+        assert_eq!(field(&report, "Visibility"), "16.1 km");
+        assert_eq!(field(&report, "Cloud Base"), "1524 m");
+    }
+
+    #[tokio::test]
+    async fn uses_the_fallback_provider_for_current_conditions() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let providers =
+            registry(vec![Fake::failing("nws", &log), Fake::working("open-meteo", &log)]);
+
+        assert!(parse_current(&providers, &fake::place(), Units::Imperial).await.is_ok());
+        assert_eq!(*log.lock().unwrap(), vec!["nws: weather", "open-meteo: weather"]);
+
+        let providers = registry(vec![Fake::failing("nws", &log)]);
+
+        assert!(parse_current(&providers, &fake::place(), Units::Imperial).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn formats_forecast_periods() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let providers = registry(vec![Fake::working("nws", &log)]);
+        let report = parse_forecast(&providers, &fake::place(), Units::Imperial).await.unwrap();
+        let names: Vec<&str> = report.fields.iter().map(|x| x.name.as_str()).collect();
+
+        assert!(report.title.starts_with("Weather Forecast => Denver, CO"));
+        assert_eq!(
+            names,
+            vec![
+                "This Afternoon (high: 75\u{b0}F)",
+                "Tonight (low: 52\u{b0}F)",
+                "Monday (high: 78\u{b0}F)",
+                "Monday Night (low: 54\u{b0}F)",
+                "Tuesday (high: 80\u{b0}F)",
+            ]
+        );
+        assert!(report.fields.iter().all(|x| !x.inline));
+        assert_eq!(report.fields[0].value, "Partly sunny, with a high near 75.");
+        assert_eq!(report.footer.as_deref(), Some("Last updated at 12:00 PM (UTC-06:00)"));
+    }
+
+    #[tokio::test]
+    async fn converts_forecast_periods_to_metric() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let providers = registry(vec![Fake::working("open-meteo", &log)]);
+        let report = parse_forecast(&providers, &fake::place(), Units::Metric).await.unwrap();
+
+        assert_eq!(report.fields[0].name, "This Afternoon (high: 24\u{b0}C)");
+    }
+
+    #[test]
+    fn groups_forecast_days() {
+        let data = fake::weather();
+        let days = forecast_days(&data.periods, &fake::hours(), data.updated.date_naive());
+
+        assert_eq!(days.len(), 3);
+        assert_eq!((days[0].high, days[0].low), (Some(75), Some(52)));
+        assert_eq!((days[2].high, days[2].low), (Some(80), Some(55)));
+        assert_eq!(days[0].precipitation, Some(20.0));
+        assert_eq!(days[0].wind_speed, Some(14.0));
+    }
+
+    #[test]
+    fn labels_hours_in_local_time() {
+        let block = parse_hourly(&fake::place(), &fake::hours()[..13], Units::Imperial);
+
+        assert!(block.contains("\nSun 12 PM"));
+        assert!(block.contains("\nMon 12 AM"));
+        assert!(block.contains("Times are local to the location (UTC-06:00)"));
+
+        let block = parse_feels(&fake::place(), &fake::hours()[..1], Units::Imperial);
+
+        assert!(block.contains("\nSun 12 PM"));
+    }
+}
@@ -0,0 +1,169 @@
+use once_cell::sync::Lazy;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Airport {
+    pub ident: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub country: String,
+}
+
+static AIRPORTS: Lazy<Vec<Airport>> = Lazy::new(|| {
+    csv::Reader::from_reader(include_str!("../../data/airports.csv").as_bytes())
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .expect("Error parsing data/airports.csv")
+});
+
+/// Parses the station table up front so a malformed row stops the bot at
+/// startup instead of on the first lookup.
+pub fn load() -> usize {
+    AIRPORTS.len()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, x) in a.chars().enumerate() {
+        let mut prev = row[0];
+
+        row[0] = i + 1;
+
+        for (j, y) in b.iter().enumerate() {
+            let cost = usize::from(x != *y);
+            let val = (row[j + 1] + 1).min(row[j] + 1).min(prev + cost);
+
+            prev = row[j + 1];
+            row[j + 1] = val;
+        }
+    }
+
+    row[b.len()]
+}
+
+pub fn find(ident: &str) -> Option<&'static Airport> {
+    AIRPORTS.iter().find(|x| x.ident == ident)
+}
+
+/// Returns up to three known stations that closely match `ident`. Three-letter
+/// input is also tried as a U.S., Alaskan, Hawaiian or Canadian identifier.
+pub fn suggest(ident: &str) -> Vec<&'static Airport> {
+    if ident.len() == 3 {
+        let matches: Vec<&Airport> =
+            ["K", "P", "C"].iter().filter_map(|x| find(&format!("{x}{ident}"))).collect();
+
+        if !matches.is_empty() {
+            return matches;
+        }
+    }
+
+    let mut matches: Vec<(usize, &Airport)> = AIRPORTS
+        .iter()
+        .map(|x| (edit_distance(ident, &x.ident), x))
+        .filter(|(distance, _)| *distance <= 1)
+        .collect();
+
+    matches.sort_by_key(|(distance, _)| *distance);
+    matches.into_iter().take(3).map(|(_, x)| x).collect()
+}
+
+/// Returns up to `limit` stations whose identifier starts with `query`,
+/// followed by stations whose name contains it.
+pub fn search(query: &str, limit: usize) -> Vec<&'static Airport> {
+    let query = query.trim().to_uppercase();
+
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let idents = AIRPORTS.iter().filter(|x| x.ident.starts_with(&query));
+    let names = AIRPORTS
+        .iter()
+        .filter(|x| !x.ident.starts_with(&query) && x.name.to_uppercase().contains(&query));
+
+    idents.chain(names).take(limit).collect()
+}
+
+/// Great-circle distance in statute miles.
+pub fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+    3958.8 * 2.0 * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Initial great-circle bearing in degrees from the first point to the second.
+pub fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lon = (lon2 - lon1).to_radians();
+    let y = d_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Returns stations within `radius` statute miles sorted by distance, along with
+/// the distance and bearing to each.
+pub fn nearest(lat: f64, lon: f64, radius: f64) -> Vec<(&'static Airport, f64, f64)> {
+    let mut matches: Vec<(&Airport, f64, f64)> = AIRPORTS
+        .iter()
+        .map(|x| {
+            (
+                x,
+                distance(lat, lon, x.latitude, x.longitude),
+                bearing(lat, lon, x.latitude, x.longitude),
+            )
+        })
+        .filter(|(_, distance, _)| *distance <= radius)
+        .collect();
+
+    matches.sort_by(|a, b| a.1.total_cmp(&b.1));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_station_table() {
+        let mut idents: Vec<&str> = AIRPORTS.iter().map(|x| x.ident.as_str()).collect();
+
+        assert_eq!(load(), idents.len());
+        assert!(idents.iter().all(|x| x.len() == 4));
+
+        idents.sort_unstable();
+        idents.dedup();
+
+        assert_eq!(idents.len(), load());
+    }
+
+    #[test]
+    fn finds_general_aviation_stations() {
+        for ident in ["KPTK", "KDET", "KOZW", "KYIP", "KARB", "KSQL", "KMQS"] {
+            assert!(find(ident).is_some(), "{} should be in the station table", ident);
+        }
+    }
+
+    #[test]
+    fn suggests_close_matches() {
+        let idents = |x: Vec<&Airport>| x.iter().map(|x| x.ident.clone()).collect::<Vec<_>>();
+
+        assert_eq!(idents(suggest("SFO")), vec!["KSFO"]);
+        assert!(idents(suggest("KSFP")).contains(&String::from("KSFO")));
+        assert_eq!(idents(search("ksq", 5)), vec!["KSQL"]);
+    }
+
+    #[test]
+    fn finds_nearest_stations() {
+        let nearest = nearest(37.62, -122.38, 15.0);
+
+        assert_eq!(nearest[0].0.ident, "KSFO");
+        assert!(nearest.iter().any(|x| x.0.ident == "KSQL"));
+        assert!((distance(37.619, -122.375, 37.619, -122.375)).abs() < 1e-9);
+    }
+}
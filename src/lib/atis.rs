@@ -0,0 +1,245 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtisKind {
+    Arrival,
+    Departure,
+    Combined,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Atis {
+    pub raw: String,
+    pub kind: AtisKind,
+    pub code: String,
+    pub time: Option<String>,
+    pub weather: Option<String>,
+    pub landing_runways: Vec<String>,
+    pub departing_runways: Vec<String>,
+    pub approaches: Vec<String>,
+    pub notams: Vec<String>,
+    pub remarks: Vec<String>,
+}
+
+fn is_runway(token: &str) -> bool {
+    let digits: String = token.chars().take_while(char::is_ascii_digit).collect();
+    let suffix = &token[digits.len()..];
+
+    (1..=2).contains(&digits.len()) && matches!(suffix, "" | "L" | "R" | "C")
+}
+
+/// Collects runway identifiers that follow a runway keyword (e.g., `RWYS 28L, 28R`).
+fn parse_runways(sentence: &str) -> Vec<String> {
+    let mut runways = Vec::new();
+    let mut listing = false;
+
+    for token in sentence.split_whitespace() {
+        let token = token.trim_matches(|x: char| x == ',' || x == '.');
+
+        if matches!(token, "RWY" | "RWYS" | "RY" | "RYS" | "RUNWAY" | "RUNWAYS") {
+            listing = true;
+        } else if listing && is_runway(token) {
+            let token = token.trim_start_matches('0').to_string();
+
+            if !runways.contains(&token) {
+                runways.push(token);
+            }
+        } else if !matches!(token, "AND" | "&") {
+            listing = false;
+        }
+    }
+
+    runways
+}
+
+fn contains_any(sentence: &str, words: &[&str]) -> bool {
+    sentence.split(|x: char| !x.is_ascii_alphanumeric() && x != '/').any(|x| words.contains(&x))
+}
+
+impl Atis {
+    pub fn parse(kind: &str, code: &str, text: &str) -> Self {
+        let kind = match kind {
+            "arr" => AtisKind::Arrival,
+            "dep" => AtisKind::Departure,
+            _ => AtisKind::Combined,
+        };
+        let mut atis = Self {
+            raw: text.to_string(),
+            kind,
+            code: code.to_uppercase(),
+            time: None,
+            weather: None,
+            landing_runways: Vec::new(),
+            departing_runways: Vec::new(),
+            approaches: Vec::new(),
+            notams: Vec::new(),
+            remarks: Vec::new(),
+        };
+        let text = text.replace("...", ". ");
+        let sentences: Vec<&str> = text
+            .split(". ")
+            .map(|x| x.trim().trim_end_matches('.'))
+            .filter(|x| !x.is_empty())
+            .collect();
+        let mut in_notams = false;
+
+        for (i, sentence) in sentences.iter().enumerate() {
+            let tokens: Vec<&str> = sentence.split_whitespace().collect();
+
+            if i == 0 && tokens.contains(&"INFO") {
+                atis.time =
+                    tokens.iter().find(|x| x.len() == 5 && x.ends_with('Z')).map(|x| x.to_string());
+                continue;
+            }
+
+            if atis.weather.is_none()
+                && tokens.iter().any(|x| x.ends_with("KT") || (x.len() == 5 && x.starts_with('A')))
+                && tokens.iter().any(|x| x.ends_with("SM") || x.contains('/'))
+            {
+                atis.weather = Some(sentence.to_string());
+                continue;
+            }
+
+            if contains_any(sentence, &["ADVS", "ADVISE"]) && sentence.contains("INFO") {
+                continue;
+            }
+
+            if contains_any(sentence, &["NOTAMS", "NOTAM"]) {
+                in_notams = true;
+
+                let rest = sentence
+                    .trim_start_matches("NOTAMS")
+                    .trim_start_matches("NOTAM")
+                    .trim_start_matches(['.', ':'])
+                    .trim();
+
+                if !rest.is_empty() {
+                    atis.notams.push(rest.to_string());
+                }
+                continue;
+            }
+
+            let landing = contains_any(sentence, &["LNDG", "LANDING", "LDG", "ARRIVING", "ARR"]);
+            let departing =
+                contains_any(sentence, &["DEPG", "DEPARTING", "DEP", "DEPARTURE", "DEPARTURES"]);
+            let approach = contains_any(
+                sentence,
+                &["APCH", "APCHS", "APPROACH", "APPROACHES", "ILS", "RNAV", "LOC", "VOR", "GPS"],
+            );
+            let runways = if landing || departing { parse_runways(sentence) } else { Vec::new() };
+
+            for runway in &runways {
+                if landing && !atis.landing_runways.contains(runway) {
+                    atis.landing_runways.push(runway.clone());
+                }
+
+                if departing && !atis.departing_runways.contains(runway) {
+                    atis.departing_runways.push(runway.clone());
+                }
+            }
+
+            if approach {
+                atis.approaches.push(sentence.to_string());
+            } else if !runways.is_empty() {
+                continue;
+            } else if in_notams
+                || contains_any(sentence, &["CLSD", "CLOSED", "OTS", "U/S", "UNUSABLE", "UNAVBL"])
+            {
+                atis.notams.push(sentence.to_string());
+            } else {
+                atis.remarks.push(sentence.to_string());
+            }
+        }
+
+        atis
+    }
+}
+
+impl fmt::Display for AtisKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Arrival => write!(f, "Arrival"),
+            Self::Departure => write!(f, "Departure"),
+            Self::Combined => write!(f, "Combined"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_combined_atis() {
+        let atis = Atis::parse(
+            "combined",
+            "b",
+            "SFO ATIS INFO B 1756Z. 28012KT 10SM FEW008 16/11 A2998 (TWO NINER NINER EIGHT). \
+             SIMUL CHARTED VISUAL FLIGHT PROCEDURES IN USE. ILS RWY 28L APCH IN USE. \
+             LNDG RWYS 28L, 28R. DEPG RWYS 01L, 1R. NOTAMS... TWY A CLSD BTN TWY F AND TWY G. \
+             RWY 10L/28R U/S ALS. ...ADVS YOU HAVE INFO B.",
+        );
+
+        assert_eq!(atis.kind, AtisKind::Combined);
+        assert_eq!(atis.code, "B");
+        assert_eq!(atis.time.as_deref(), Some("1756Z"));
+        assert_eq!(
+            atis.weather.as_deref(),
+            Some("28012KT 10SM FEW008 16/11 A2998 (TWO NINER NINER EIGHT)")
+        );
+        assert_eq!(atis.landing_runways, vec!["28L", "28R"]);
+        assert_eq!(atis.departing_runways, vec!["1L", "1R"]);
+        assert_eq!(atis.approaches, vec!["ILS RWY 28L APCH IN USE"]);
+        assert_eq!(atis.notams, vec!["TWY A CLSD BTN TWY F AND TWY G", "RWY 10L/28R U/S ALS"]);
+        assert_eq!(atis.remarks, vec!["SIMUL CHARTED VISUAL FLIGHT PROCEDURES IN USE"]);
+    }
+
+    #[test]
+    fn parses_arrival_atis() {
+        let atis = Atis::parse(
+            "arr",
+            "K",
+            "DTW ARR INFO K 1853Z. 21010KT 10SM BKN250 22/08 A3012. \
+             ILS OR RNAV APPROACHES IN USE. LANDING RUNWAYS 21L AND 22R. \
+             BIRD ACTIVITY VICINITY ARPT. NOTAMS: TWY Z CLSD. \
+             ADVISE ON INITIAL CONTACT YOU HAVE INFO K.",
+        );
+
+        assert_eq!(atis.kind, AtisKind::Arrival);
+        assert_eq!(atis.time.as_deref(), Some("1853Z"));
+        assert_eq!(atis.landing_runways, vec!["21L", "22R"]);
+        assert!(atis.departing_runways.is_empty());
+        assert_eq!(atis.approaches, vec!["ILS OR RNAV APPROACHES IN USE"]);
+        assert_eq!(atis.notams, vec!["TWY Z CLSD"]);
+        assert_eq!(atis.remarks, vec!["BIRD ACTIVITY VICINITY ARPT"]);
+    }
+
+    #[test]
+    fn parses_departure_atis() {
+        let atis = Atis::parse(
+            "dep",
+            "L",
+            "DTW DEP INFO L 1853Z. 21010KT 10SM BKN250 22/08 A3012. \
+             DEPARTING RUNWAY 22L, 21R. READBACK ALL RUNWAY HOLD SHORT INSTRUCTIONS. \
+             ADVS YOU HAVE INFO L.",
+        );
+
+        assert_eq!(atis.kind, AtisKind::Departure);
+        assert_eq!(atis.departing_runways, vec!["22L", "21R"]);
+        assert!(atis.landing_runways.is_empty());
+        assert!(atis.approaches.is_empty());
+        assert!(atis.notams.is_empty());
+        assert_eq!(atis.remarks, vec!["READBACK ALL RUNWAY HOLD SHORT INSTRUCTIONS"]);
+    }
+
+    #[test]
+    fn matches_runway_identifiers() {
+        assert!(is_runway("4"));
+        assert!(is_runway("28L"));
+        assert!(is_runway("09C"));
+        assert!(!is_runway("280"));
+        assert!(!is_runway("28X"));
+        assert!(!is_runway("TWY"));
+        assert_eq!(parse_runways("LNDG AND DEPG RWY 09 & RWY 4R"), vec!["9", "4R"]);
+    }
+}
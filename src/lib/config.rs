@@ -5,15 +5,44 @@ use crate::lib::error;
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub admin: u64,
+    /// Legacy alert subscribers, imported into subscriptions at startup.
+    #[serde(default)]
     pub alert_users: Vec<u64>,
-    pub alert_zip_codes: Vec<i32>,
+    #[serde(default)]
+    pub alert_zip_codes: Vec<u32>,
     pub debug: bool,
     pub discord: String,
     pub healthcheck: String,
+    #[serde(default)]
+    pub metar_stations: Vec<String>,
     pub openuv: String,
+    #[serde(default)]
+    pub providers: ProviderConfig,
     pub user_agent: String,
+    /// Legacy UV subscribers, imported into subscriptions at startup.
+    #[serde(default)]
     pub uv_users: Vec<u64>,
-    pub uv_zip_codes: Vec<i32>,
+    #[serde(default)]
+    pub uv_zip_codes: Vec<u32>,
+}
+
+/// Provider names for each kind of data, tried in order.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ProviderConfig {
+    pub geocode: Vec<String>,
+    pub uv: Vec<String>,
+    pub weather: Vec<String>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            geocode: vec![String::from("open-meteo")],
+            uv: vec![String::from("openuv")],
+            weather: vec![String::from("nws"), String::from("open-meteo")],
+        }
+    }
 }
 
 impl Config {
@@ -1,4 +1,8 @@
-use serenity::model::channel::Message;
+use serenity::model::{
+    channel::Message,
+    id::{GuildId, UserId},
+    Timestamp,
+};
 use sqlx::{sqlite::SqlitePool, Row};
 
 use crate::error::Error;
@@ -19,6 +23,59 @@ pub struct Location {
     pub timestamp: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct AtisWatch {
+    pub id: i64,
+    pub user_id: String,
+    pub station: String,
+    pub code: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Subscription {
+    pub id: i64,
+    pub user_id: String,
+    pub kind: String,
+    pub location: String,
+    pub time: Option<String>,
+    /// Seconds east of UTC at the location, which daily times are local to.
+    pub utc_offset: Option<i32>,
+    pub filter: Option<String>,
+    pub last_sent: Option<String>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub id: i64,
+    pub location: String,
+    pub alert_id: String,
+    pub event: String,
+    pub severity: String,
+    pub headline: String,
+    pub expires: Option<String>,
+    /// Consecutive polls the alert has been missing from the active alerts.
+    pub missed: i64,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Observation {
+    pub station: String,
+    pub observed: String,
+    pub raw: String,
+    pub category: Option<String>,
+    pub wind_direction: Option<i64>,
+    pub wind_speed: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub visibility: Option<f64>,
+    pub ceiling: Option<i64>,
+    pub temperature: Option<i64>,
+    pub dewpoint: Option<i64>,
+    pub altimeter: Option<f64>,
+}
+
 pub async fn create_log_table(pool: &SqlitePool) -> Result<(), Error> {
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS logs (
@@ -44,6 +101,105 @@ pub async fn create_log_table(pool: &SqlitePool) -> Result<(), Error> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS units (
+            id INTEGER PRIMARY KEY,
+            user_id VARCHAR UNIQUE,
+            units VARCHAR,
+            timestamp TIMESTAMP WITH TIME ZONE
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS guild_settings (
+            id INTEGER PRIMARY KEY,
+            guild_id VARCHAR UNIQUE,
+            output VARCHAR,
+            timestamp TIMESTAMP WITH TIME ZONE
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS atis_watches (
+            id INTEGER PRIMARY KEY,
+            user_id VARCHAR,
+            station VARCHAR,
+            code VARCHAR,
+            timestamp TIMESTAMP WITH TIME ZONE
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS subscriptions (
+            id INTEGER PRIMARY KEY,
+            user_id VARCHAR,
+            kind VARCHAR,
+            location VARCHAR,
+            time VARCHAR,
+            utc_offset INTEGER,
+            filter VARCHAR,
+            last_sent VARCHAR,
+            timestamp TIMESTAMP WITH TIME ZONE,
+            UNIQUE (user_id, kind, location)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS migrations (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR UNIQUE,
+            timestamp TIMESTAMP WITH TIME ZONE
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS alerts (
+            id INTEGER PRIMARY KEY,
+            location VARCHAR,
+            alert_id VARCHAR,
+            event VARCHAR,
+            severity VARCHAR,
+            headline TEXT,
+            expires TIMESTAMP WITH TIME ZONE,
+            missed INTEGER DEFAULT 0,
+            timestamp TIMESTAMP WITH TIME ZONE,
+            UNIQUE (location, alert_id)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS observations (
+            id INTEGER PRIMARY KEY,
+            station VARCHAR,
+            observed TIMESTAMP WITH TIME ZONE,
+            raw TEXT,
+            category VARCHAR,
+            wind_direction INTEGER,
+            wind_speed REAL,
+            wind_gust REAL,
+            visibility REAL,
+            ceiling INTEGER,
+            temperature INTEGER,
+            dewpoint INTEGER,
+            altimeter REAL,
+            UNIQUE (station, observed)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
@@ -88,11 +244,15 @@ pub async fn fetch_log(pool: &SqlitePool) -> Result<Vec<Log>, Error> {
     Ok(v)
 }
 
-pub async fn insert_location(pool: &SqlitePool, msg: &Message, zip_code: i32) -> Result<(), Error> {
+pub async fn insert_location(
+    pool: &SqlitePool,
+    user_id: UserId,
+    location: &str,
+) -> Result<(), Error> {
     let data = Location {
-        user_id: msg.author.id.0.to_string(),
-        zip_code: zip_code.to_string(),
-        timestamp: msg.timestamp.to_string(),
+        user_id: user_id.0.to_string(),
+        zip_code: location.to_string(),
+        timestamp: Timestamp::now().to_string(),
     };
 
     sqlx::query("INSERT INTO locations (user_id, zip_code, timestamp) VALUES (?, ?, ?)")
@@ -105,8 +265,8 @@ pub async fn insert_location(pool: &SqlitePool, msg: &Message, zip_code: i32) ->
     Ok(())
 }
 
-pub async fn fetch_location(pool: &SqlitePool, msg: &Message) -> Result<String, Error> {
-    let user_id = msg.author.id.0.to_string();
+pub async fn fetch_location(pool: &SqlitePool, user_id: UserId) -> Result<String, Error> {
+    let user_id = user_id.0.to_string();
     let row = sqlx::query("SELECT zip_code FROM locations WHERE user_id = ?")
         .bind(user_id)
         .fetch_one(pool)
@@ -116,10 +276,430 @@ pub async fn fetch_location(pool: &SqlitePool, msg: &Message) -> Result<String,
     Ok(zip_code)
 }
 
-pub async fn delete_location(pool: &SqlitePool, msg: &Message) -> Result<(), Error> {
-    let user_id = msg.author.id.0.to_string();
+pub async fn delete_location(pool: &SqlitePool, user_id: UserId) -> Result<(), Error> {
+    let user_id = user_id.0.to_string();
 
     sqlx::query("DELETE FROM locations WHERE user_id = ?").bind(user_id).execute(pool).await?;
 
     Ok(())
 }
+
+pub async fn insert_units(pool: &SqlitePool, user_id: UserId, units: &str) -> Result<(), Error> {
+    sqlx::query("INSERT OR REPLACE INTO units (user_id, units, timestamp) VALUES (?, ?, ?)")
+        .bind(user_id.0.to_string())
+        .bind(units)
+        .bind(Timestamp::now().to_string())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_units(pool: &SqlitePool, user_id: UserId) -> Result<String, Error> {
+    let user_id = user_id.0.to_string();
+    let row = sqlx::query("SELECT units FROM units WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    let units = row.get("units");
+
+    Ok(units)
+}
+
+pub async fn insert_output(
+    pool: &SqlitePool,
+    guild_id: GuildId,
+    output: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO guild_settings (guild_id, output, timestamp) VALUES (?, ?, ?)",
+    )
+    .bind(guild_id.0.to_string())
+    .bind(output)
+    .bind(Timestamp::now().to_string())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_output(pool: &SqlitePool, guild_id: GuildId) -> Result<String, Error> {
+    let row = sqlx::query("SELECT output FROM guild_settings WHERE guild_id = ?")
+        .bind(guild_id.0.to_string())
+        .fetch_one(pool)
+        .await?;
+    let output = row.get("output");
+
+    Ok(output)
+}
+
+pub async fn insert_atis_watch(
+    pool: &SqlitePool,
+    user_id: UserId,
+    station: &str,
+    code: &str,
+) -> Result<(), Error> {
+    let user_id = user_id.0.to_string();
+    let timestamp = Timestamp::now().to_string();
+
+    sqlx::query("INSERT INTO atis_watches (user_id, station, code, timestamp) VALUES (?, ?, ?, ?)")
+        .bind(user_id)
+        .bind(station)
+        .bind(code)
+        .bind(timestamp)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn parse_atis_watch(row: &sqlx::sqlite::SqliteRow) -> AtisWatch {
+    let id: i64 = row.get("id");
+    let user_id: String = row.get("user_id");
+    let station: String = row.get("station");
+    let code: String = row.get("code");
+    let timestamp: String = row.get("timestamp");
+
+    AtisWatch { id, user_id, station, code, timestamp }
+}
+
+pub async fn fetch_atis_watches(pool: &SqlitePool) -> Result<Vec<AtisWatch>, Error> {
+    let rows = sqlx::query("SELECT * FROM atis_watches").fetch_all(pool).await?;
+
+    Ok(rows.iter().map(parse_atis_watch).collect())
+}
+
+pub async fn fetch_user_atis_watches(
+    pool: &SqlitePool,
+    user_id: UserId,
+) -> Result<Vec<AtisWatch>, Error> {
+    let user_id = user_id.0.to_string();
+    let rows = sqlx::query("SELECT * FROM atis_watches WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.iter().map(parse_atis_watch).collect())
+}
+
+pub async fn update_atis_watch(pool: &SqlitePool, id: i64, code: &str) -> Result<(), Error> {
+    sqlx::query("UPDATE atis_watches SET code = ? WHERE id = ?")
+        .bind(code)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_atis_watch(
+    pool: &SqlitePool,
+    user_id: UserId,
+    station: &str,
+) -> Result<(), Error> {
+    let user_id = user_id.0.to_string();
+
+    sqlx::query("DELETE FROM atis_watches WHERE user_id = ? AND station = ?")
+        .bind(user_id)
+        .bind(station)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn insert_subscription(
+    pool: &SqlitePool,
+    user_id: UserId,
+    kind: &str,
+    location: &str,
+    (time, utc_offset): (Option<&str>, Option<i32>),
+    filter: Option<&str>,
+    last_sent: Option<&str>,
+) -> Result<(), Error> {
+    let user_id = user_id.0.to_string();
+    let timestamp = Timestamp::now().to_string();
+
+    sqlx::query(
+        "INSERT INTO subscriptions (user_id, kind, location, time, utc_offset, filter, last_sent,
+            timestamp) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(kind)
+    .bind(location)
+    .bind(time)
+    .bind(utc_offset)
+    .bind(filter)
+    .bind(last_sent)
+    .bind(timestamp)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+fn parse_subscription(row: &sqlx::sqlite::SqliteRow) -> Subscription {
+    Subscription {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        kind: row.get("kind"),
+        location: row.get("location"),
+        time: row.get("time"),
+        utc_offset: row.get("utc_offset"),
+        filter: row.get("filter"),
+        last_sent: row.get("last_sent"),
+        timestamp: row.get("timestamp"),
+    }
+}
+
+pub async fn fetch_subscriptions(
+    pool: &SqlitePool,
+    kind: &str,
+) -> Result<Vec<Subscription>, Error> {
+    let rows = sqlx::query("SELECT * FROM subscriptions WHERE kind = ?")
+        .bind(kind)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.iter().map(parse_subscription).collect())
+}
+
+pub async fn fetch_user_subscriptions(
+    pool: &SqlitePool,
+    user_id: UserId,
+) -> Result<Vec<Subscription>, Error> {
+    let user_id = user_id.0.to_string();
+    let rows = sqlx::query("SELECT * FROM subscriptions WHERE user_id = ? ORDER BY kind, location")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.iter().map(parse_subscription).collect())
+}
+
+pub async fn update_subscription(
+    pool: &SqlitePool,
+    id: i64,
+    time: Option<&str>,
+    utc_offset: Option<i32>,
+    filter: Option<&str>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE subscriptions SET time = ?, utc_offset = ?, filter = ? WHERE id = ?")
+        .bind(time)
+        .bind(utc_offset)
+        .bind(filter)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Records the current UTC offset of a daily subscription's location, which
+/// changes with daylight saving time.
+pub async fn update_subscription_offset(
+    pool: &SqlitePool,
+    id: i64,
+    utc_offset: i32,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE subscriptions SET utc_offset = ? WHERE id = ?")
+        .bind(utc_offset)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Records the local date a daily subscription was last sent.
+pub async fn update_subscription_sent(pool: &SqlitePool, id: i64, date: &str) -> Result<(), Error> {
+    sqlx::query("UPDATE subscriptions SET last_sent = ? WHERE id = ?")
+        .bind(date)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_subscription(pool: &SqlitePool, id: i64) -> Result<(), Error> {
+    sqlx::query("DELETE FROM subscriptions WHERE id = ?").bind(id).execute(pool).await?;
+
+    Ok(())
+}
+
+/// Returns whether a one-time migration, such as importing legacy config
+/// fields, has already run.
+pub async fn has_migration(pool: &SqlitePool, name: &str) -> Result<bool, Error> {
+    let row = sqlx::query("SELECT id FROM migrations WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Records a one-time migration as run so it isn't repeated.
+pub async fn insert_migration(pool: &SqlitePool, name: &str) -> Result<(), Error> {
+    let timestamp = Timestamp::now().to_string();
+
+    sqlx::query("INSERT OR IGNORE INTO migrations (name, timestamp) VALUES (?, ?)")
+        .bind(name)
+        .bind(timestamp)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Records an alert as sent for a location, ignoring alerts already recorded.
+pub async fn insert_alert(
+    pool: &SqlitePool,
+    location: &str,
+    alert_id: &str,
+    event: &str,
+    severity: &str,
+    headline: &str,
+    expires: Option<&str>,
+) -> Result<(), Error> {
+    let timestamp = Timestamp::now().to_string();
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO alerts (location, alert_id, event, severity, headline, expires,
+            timestamp) VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(location)
+    .bind(alert_id)
+    .bind(event)
+    .bind(severity)
+    .bind(headline)
+    .bind(expires)
+    .bind(timestamp)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_alerts(pool: &SqlitePool, location: &str) -> Result<Vec<Alert>, Error> {
+    let rows = sqlx::query("SELECT * FROM alerts WHERE location = ?")
+        .bind(location)
+        .fetch_all(pool)
+        .await?;
+    let mut v = Vec::new();
+
+    for row in rows {
+        let obj = Alert {
+            id: row.get("id"),
+            location: row.get("location"),
+            alert_id: row.get("alert_id"),
+            event: row.get("event"),
+            severity: row.get("severity"),
+            headline: row.get("headline"),
+            expires: row.get("expires"),
+            missed: row.get("missed"),
+            timestamp: row.get("timestamp"),
+        };
+
+        v.push(obj);
+    }
+
+    Ok(v)
+}
+
+pub async fn update_alert_missed(pool: &SqlitePool, id: i64, missed: i64) -> Result<(), Error> {
+    sqlx::query("UPDATE alerts SET missed = ? WHERE id = ?")
+        .bind(missed)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_alert(pool: &SqlitePool, id: i64) -> Result<(), Error> {
+    sqlx::query("DELETE FROM alerts WHERE id = ?").bind(id).execute(pool).await?;
+
+    Ok(())
+}
+
+/// Forgets alerts sent for locations nobody is subscribed to anymore, so a
+/// later subscriber isn't sent expirations for alerts they never saw.
+pub async fn delete_unsubscribed_alerts(pool: &SqlitePool) -> Result<(), Error> {
+    sqlx::query(
+        "DELETE FROM alerts WHERE location NOT IN
+            (SELECT location FROM subscriptions WHERE kind = 'alerts')",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert_observation(pool: &SqlitePool, data: Observation) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO observations (station, observed, raw, category, wind_direction,
+            wind_speed, wind_gust, visibility, ceiling, temperature, dewpoint, altimeter)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(data.station)
+    .bind(data.observed)
+    .bind(data.raw)
+    .bind(data.category)
+    .bind(data.wind_direction)
+    .bind(data.wind_speed)
+    .bind(data.wind_gust)
+    .bind(data.visibility)
+    .bind(data.ceiling)
+    .bind(data.temperature)
+    .bind(data.dewpoint)
+    .bind(data.altimeter)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns archived observations for a station at or after `since` (RFC 3339),
+/// oldest first.
+pub async fn fetch_observations(
+    pool: &SqlitePool,
+    station: &str,
+    since: &str,
+) -> Result<Vec<Observation>, Error> {
+    let rows = sqlx::query(
+        "SELECT * FROM observations WHERE station = ? AND observed >= ? ORDER BY observed",
+    )
+    .bind(station)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    let mut v = Vec::new();
+
+    for row in rows {
+        let obj = Observation {
+            station: row.get("station"),
+            observed: row.get("observed"),
+            raw: row.get("raw"),
+            category: row.get("category"),
+            wind_direction: row.get("wind_direction"),
+            wind_speed: row.get("wind_speed"),
+            wind_gust: row.get("wind_gust"),
+            visibility: row.get("visibility"),
+            ceiling: row.get("ceiling"),
+            temperature: row.get("temperature"),
+            dewpoint: row.get("dewpoint"),
+            altimeter: row.get("altimeter"),
+        };
+
+        v.push(obj);
+    }
+
+    Ok(v)
+}
+
+pub async fn delete_observations(pool: &SqlitePool, before: &str) -> Result<(), Error> {
+    sqlx::query("DELETE FROM observations WHERE observed < ?").bind(before).execute(pool).await?;
+
+    Ok(())
+}
@@ -6,8 +6,9 @@ pub enum Error {
     NotFound(String),
     Io(std::io::Error),
     Json(serde_json::Error),
+    Plot(String),
     Reqwest(reqwest::Error),
-    Serenity(serenity::Error),
+    Serenity(Box<serenity::Error>),
     Sqlx(sqlx::Error),
 }
 
@@ -17,6 +18,7 @@ impl fmt::Display for Error {
             Self::Invalid(ref err) | Self::NotFound(ref err) => write!(f, "{err}"),
             Self::Io(ref err) => write!(f, "{err}"),
             Self::Json(ref err) => write!(f, "{err}"),
+            Self::Plot(ref err) => write!(f, "{err}"),
             Self::Reqwest(ref err) => write!(f, "{err}"),
             Self::Serenity(ref err) => write!(f, "{err}"),
             Self::Sqlx(ref err) => write!(f, "{err}"),
@@ -30,12 +32,26 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Self::Plot(err.to_string())
+    }
+}
+
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
         Self::Json(err)
     }
 }
 
+impl<E: std::error::Error + Send + Sync> From<plotters::drawing::DrawingAreaErrorKind<E>>
+    for Error
+{
+    fn from(err: plotters::drawing::DrawingAreaErrorKind<E>) -> Self {
+        Self::Plot(err.to_string())
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Self::Reqwest(err)
@@ -44,7 +60,7 @@ impl From<reqwest::Error> for Error {
 
 impl From<serenity::Error> for Error {
     fn from(err: serenity::Error) -> Self {
-        Self::Serenity(err)
+        Self::Serenity(Box::new(err))
     }
 }
 
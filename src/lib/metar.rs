@@ -0,0 +1,1149 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use std::fmt;
+
+use crate::lib::{error::Error, units::HPA_PER_IN_HG, utils};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedUnit {
+    Knots,
+    MetersPerSecond,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    LessThan,
+    GreaterThan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservationTime {
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wind {
+    pub direction: Option<u32>,
+    pub speed: u32,
+    pub gust: Option<u32>,
+    pub unit: SpeedUnit,
+    pub variable: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    StatuteMiles { miles: f64, modifier: Option<Modifier> },
+    Meters(u32),
+    Cavok,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunwayVisualRange {
+    pub runway: String,
+    pub low: u32,
+    pub high: Option<u32>,
+    pub modifier: Option<Modifier>,
+    pub feet: bool,
+    pub trend: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+    Vicinity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weather {
+    pub intensity: Intensity,
+    pub descriptor: Option<String>,
+    pub phenomena: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudCover {
+    Clear,
+    Few,
+    Scattered,
+    Broken,
+    Overcast,
+    VerticalVisibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cloud {
+    pub cover: CloudCover,
+    pub base: Option<u32>,
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Altimeter {
+    InHg(f64),
+    Hpa(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FlightCategory {
+    Vfr,
+    Mvfr,
+    Ifr,
+    Lifr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remarks {
+    pub raw: String,
+    pub decoded: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metar {
+    pub raw: String,
+    pub station: String,
+    pub time: Option<ObservationTime>,
+    pub modifier: Option<String>,
+    pub wind: Option<Wind>,
+    pub visibility: Option<Visibility>,
+    pub rvr: Vec<RunwayVisualRange>,
+    pub weather: Vec<Weather>,
+    pub clouds: Vec<Cloud>,
+    pub temperature: Option<i32>,
+    pub dewpoint: Option<i32>,
+    pub altimeter: Option<Altimeter>,
+    /// The TREND forecast appended to international reports (e.g., "TEMPO
+    /// BKN008"), kept apart so it doesn't affect the observed conditions.
+    pub trend: Option<String>,
+    pub remarks: Option<Remarks>,
+}
+
+/// Groups that start a TREND forecast at the end of a report.
+const TREND: [&str; 3] = ["NOSIG", "BECMG", "TEMPO"];
+
+const DESCRIPTORS: [&str; 8] = ["MI", "PR", "BC", "DR", "BL", "SH", "TS", "FZ"];
+
+const PHENOMENA: [&str; 22] = [
+    "DZ", "RA", "SN", "SG", "IC", "PL", "GR", "GS", "UP", "BR", "FG", "FU", "VA", "DU", "SA", "HZ",
+    "PY", "PO", "SQ", "FC", "SS", "DS",
+];
+
+pub fn digits(val: &str) -> Option<u32> {
+    if !val.is_empty() && val.chars().all(|x| x.is_ascii_digit()) {
+        val.parse::<u32>().ok()
+    } else {
+        None
+    }
+}
+
+fn signed(val: &str) -> Option<i32> {
+    match val.strip_prefix('M') {
+        Some(val) => digits(val).map(|x| -(x as i32)),
+        None => digits(val).map(|x| x as i32),
+    }
+}
+
+pub fn thousands(val: u32) -> String {
+    let s = val.to_string();
+    let mut out = String::new();
+
+    for (i, c) in s.chars().enumerate() {
+        if i > 0 && (s.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+pub fn celsius_to_fahrenheit(val: f64) -> f64 {
+    val * 9.0 / 5.0 + 32.0
+}
+
+pub fn parse_time(token: &str) -> Option<ObservationTime> {
+    let body = token.strip_suffix('Z')?;
+
+    if body.len() != 6 {
+        return None;
+    }
+
+    Some(ObservationTime {
+        day: digits(&body[0..2])?,
+        hour: digits(&body[2..4])?,
+        minute: digits(&body[4..6])?,
+    })
+}
+
+pub fn parse_wind(token: &str) -> Option<Wind> {
+    let (body, unit) = if let Some(body) = token.strip_suffix("KT") {
+        (body, SpeedUnit::Knots)
+    } else if let Some(body) = token.strip_suffix("MPS") {
+        (body, SpeedUnit::MetersPerSecond)
+    } else {
+        return None;
+    };
+
+    if body.len() < 5 || !body.is_char_boundary(3) {
+        return None;
+    }
+
+    let (direction, rest) = body.split_at(3);
+    let direction = if direction == "VRB" { None } else { Some(digits(direction)?) };
+    let (speed, gust) = match rest.split_once('G') {
+        Some((speed, gust)) => (digits(speed)?, Some(digits(gust)?)),
+        None => (digits(rest)?, None),
+    };
+
+    Some(Wind { direction, speed, gust, unit, variable: None })
+}
+
+pub fn parse_variable_wind(token: &str) -> Option<(u32, u32)> {
+    let (from, to) = token.split_once('V')?;
+
+    if from.len() == 3 && to.len() == 3 {
+        Some((digits(from)?, digits(to)?))
+    } else {
+        None
+    }
+}
+
+fn parse_fraction(val: &str) -> Option<f64> {
+    match val.split_once('/') {
+        Some((num, den)) => {
+            let den = digits(den)?;
+
+            if den == 0 {
+                None
+            } else {
+                Some(f64::from(digits(num)?) / f64::from(den))
+            }
+        }
+        None => digits(val).map(f64::from),
+    }
+}
+
+/// Parses a visibility group. `whole` is the preceding token when visibility
+/// is split across two groups (e.g., `1 1/2SM`).
+pub fn parse_visibility(token: &str, whole: Option<&str>) -> Option<Visibility> {
+    if token == "CAVOK" {
+        return Some(Visibility::Cavok);
+    }
+
+    if let Some(body) = token.strip_suffix("SM") {
+        let (body, modifier) = if let Some(body) = body.strip_prefix('P') {
+            (body, Some(Modifier::GreaterThan))
+        } else if let Some(body) = body.strip_prefix('M') {
+            (body, Some(Modifier::LessThan))
+        } else {
+            (body, None)
+        };
+        let mut miles = parse_fraction(body)?;
+
+        if let Some(whole) = whole {
+            miles += f64::from(digits(whole)?);
+        }
+
+        return Some(Visibility::StatuteMiles { miles, modifier });
+    }
+
+    let body = token.strip_suffix("NDV").unwrap_or(token);
+
+    if body.len() == 4 {
+        digits(body).map(Visibility::Meters)
+    } else {
+        None
+    }
+}
+
+pub fn parse_rvr(token: &str) -> Option<RunwayVisualRange> {
+    let body = token.strip_prefix('R')?;
+    let (runway, rest) = body.split_once('/')?;
+
+    if runway.len() < 2 || !runway.is_ascii() || digits(&runway[0..2]).is_none() {
+        return None;
+    }
+
+    let (rest, trend) = match rest.split_once('/') {
+        Some((rest, trend)) => (rest, trend.chars().next()),
+        None => match rest.chars().last() {
+            Some(c) if matches!(c, 'U' | 'D' | 'N') => (&rest[..rest.len() - 1], Some(c)),
+            _ => (rest, None),
+        },
+    };
+    let (rest, feet) = match rest.strip_suffix("FT") {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    let (rest, modifier) = if let Some(rest) = rest.strip_prefix('P') {
+        (rest, Some(Modifier::GreaterThan))
+    } else if let Some(rest) = rest.strip_prefix('M') {
+        (rest, Some(Modifier::LessThan))
+    } else {
+        (rest, None)
+    };
+    let (low, high) = match rest.split_once('V') {
+        Some((low, high)) => (digits(low)?, Some(digits(high.trim_start_matches('P'))?)),
+        None => (digits(rest)?, None),
+    };
+
+    Some(RunwayVisualRange { runway: runway.to_string(), low, high, modifier, feet, trend })
+}
+
+pub fn parse_weather(token: &str) -> Option<Weather> {
+    let (rest, intensity) = if let Some(rest) = token.strip_prefix('-') {
+        (rest, Intensity::Light)
+    } else if let Some(rest) = token.strip_prefix('+') {
+        (rest, Intensity::Heavy)
+    } else if let Some(rest) = token.strip_prefix("VC") {
+        (rest, Intensity::Vicinity)
+    } else {
+        (token, Intensity::Moderate)
+    };
+
+    if rest.is_empty() || rest.len() % 2 != 0 || !rest.is_ascii() {
+        return None;
+    }
+
+    let (descriptor, rest) = if DESCRIPTORS.contains(&&rest[0..2]) {
+        (Some(rest[0..2].to_string()), &rest[2..])
+    } else {
+        (None, rest)
+    };
+    let mut phenomena = Vec::new();
+
+    for i in (0..rest.len()).step_by(2) {
+        let code = &rest[i..i + 2];
+
+        if PHENOMENA.contains(&code) {
+            phenomena.push(code.to_string());
+        } else {
+            return None;
+        }
+    }
+
+    if descriptor.is_none() && phenomena.is_empty() {
+        None
+    } else {
+        Some(Weather { intensity, descriptor, phenomena })
+    }
+}
+
+pub fn parse_cloud(token: &str) -> Option<Cloud> {
+    if matches!(token, "SKC" | "CLR" | "NSC" | "NCD") {
+        return Some(Cloud { cover: CloudCover::Clear, base: None, kind: None });
+    }
+
+    let (cover, rest) = if let Some(rest) = token.strip_prefix("FEW") {
+        (CloudCover::Few, rest)
+    } else if let Some(rest) = token.strip_prefix("SCT") {
+        (CloudCover::Scattered, rest)
+    } else if let Some(rest) = token.strip_prefix("BKN") {
+        (CloudCover::Broken, rest)
+    } else if let Some(rest) = token.strip_prefix("OVC") {
+        (CloudCover::Overcast, rest)
+    } else if let Some(rest) = token.strip_prefix("VV") {
+        (CloudCover::VerticalVisibility, rest)
+    } else {
+        return None;
+    };
+
+    if rest.len() < 3 || !rest.is_char_boundary(3) {
+        return None;
+    }
+
+    let (height, kind) = rest.split_at(3);
+    let base = if height == "///" { None } else { Some(digits(height)? * 100) };
+    let kind = match kind {
+        "" | "///" => None,
+        "CB" | "TCU" => Some(kind.to_string()),
+        _ => return None,
+    };
+
+    Some(Cloud { cover, base, kind })
+}
+
+fn parse_temperature(token: &str) -> Option<(i32, Option<i32>)> {
+    let (temp, dew) = token.split_once('/')?;
+    let temp = signed(temp)?;
+    let dew = if dew.is_empty() || dew == "//" { None } else { Some(signed(dew)?) };
+
+    Some((temp, dew))
+}
+
+pub fn parse_altimeter(token: &str) -> Option<Altimeter> {
+    if token.len() != 5 {
+        return None;
+    }
+
+    if let Some(val) = token.strip_prefix('A') {
+        digits(val).map(|x| Altimeter::InHg(f64::from(x) / 100.0))
+    } else if let Some(val) = token.strip_prefix('Q') {
+        digits(val).map(Altimeter::Hpa)
+    } else {
+        None
+    }
+}
+
+fn tenths(sign: &str, val: &str) -> Option<f64> {
+    let val = f64::from(digits(val)?) / 10.0;
+
+    match sign {
+        "0" => Some(val),
+        "1" => Some(-val),
+        _ => None,
+    }
+}
+
+fn format_celsius(val: f64) -> String {
+    format!("{val:.1}\u{b0}C ({:.0}\u{b0}F)", celsius_to_fahrenheit(val))
+}
+
+fn parse_remarks(tokens: &[&str]) -> Remarks {
+    let mut decoded = Vec::new();
+    let mut other = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = tokens[i];
+
+        match token {
+            "AO1" => decoded.push("Automated station without precipitation sensor".to_string()),
+            "AO2" => decoded.push("Automated station with precipitation sensor".to_string()),
+            "PRESRR" => decoded.push("Pressure rising rapidly".to_string()),
+            "PRESFR" => decoded.push("Pressure falling rapidly".to_string()),
+            "SLPNO" => decoded.push("Sea-level pressure not available".to_string()),
+            "RVRNO" => decoded.push("Runway visual range not available".to_string()),
+            "PWINO" => decoded.push("Present weather sensor not available".to_string()),
+            "PNO" => decoded.push("Precipitation gauge not available".to_string()),
+            "FZRANO" => decoded.push("Freezing rain sensor not available".to_string()),
+            "TSNO" => decoded.push("Lightning detector not available".to_string()),
+            "VISNO" => decoded.push("Visibility sensor not available".to_string()),
+            "$" => decoded.push("Station requires maintenance".to_string()),
+            "PK" if tokens.get(i + 1) == Some(&"WND") && i + 2 < tokens.len() => {
+                let val = tokens[i + 2];
+
+                match val.split_once('/') {
+                    Some((wind, time)) if wind.len() >= 5 => {
+                        let time = &time[time.len().saturating_sub(2)..];
+
+                        decoded.push(format!(
+                            "Peak wind {}\u{b0} at {} kt at :{}",
+                            &wind[0..3],
+                            &wind[3..],
+                            time
+                        ));
+                    }
+                    _ => other.push(format!("PK WND {val}")),
+                }
+                i += 2;
+            }
+            "WSHFT" if i + 1 < tokens.len() => {
+                let time = tokens[i + 1];
+                let time = if time.len() == 4 {
+                    format!("{}:{}", &time[0..2], &time[2..4])
+                } else {
+                    format!(":{time}")
+                };
+
+                decoded.push(format!("Wind shift at {time}"));
+                i += 1;
+            }
+            _ => {
+                if let Some(val) = token.strip_prefix("SLP").and_then(digits) {
+                    let val = f64::from(val) / 10.0;
+                    let val = if val < 50.0 { 1000.0 + val } else { 900.0 + val };
+
+                    decoded.push(format!("Sea-level pressure {val:.1} hPa"));
+                } else if token.len() == 9
+                    && token.starts_with('T')
+                    && digits(&token[1..]).is_some()
+                {
+                    match (tenths(&token[1..2], &token[2..5]), tenths(&token[5..6], &token[6..9])) {
+                        (Some(temp), Some(dew)) => decoded.push(format!(
+                            "Temperature {}, dew point {}",
+                            format_celsius(temp),
+                            format_celsius(dew)
+                        )),
+                        _ => other.push(token.to_string()),
+                    }
+                } else if token.len() == 5
+                    && token.starts_with('P')
+                    && digits(&token[1..]).is_some()
+                {
+                    let val = f64::from(digits(&token[1..]).unwrap_or(0)) / 100.0;
+
+                    decoded.push(format!("Precipitation last hour {val:.2} in"));
+                } else if token.len() == 5 && digits(token).is_some() {
+                    let val = &token[1..];
+
+                    match &token[0..1] {
+                        "1" | "2" => match tenths(&val[0..1], &val[1..4]) {
+                            Some(temp) => decoded.push(format!(
+                                "6-hour {} temperature {}",
+                                if token.starts_with('1') { "maximum" } else { "minimum" },
+                                format_celsius(temp)
+                            )),
+                            None => other.push(token.to_string()),
+                        },
+                        "5" => {
+                            let tendency = match &val[0..1] {
+                                "0" | "1" | "2" | "3" => "increased",
+                                "4" => "steady",
+                                _ => "decreased",
+                            };
+                            let change = f64::from(digits(&val[1..]).unwrap_or(0)) / 10.0;
+
+                            decoded.push(format!(
+                                "3-hour pressure tendency {tendency} by {change:.1} hPa"
+                            ));
+                        }
+                        "6" => {
+                            let val = f64::from(digits(val).unwrap_or(0)) / 100.0;
+
+                            decoded.push(format!("3/6-hour precipitation {val:.2} in"));
+                        }
+                        "7" => {
+                            let val = f64::from(digits(val).unwrap_or(0)) / 100.0;
+
+                            decoded.push(format!("24-hour precipitation {val:.2} in"));
+                        }
+                        _ => other.push(token.to_string()),
+                    }
+                } else if token.len() == 9 && token.starts_with('4') && digits(token).is_some() {
+                    match (tenths(&token[1..2], &token[2..5]), tenths(&token[5..6], &token[6..9])) {
+                        (Some(max), Some(min)) => decoded.push(format!(
+                            "24-hour maximum temperature {}, minimum {}",
+                            format_celsius(max),
+                            format_celsius(min)
+                        )),
+                        _ => other.push(token.to_string()),
+                    }
+                } else {
+                    other.push(token.to_string());
+                }
+            }
+        }
+
+        i += 1;
+    }
+
+    if !other.is_empty() {
+        decoded.push(format!("Other: {}", other.join(" ")));
+    }
+
+    Remarks { raw: tokens.join(" "), decoded }
+}
+
+impl Metar {
+    pub fn parse(raw: &str) -> Result<Self, Error> {
+        let raw = raw.trim().trim_end_matches('=').to_string();
+        let mut tokens: Vec<&str> = raw.split_whitespace().collect();
+
+        if matches!(tokens.first(), Some(&"METAR") | Some(&"SPECI")) {
+            tokens.remove(0);
+        }
+
+        let station = match tokens.first() {
+            Some(val) if val.len() == 4 && val.chars().all(|x| x.is_ascii_alphanumeric()) => {
+                val.to_string()
+            }
+            _ => return Err(Error::Invalid("The METAR report could not be decoded".into())),
+        };
+        let (body, remarks) = match tokens.iter().position(|x| *x == "RMK") {
+            Some(i) => (&tokens[1..i], Some(parse_remarks(&tokens[i + 1..]))),
+            None => (&tokens[1..], None),
+        };
+        let (body, trend) = match body.iter().position(|x| TREND.contains(x)) {
+            Some(i) => (&body[..i], Some(body[i..].join(" "))),
+            None => (body, None),
+        };
+        let mut metar = Self {
+            raw: raw.clone(),
+            station,
+            time: None,
+            modifier: None,
+            wind: None,
+            visibility: None,
+            rvr: Vec::new(),
+            weather: Vec::new(),
+            clouds: Vec::new(),
+            temperature: None,
+            dewpoint: None,
+            altimeter: None,
+            trend,
+            remarks,
+        };
+        let mut i = 0;
+
+        while i < body.len() {
+            let token = body[i];
+
+            if metar.time.is_none() && parse_time(token).is_some() {
+                metar.time = parse_time(token);
+            } else if matches!(token, "AUTO" | "COR") {
+                metar.modifier = Some(token.to_string());
+            } else if metar.wind.is_none() && parse_wind(token).is_some() {
+                metar.wind = parse_wind(token);
+            } else if let (Some(wind), Some(variable)) =
+                (metar.wind.as_mut(), parse_variable_wind(token))
+            {
+                wind.variable = Some(variable);
+            } else if metar.visibility.is_none()
+                && digits(token).is_some()
+                && token.len() == 1
+                && body.get(i + 1).is_some_and(|x| x.ends_with("SM") && x.contains('/'))
+            {
+                metar.visibility = parse_visibility(body[i + 1], Some(token));
+                i += 1;
+            } else if metar.visibility.is_none() && parse_visibility(token, None).is_some() {
+                metar.visibility = parse_visibility(token, None);
+            } else if let Some(rvr) = parse_rvr(token) {
+                metar.rvr.push(rvr);
+            } else if let Some(cloud) = parse_cloud(token) {
+                metar.clouds.push(cloud);
+            } else if let Some(weather) = parse_weather(token) {
+                metar.weather.push(weather);
+            } else if let Some((temp, dew)) = parse_temperature(token) {
+                metar.temperature = Some(temp);
+                metar.dewpoint = dew;
+            } else if let Some(altimeter) = parse_altimeter(token) {
+                metar.altimeter = Some(altimeter);
+            }
+
+            i += 1;
+        }
+
+        Ok(metar)
+    }
+}
+
+impl ObservationTime {
+    /// Resolves the day/time group to a full timestamp, choosing the month
+    /// closest to `reference`.
+    pub fn resolve(&self, reference: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let month = reference.year() * 12 + reference.month0() as i32;
+
+        (month - 1..=month + 1)
+            .filter_map(|x| {
+                let date = NaiveDate::from_ymd_opt(
+                    x.div_euclid(12),
+                    x.rem_euclid(12) as u32 + 1,
+                    self.day,
+                )?;
+                let time =
+                    date.and_hms_opt(0, self.minute, 0)? + Duration::hours(i64::from(self.hour));
+
+                Some(Utc.from_utc_datetime(&time))
+            })
+            .min_by_key(|x| (*x - reference).num_seconds().abs())
+    }
+}
+
+impl Wind {
+    /// Converts a speed reported in this group's unit to knots.
+    pub fn knots(&self, val: u32) -> f64 {
+        match self.unit {
+            SpeedUnit::Knots => f64::from(val),
+            SpeedUnit::MetersPerSecond => f64::from(val) * 1.943_84,
+        }
+    }
+}
+
+impl Metar {
+    pub fn ceiling(&self) -> Option<u32> {
+        ceiling(&self.clouds)
+    }
+
+    pub fn flight_category(&self) -> Option<FlightCategory> {
+        FlightCategory::from_conditions(
+            self.ceiling(),
+            self.visibility.map(|x| x.statute_miles()),
+            !self.clouds.is_empty(),
+        )
+    }
+}
+
+impl Visibility {
+    pub fn statute_miles(&self) -> f64 {
+        match *self {
+            Self::StatuteMiles { miles, .. } => miles,
+            Self::Meters(val) => f64::from(val) / 1609.344,
+            Self::Cavok => 10.0,
+        }
+    }
+}
+
+impl FlightCategory {
+    /// Computes the FAA flight category from the ceiling (feet) and visibility
+    /// (statute miles). `has_clouds` indicates whether sky condition was reported
+    /// so a missing ceiling can be treated as unlimited.
+    pub fn from_conditions(
+        ceiling: Option<u32>,
+        visibility: Option<f64>,
+        has_clouds: bool,
+    ) -> Option<Self> {
+        if visibility.is_none() && !has_clouds {
+            return None;
+        }
+
+        let by_ceiling = match ceiling {
+            Some(val) if val < 500 => Self::Lifr,
+            Some(val) if val < 1000 => Self::Ifr,
+            Some(val) if val <= 3000 => Self::Mvfr,
+            _ => Self::Vfr,
+        };
+        let by_visibility = match visibility {
+            Some(val) if val < 1.0 => Self::Lifr,
+            Some(val) if val < 3.0 => Self::Ifr,
+            Some(val) if val <= 5.0 => Self::Mvfr,
+            _ => Self::Vfr,
+        };
+
+        Some(by_ceiling.max(by_visibility))
+    }
+}
+
+/// Returns the height of the lowest broken, overcast or obscured layer.
+pub fn ceiling(clouds: &[Cloud]) -> Option<u32> {
+    clouds
+        .iter()
+        .filter(|x| {
+            matches!(
+                x.cover,
+                CloudCover::Broken | CloudCover::Overcast | CloudCover::VerticalVisibility
+            )
+        })
+        .filter_map(|x| x.base)
+        .min()
+}
+
+impl Altimeter {
+    pub fn in_hg(&self) -> f64 {
+        match *self {
+            Self::InHg(val) => val,
+            Self::Hpa(val) => f64::from(val) / HPA_PER_IN_HG,
+        }
+    }
+
+    pub fn hpa(&self) -> f64 {
+        match *self {
+            Self::InHg(val) => val * HPA_PER_IN_HG,
+            Self::Hpa(val) => f64::from(val),
+        }
+    }
+}
+
+impl fmt::Display for ObservationTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "day {} at {:02}:{:02}Z", self.day, self.hour, self.minute)
+    }
+}
+
+impl fmt::Display for FlightCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Vfr => write!(f, "VFR"),
+            Self::Mvfr => write!(f, "MVFR"),
+            Self::Ifr => write!(f, "IFR"),
+            Self::Lifr => write!(f, "LIFR"),
+        }
+    }
+}
+
+impl fmt::Display for SpeedUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Knots => write!(f, "kt"),
+            Self::MetersPerSecond => write!(f, "m/s"),
+        }
+    }
+}
+
+impl fmt::Display for Wind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.speed == 0 && self.gust.is_none() {
+            return write!(f, "Calm");
+        }
+
+        match self.direction {
+            Some(val) => write!(
+                f,
+                "From {val:03}\u{b0} ({}) at {} {}",
+                utils::cardinal_direction(f64::from(val)),
+                self.speed,
+                self.unit
+            )?,
+            None => write!(f, "Variable at {} {}", self.speed, self.unit)?,
+        }
+
+        if let Some(gust) = self.gust {
+            write!(f, ", gusting {gust} {}", self.unit)?;
+        }
+
+        if let Some((from, to)) = self.variable {
+            write!(f, ", varying {from:03}\u{b0} to {to:03}\u{b0}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::StatuteMiles { miles, modifier } => {
+                let prefix = match modifier {
+                    Some(Modifier::LessThan) => "Less than ",
+                    Some(Modifier::GreaterThan) => "Greater than ",
+                    None => "",
+                };
+                let miles = if miles.fract() == 0.0 {
+                    format!("{miles:.0}")
+                } else {
+                    format!("{}", (miles * 100.0).round() / 100.0)
+                };
+                let unit = if miles == "1" { "statute mile" } else { "statute miles" };
+
+                write!(f, "{prefix}{miles} {unit}")
+            }
+            Self::Meters(9999) => write!(f, "10 km or more"),
+            Self::Meters(val) => write!(f, "{} m", thousands(val)),
+            Self::Cavok => write!(f, "10 km or more, no significant clouds (CAVOK)"),
+        }
+    }
+}
+
+impl fmt::Display for RunwayVisualRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = if self.feet { "ft" } else { "m" };
+        let prefix = match self.modifier {
+            Some(Modifier::LessThan) => "less than ",
+            Some(Modifier::GreaterThan) => "more than ",
+            None => "",
+        };
+
+        write!(f, "Runway {}: {prefix}{}", self.runway, thousands(self.low))?;
+
+        if let Some(high) = self.high {
+            write!(f, " to {}", thousands(high))?;
+        }
+
+        write!(f, " {unit}")?;
+
+        match self.trend {
+            Some('U') => write!(f, ", rising"),
+            Some('D') => write!(f, ", falling"),
+            Some('N') => write!(f, ", no change"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn descriptor_name(code: &str) -> &'static str {
+    match code {
+        "MI" => "shallow",
+        "PR" => "partial",
+        "BC" => "patches of",
+        "DR" => "low drifting",
+        "BL" => "blowing",
+        "FZ" => "freezing",
+        _ => "",
+    }
+}
+
+fn phenomenon_name(code: &str, intensity: Intensity) -> &'static str {
+    match code {
+        "DZ" => "drizzle",
+        "RA" => "rain",
+        "SN" => "snow",
+        "SG" => "snow grains",
+        "IC" => "ice crystals",
+        "PL" => "ice pellets",
+        "GR" => "hail",
+        "GS" => "small hail",
+        "UP" => "unknown precipitation",
+        "BR" => "mist",
+        "FG" => "fog",
+        "FU" => "smoke",
+        "VA" => "volcanic ash",
+        "DU" => "widespread dust",
+        "SA" => "sand",
+        "HZ" => "haze",
+        "PY" => "spray",
+        "PO" => "dust whirls",
+        "SQ" => "squalls",
+        "FC" if intensity == Intensity::Heavy => "tornado or waterspout",
+        "FC" => "funnel cloud",
+        "SS" => "sandstorm",
+        "DS" => "duststorm",
+        _ => "",
+    }
+}
+
+impl fmt::Display for Weather {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phenomena: Vec<&str> =
+            self.phenomena.iter().map(|x| phenomenon_name(x, self.intensity)).collect();
+        let phenomena = phenomena.join(" and ");
+        let intensity = match self.intensity {
+            Intensity::Light => "light ",
+            Intensity::Heavy if !self.phenomena.contains(&"FC".to_string()) => "heavy ",
+            _ => "",
+        };
+        let text = match self.descriptor.as_deref() {
+            Some("TS") if phenomena.is_empty() => String::from("thunderstorm"),
+            Some("TS") => format!("thunderstorm with {intensity}{phenomena}"),
+            Some("SH") if phenomena.is_empty() => String::from("showers"),
+            Some("SH") => format!("{intensity}{phenomena} showers"),
+            Some(code) => format!("{intensity}{} {phenomena}", descriptor_name(code)),
+            None => format!("{intensity}{phenomena}"),
+        };
+
+        if self.intensity == Intensity::Vicinity {
+            write!(f, "{} in the vicinity", text.trim())
+        } else {
+            write!(f, "{}", text.trim())
+        }
+    }
+}
+
+impl fmt::Display for CloudCover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Clear => write!(f, "Clear"),
+            Self::Few => write!(f, "Few"),
+            Self::Scattered => write!(f, "Scattered"),
+            Self::Broken => write!(f, "Broken"),
+            Self::Overcast => write!(f, "Overcast"),
+            Self::VerticalVisibility => write!(f, "Vertical visibility"),
+        }
+    }
+}
+
+impl fmt::Display for Cloud {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.cover, self.base) {
+            (CloudCover::Clear, _) => write!(f, "Clear")?,
+            (cover, Some(base)) => write!(f, "{cover} at {} ft", thousands(base))?,
+            (cover, None) => write!(f, "{cover}, height unknown")?,
+        }
+
+        match self.kind.as_deref() {
+            Some("CB") => write!(f, " (cumulonimbus)"),
+            Some("TCU") => write!(f, " (towering cumulus)"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Altimeter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} inHg ({:.0} hPa)", self.in_hg(), self.hpa())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> Metar {
+        Metar::parse(raw).expect("report should parse")
+    }
+
+    #[test]
+    fn parses_basic_report() {
+        let metar = parse("METAR KDEN 121853Z 36010KT 10SM FEW100 SCT200 22/08 A3012 RMK AO2");
+
+        assert_eq!(metar.station, "KDEN");
+        assert_eq!(metar.time, Some(ObservationTime { day: 12, hour: 18, minute: 53 }));
+        assert_eq!(metar.wind.as_ref().and_then(|x| x.direction), Some(360));
+        assert_eq!(metar.clouds.len(), 2);
+        assert_eq!(metar.temperature, Some(22));
+        assert_eq!(metar.dewpoint, Some(8));
+        assert_eq!(metar.altimeter, Some(Altimeter::InHg(30.12)));
+        assert_eq!(metar.flight_category(), Some(FlightCategory::Vfr));
+    }
+
+    #[test]
+    fn parses_variable_wind() {
+        let metar = parse("KDEN 121853Z VRB03KT 10SM CLR 22/M03 A3012");
+        let wind = metar.wind.expect("wind should parse");
+
+        assert_eq!(wind.direction, None);
+        assert_eq!(wind.speed, 3);
+        assert_eq!(wind.to_string(), "Variable at 3 kt");
+
+        let metar = parse("KSFO 121856Z 28015G25KT 250V310 10SM FEW008 16/11 A2998");
+        let wind = metar.wind.expect("wind should parse");
+
+        assert_eq!(wind.direction, Some(280));
+        assert_eq!(wind.gust, Some(25));
+        assert_eq!(wind.variable, Some((250, 310)));
+        assert_eq!(
+            wind.to_string(),
+            "From 280\u{b0} (W) at 15 kt, gusting 25 kt, varying 250\u{b0} to 310\u{b0}"
+        );
+    }
+
+    #[test]
+    fn parses_gusts_in_meters_per_second() {
+        let wind = parse_wind("24012G22MPS").expect("wind should parse");
+
+        assert_eq!(wind.unit, SpeedUnit::MetersPerSecond);
+        assert_eq!(wind.gust, Some(22));
+        assert!((wind.knots(wind.speed) - 23.33).abs() < 0.01);
+        assert_eq!(
+            parse("KBOS 121854Z 00000KT 10SM CLR 09/01 A3001").wind.unwrap().to_string(),
+            "Calm"
+        );
+    }
+
+    #[test]
+    fn parses_split_fraction_visibility() {
+        let metar = parse("KORD 121851Z 18012KT 1 1/2SM -RA BR OVC008 12/11 A2990");
+
+        assert_eq!(metar.visibility, Some(Visibility::StatuteMiles { miles: 1.5, modifier: None }));
+        assert_eq!(metar.weather.len(), 2);
+        assert_eq!(metar.weather[0].to_string(), "light rain");
+        assert_eq!(metar.weather[1].to_string(), "mist");
+        assert_eq!(metar.ceiling(), Some(800));
+        assert_eq!(metar.flight_category(), Some(FlightCategory::Ifr));
+    }
+
+    #[test]
+    fn parses_less_than_visibility_and_rvr() {
+        let metar =
+            parse("KBOS 121854Z 00000KT M1/4SM R04R/1200V2400FT FG VV001 09/09 A3001 RMK AO2");
+
+        assert_eq!(
+            metar.visibility,
+            Some(Visibility::StatuteMiles { miles: 0.25, modifier: Some(Modifier::LessThan) })
+        );
+        assert_eq!(metar.visibility.unwrap().to_string(), "Less than 0.25 statute miles");
+        assert_eq!(metar.rvr.len(), 1);
+        assert_eq!(metar.rvr[0].low, 1200);
+        assert_eq!(metar.rvr[0].high, Some(2400));
+        assert!(metar.rvr[0].feet);
+        assert_eq!(metar.rvr[0].to_string(), "Runway 04R: 1,200 to 2,400 ft");
+        assert_eq!(metar.clouds[0].cover, CloudCover::VerticalVisibility);
+        assert_eq!(metar.flight_category(), Some(FlightCategory::Lifr));
+    }
+
+    #[test]
+    fn parses_metric_report() {
+        let metar = parse("EGLL 121850Z 24008KT 0300 R27L/P1500U FG OVC001 05/05 Q1021");
+
+        assert_eq!(metar.visibility, Some(Visibility::Meters(300)));
+        assert_eq!(metar.rvr[0].modifier, Some(Modifier::GreaterThan));
+        assert_eq!(metar.rvr[0].trend, Some('U'));
+        assert_eq!(metar.rvr[0].to_string(), "Runway 27L: more than 1,500 m, rising");
+        assert_eq!(metar.altimeter, Some(Altimeter::Hpa(1021)));
+        assert!((metar.altimeter.unwrap().in_hg() - 30.15).abs() < 0.01);
+
+        let metar = parse("LFPG 121830Z 20005KT CAVOK 18/09 Q1018");
+
+        assert_eq!(metar.visibility, Some(Visibility::Cavok));
+        assert_eq!(metar.flight_category(), Some(FlightCategory::Vfr));
+    }
+
+    #[test]
+    fn parses_negative_temperatures() {
+        let metar = parse("PANC 121853Z 02005KT 10SM FEW045 M12/M18 A2982");
+
+        assert_eq!(metar.temperature, Some(-12));
+        assert_eq!(metar.dewpoint, Some(-18));
+
+        let metar = parse("PANC 121853Z 02005KT 10SM FEW045 M01/ A2982");
+
+        assert_eq!(metar.temperature, Some(-1));
+        assert_eq!(metar.dewpoint, None);
+    }
+
+    #[test]
+    fn decodes_remarks() {
+        let metar = parse(
+            "KSFO 121856Z 28015G25KT 10SM FEW008 16/11 A2998 RMK AO2 PK WND 29030/1820 \
+             SLP152 T01560106 10161 20139 53012 $",
+        );
+        let remarks = metar.remarks.expect("remarks should parse");
+
+        assert_eq!(
+            remarks.decoded,
+            vec![
+                "Automated station with precipitation sensor",
+                "Peak wind 290\u{b0} at 30 kt at :20",
+                "Sea-level pressure 1015.2 hPa",
+                "Temperature 15.6\u{b0}C (60\u{b0}F), dew point 10.6\u{b0}C (51\u{b0}F)",
+                "6-hour maximum temperature 16.1\u{b0}C (61\u{b0}F)",
+                "6-hour minimum temperature 13.9\u{b0}C (57\u{b0}F)",
+                "3-hour pressure tendency increased by 1.2 hPa",
+                "Station requires maintenance",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_remarks_out_of_the_body() {
+        let metar = parse("KJFK 121851Z 31008KT 10SM BKN250 20/05 RMK AO2 A3001 FEW005 SLP984");
+        let remarks = metar.remarks.expect("remarks should parse");
+
+        assert_eq!(metar.altimeter, None);
+        assert_eq!(metar.clouds.len(), 1);
+        assert_eq!(remarks.decoded.last().unwrap(), "Other: A3001 FEW005");
+        assert!(remarks.decoded.contains(&String::from("Sea-level pressure 998.4 hPa")));
+    }
+
+    #[test]
+    fn keeps_trends_out_of_the_observation() {
+        let metar = parse("EGLL 121150Z 24012KT 9999 BKN040 15/09 Q1012 TEMPO BKN008 RA");
+
+        assert_eq!(metar.clouds.len(), 1);
+        assert!(metar.weather.is_empty());
+        assert_eq!(metar.ceiling(), Some(4000));
+        assert_eq!(metar.flight_category(), Some(FlightCategory::Vfr));
+        assert_eq!(metar.trend.as_deref(), Some("TEMPO BKN008 RA"));
+
+        let metar = parse("EDDF 121150Z 27008KT CAVOK 18/06 Q1015 NOSIG");
+
+        assert_eq!(metar.trend.as_deref(), Some("NOSIG"));
+        assert!(metar.altimeter.is_some());
+    }
+
+    #[test]
+    fn rejects_invalid_reports() {
+        assert!(Metar::parse("").is_err());
+        assert!(Metar::parse("NOT A REPORT").is_err());
+    }
+
+    #[test]
+    fn computes_flight_category_boundaries() {
+        let category = |ceiling, visibility| {
+            FlightCategory::from_conditions(ceiling, Some(visibility), true).unwrap()
+        };
+
+        assert_eq!(category(Some(499), 10.0), FlightCategory::Lifr);
+        assert_eq!(category(Some(500), 10.0), FlightCategory::Ifr);
+        assert_eq!(category(Some(999), 10.0), FlightCategory::Ifr);
+        assert_eq!(category(Some(1000), 10.0), FlightCategory::Mvfr);
+        assert_eq!(category(Some(3000), 10.0), FlightCategory::Mvfr);
+        assert_eq!(category(Some(3100), 10.0), FlightCategory::Vfr);
+        assert_eq!(category(None, 0.75), FlightCategory::Lifr);
+        assert_eq!(category(None, 1.0), FlightCategory::Ifr);
+        assert_eq!(category(None, 3.0), FlightCategory::Mvfr);
+        assert_eq!(category(None, 5.0), FlightCategory::Mvfr);
+        assert_eq!(category(None, 6.0), FlightCategory::Vfr);
+        assert_eq!(category(Some(2000), 0.5), FlightCategory::Lifr);
+        assert_eq!(FlightCategory::from_conditions(None, None, false), None);
+    }
+
+    #[test]
+    fn resolves_observation_time_across_months() {
+        let time = ObservationTime { day: 31, hour: 23, minute: 56 };
+        let reference = Utc.with_ymd_and_hms(2024, 2, 1, 0, 10, 0).unwrap();
+
+        assert_eq!(time.resolve(reference), Utc.with_ymd_and_hms(2024, 1, 31, 23, 56, 0).single());
+    }
+}
@@ -0,0 +1,716 @@
+use serenity::{
+    builder::{CreateComponents, CreateEmbed, CreateInteractionResponseData},
+    http::Http,
+    model::{
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                application_command::ApplicationCommandInteraction, InteractionResponseType,
+            },
+        },
+        prelude::*,
+    },
+    prelude::*,
+    utils::Colour,
+};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::lib::error::Error;
+
+/// How command reports are rendered in a guild. Code blocks are the default
+/// since some clients don't render embeds well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    #[default]
+    Code,
+    Embed,
+}
+
+impl FromStr for Style {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "code" => Ok(Self::Code),
+            "embed" => Ok(Self::Embed),
+            _ => Err(Error::Invalid("Output must be code or embed".into())),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Code => write!(f, "code"),
+            Self::Embed => write!(f, "embed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    /// Inline fields are short name/value pairs; the rest are sections with a
+    /// heading and a body of text.
+    pub inline: bool,
+}
+
+/// A command response that can be rendered as a code block or an embed.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub title: String,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+    pub footer: Option<String>,
+    pub url: Option<String>,
+    pub color: Option<Colour>,
+    pub thumbnail: Option<String>,
+}
+
+/// Discord limit for message content.
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Results longer than this many messages are sent as one message with
+/// buttons to page through them, instead of flooding the channel.
+const PAGE_THRESHOLD: usize = 3;
+
+/// Room left on each page for the "Page x of y" label.
+const PAGE_LABEL: usize = 20;
+
+/// Discord limits for embed titles, descriptions, field names and values,
+/// footers and fields.
+const TITLE_LIMIT: usize = 256;
+const DESCRIPTION_LIMIT: usize = 4096;
+const NAME_LIMIT: usize = 256;
+const VALUE_LIMIT: usize = 1024;
+const FOOTER_LIMIT: usize = 2048;
+const FIELD_LIMIT: usize = 25;
+
+/// Discord limit for all the text in an embed combined.
+const EMBED_LIMIT: usize = 6000;
+
+fn truncate(val: &str, limit: usize) -> String {
+    if val.chars().count() <= limit {
+        val.to_string()
+    } else if limit == 0 {
+        String::new()
+    } else {
+        let mut val: String = val.chars().take(limit - 1).collect();
+        val.push('\u{2026}');
+        val
+    }
+}
+
+impl Report {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), ..Self::default() }
+    }
+
+    pub fn description(mut self, val: impl Into<String>) -> Self {
+        self.description = Some(val.into());
+        self
+    }
+
+    /// Adds a name/value pair, shown as "Name:  value" in a code block.
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push(Field { name: name.into(), value: value.into(), inline: true });
+        self
+    }
+
+    /// Adds a section, shown as an underlined heading over its text in a code
+    /// block.
+    pub fn section(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push(Field { name: name.into(), value: value.into(), inline: false });
+        self
+    }
+
+    pub fn footer(mut self, val: impl Into<String>) -> Self {
+        self.footer = Some(val.into());
+        self
+    }
+
+    pub fn url(mut self, val: impl Into<String>) -> Self {
+        self.url = Some(val.into());
+        self
+    }
+
+    pub fn color(mut self, val: Colour) -> Self {
+        self.color = Some(val);
+        self
+    }
+
+    pub fn thumbnail(mut self, val: Option<String>) -> Self {
+        self.thumbnail = val;
+        self
+    }
+
+    pub fn to_code_block(&self) -> String {
+        let width = self
+            .fields
+            .iter()
+            .filter(|x| x.inline)
+            .map(|x| x.name.chars().count() + 1)
+            .max()
+            .unwrap_or_default()
+            .max(12)
+            + 4;
+        let mut lines = vec![self.title.clone()];
+        let mut inline = false;
+
+        if let Some(description) = &self.description {
+            lines.push(String::new());
+            lines.push(description.clone());
+        }
+
+        for field in &self.fields {
+            if field.inline {
+                if !inline {
+                    lines.push(String::new());
+                }
+                lines.push(format!(
+                    "{:<width$}{}",
+                    format!("{}:", field.name),
+                    field.value.replace('\n', &format!("\n{}", " ".repeat(width)))
+                ));
+            } else {
+                lines.push(String::new());
+                lines.push(field.name.clone());
+                lines.push("-".repeat(field.name.chars().count()));
+                lines.push(String::new());
+                lines.push(field.value.clone());
+            }
+            inline = field.inline;
+        }
+
+        if let Some(footer) = &self.footer {
+            lines.push(String::new());
+            lines.push(footer.clone());
+        }
+
+        format!("```\n{}\n```", lines.join("\n"))
+    }
+
+    /// Returns a copy of the report cut down to Discord's embed limits. The
+    /// title and footer are kept first, then the description and fields in
+    /// order until the running total reaches the limit.
+    fn fit_embed(&self) -> Self {
+        let title = truncate(&self.title, TITLE_LIMIT);
+        let footer = self.footer.as_ref().map(|x| truncate(x, FOOTER_LIMIT));
+        let mut room =
+            EMBED_LIMIT - title.chars().count() - footer.as_ref().map_or(0, |x| x.chars().count());
+        let description = self.description.as_ref().map(|x| {
+            let val = truncate(x, DESCRIPTION_LIMIT.min(room));
+            room -= val.chars().count();
+            val
+        });
+        let mut fields = Vec::new();
+
+        for field in self.fields.iter().take(FIELD_LIMIT) {
+            let name = truncate(&field.name, NAME_LIMIT);
+            let value = if field.value.trim().is_empty() { "-" } else { &field.value };
+            let length = name.chars().count();
+
+            if length >= room {
+                break;
+            }
+
+            let value = truncate(value, VALUE_LIMIT.min(room - length));
+            room -= length + value.chars().count();
+            fields.push(Field { name, value, inline: field.inline });
+        }
+
+        Self { title, description, fields, footer, ..self.clone() }
+    }
+
+    /// Splits the report into embeds that each fit Discord's limits. The
+    /// description stays on the first page and the title on every page, with
+    /// "Page x of y" added to the footer when there is more than one.
+    fn embed_pages(&self) -> Vec<Self> {
+        let fixed = self.title.chars().count().min(TITLE_LIMIT)
+            + self.footer.as_ref().map_or(0, |x| x.chars().count().min(FOOTER_LIMIT))
+            + PAGE_LABEL;
+        let description = self
+            .description
+            .as_ref()
+            .map_or(0, |x| x.chars().count().min(DESCRIPTION_LIMIT).min(EMBED_LIMIT - fixed));
+        let mut pages = vec![Self { fields: Vec::new(), ..self.clone() }];
+        let mut length = fixed + description;
+
+        for field in &self.fields {
+            let size = field.name.chars().count().min(NAME_LIMIT)
+                + field.value.chars().count().clamp(1, VALUE_LIMIT);
+            let page = pages.last_mut().unwrap();
+
+            if !page.fields.is_empty()
+                && (page.fields.len() == FIELD_LIMIT || length + size > EMBED_LIMIT)
+            {
+                pages.push(Self { description: None, fields: Vec::new(), ..self.clone() });
+                length = fixed;
+            }
+
+            pages.last_mut().unwrap().fields.push(field.clone());
+            length += size;
+        }
+
+        let total = pages.len();
+
+        if total > 1 {
+            for (i, page) in pages.iter_mut().enumerate() {
+                let label = format!("Page {} of {total}", i + 1);
+
+                page.footer = Some(match &page.footer {
+                    Some(footer) => format!("{}\n{label}", truncate(footer, FOOTER_LIMIT)),
+                    None => label,
+                });
+            }
+        }
+
+        pages
+    }
+
+    pub fn create_embed<'a>(&self, e: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+        let report = self.fit_embed();
+
+        e.title(&report.title);
+
+        if let Some(description) = &report.description {
+            e.description(description);
+        }
+
+        for field in &report.fields {
+            e.field(&field.name, &field.value, field.inline);
+        }
+
+        if let Some(footer) = &report.footer {
+            e.footer(|f| f.text(footer));
+        }
+
+        if let Some(url) = &self.url {
+            e.url(url);
+        }
+
+        if let Some(color) = self.color {
+            e.colour(color);
+        }
+
+        if let Some(thumbnail) = &self.thumbnail {
+            e.thumbnail(thumbnail);
+        }
+
+        e
+    }
+}
+
+/// Splits text into messages of at most `limit` characters, breaking at line
+/// boundaries. A code block that spans a break is closed at the end of one
+/// message and reopened, with its language, at the start of the next.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    let mut fence: Option<String> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let next = match &fence {
+            Some(_) if trimmed.ends_with("```") => None,
+            None if trimmed.starts_with("```")
+                && !(trimmed.len() > 3 && trimmed.ends_with("```")) =>
+            {
+                let language = &trimmed[3..];
+
+                if language.chars().all(|x| x.is_ascii_alphanumeric()) {
+                    Some(trimmed.to_string())
+                } else {
+                    Some(String::from("```"))
+                }
+            }
+            _ => fence.clone(),
+        };
+
+        // Lines too long for a message of their own are broken at the limit,
+        // leaving room to reopen and close a code block around each piece
+        let reopen = fence.as_ref().map_or(3, |x| x.chars().count()) + 1;
+        let room = limit.saturating_sub((reopen + 4).max(12)).max(1);
+        let chars: Vec<char> = line.chars().collect();
+        let pieces: Vec<String> = if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars.chunks(room).map(|x| x.iter().collect()).collect()
+        };
+
+        for piece in pieces {
+            // Reserve room for the closing fence whenever a code block is
+            // open before or after this line, so closing it never overflows
+            let closing = if fence.is_some() || next.is_some() { 4 } else { 0 };
+            let length = current.chars().count() + piece.chars().count() + 1 + closing;
+
+            if !current.is_empty() && length > limit {
+                if fence.is_some() {
+                    current.push_str("\n```");
+                }
+                messages.push(current);
+                current = fence.clone().unwrap_or_default();
+            }
+
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&piece);
+        }
+
+        fence = next;
+    }
+
+    if !current.trim().is_empty() {
+        messages.push(current);
+    }
+
+    messages
+}
+
+fn page_buttons(c: &mut CreateComponents, page: usize, total: usize) -> &mut CreateComponents {
+    c.create_action_row(|r| {
+        r.create_button(|b| {
+            b.custom_id("previous")
+                .label("Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(page == 0)
+        })
+        .create_button(|b| {
+            b.custom_id("next")
+                .label("Next")
+                .style(ButtonStyle::Secondary)
+                .disabled(page + 1 == total)
+        })
+    })
+}
+
+fn page_content(pages: &[String], page: usize) -> String {
+    format!("{}\n`Page {} of {}`", pages[page], page + 1, pages.len())
+}
+
+/// The pages of a paged reply, either messages or embeds.
+#[derive(Clone, Copy)]
+enum Pages<'a> {
+    Text(&'a [String]),
+    Embeds(&'a [Report]),
+}
+
+impl Pages<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::Text(pages) => pages.len(),
+            Self::Embeds(pages) => pages.len(),
+        }
+    }
+
+    fn show<'a, 'b>(
+        &self,
+        d: &'b mut CreateInteractionResponseData<'a>,
+        page: usize,
+    ) -> &'b mut CreateInteractionResponseData<'a> {
+        match self {
+            Self::Text(pages) => d.content(page_content(pages, page)),
+            Self::Embeds(pages) => d.embed(|e| pages[page].create_embed(e)),
+        }
+        .components(|c| page_buttons(c, page, self.len()))
+    }
+}
+
+/// Sends text to a channel, split into as many messages as needed.
+pub async fn send_text(
+    http: impl AsRef<Http>,
+    channel_id: ChannelId,
+    text: &str,
+) -> serenity::Result<()> {
+    for message in split_message(text, MESSAGE_LIMIT) {
+        channel_id.say(&http, message).await?;
+    }
+
+    Ok(())
+}
+
+/// Replies with text of any length. Short results are split into a few
+/// messages; long ones are sent as a single message the author can page
+/// through with buttons for two minutes.
+pub async fn say(ctx: &Context, msg: &Message, text: &str) -> serenity::Result<()> {
+    if split_message(text, MESSAGE_LIMIT).len() <= PAGE_THRESHOLD {
+        return send_text(&ctx.http, msg.channel_id, text).await;
+    }
+
+    let pages = split_message(text, MESSAGE_LIMIT - PAGE_LABEL);
+    let message = msg
+        .channel_id
+        .send_message(&ctx.http, |m| {
+            m.content(page_content(&pages, 0)).components(|c| page_buttons(c, 0, pages.len()))
+        })
+        .await?;
+
+    paginate(ctx, message, msg.author.id, Pages::Text(&pages)).await
+}
+
+/// Answers the page buttons on a message showing the first page, then removes
+/// them once the author stops paging. Anyone else who clicks is told privately
+/// that only the author can turn the pages.
+async fn paginate(
+    ctx: &Context,
+    mut message: Message,
+    author: UserId,
+    pages: Pages<'_>,
+) -> serenity::Result<()> {
+    let mut page: usize = 0;
+    let mut expires = Instant::now() + Duration::from_secs(120);
+
+    while let Some(interaction) = message
+        .await_component_interaction(ctx)
+        .timeout(expires.saturating_duration_since(Instant::now()))
+        .await
+    {
+        if interaction.user.id != author {
+            interaction
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.content("`Only the person who ran the command can turn the pages`")
+                                .ephemeral(true)
+                        })
+                })
+                .await?;
+            continue;
+        }
+
+        page = match interaction.data.custom_id.as_str() {
+            "previous" => page.saturating_sub(1),
+            _ => (page + 1).min(pages.len() - 1),
+        };
+        expires = Instant::now() + Duration::from_secs(120);
+
+        interaction
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| pages.show(d, page))
+            })
+            .await?;
+    }
+
+    message.edit(&ctx.http, |m| m.components(|c| c)).await
+}
+
+/// Answers a deferred slash command with text of any length, paging long
+/// results like `say`.
+pub async fn followup(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    text: &str,
+) -> serenity::Result<()> {
+    let messages = split_message(text, MESSAGE_LIMIT);
+
+    if messages.len() <= PAGE_THRESHOLD {
+        for message in messages {
+            command.create_followup_message(&ctx.http, |f| f.content(message)).await?;
+        }
+
+        return Ok(());
+    }
+
+    let pages = split_message(text, MESSAGE_LIMIT - PAGE_LABEL);
+    let message = command
+        .create_followup_message(&ctx.http, |f| {
+            f.content(page_content(&pages, 0)).components(|c| page_buttons(c, 0, pages.len()))
+        })
+        .await?;
+
+    paginate(ctx, message, command.user.id, Pages::Text(&pages)).await
+}
+
+/// Answers a deferred slash command with a report in the given style, or the
+/// error that prevented building it. Embeds too long for one message are
+/// paged like `followup`.
+pub async fn followup_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    report: Result<Report, Error>,
+    style: Style,
+) -> serenity::Result<()> {
+    match (report, style) {
+        (Ok(report), Style::Code) => followup(ctx, command, &report.to_code_block()).await,
+        (Ok(report), Style::Embed) => {
+            let pages = report.embed_pages();
+            let message = command
+                .create_followup_message(&ctx.http, |f| {
+                    f.embed(|e| pages[0].create_embed(e));
+
+                    if pages.len() > 1 {
+                        f.components(|c| page_buttons(c, 0, pages.len()));
+                    }
+
+                    f
+                })
+                .await?;
+
+            if pages.len() > 1 {
+                paginate(ctx, message, command.user.id, Pages::Embeds(&pages)).await?;
+            }
+
+            Ok(())
+        }
+        (Err(e), _) => {
+            followup(ctx, command, &format!("`There was an error retrieving data: {e}`")).await
+        }
+    }
+}
+
+/// Sends a report in the given style. Embeds too long for one message are
+/// paged like `say`.
+pub async fn send_report(
+    ctx: &Context,
+    msg: &Message,
+    report: &Report,
+    style: Style,
+) -> serenity::Result<()> {
+    match style {
+        Style::Code => say(ctx, msg, &report.to_code_block()).await,
+        Style::Embed => {
+            let pages = report.embed_pages();
+            let message = msg
+                .channel_id
+                .send_message(&ctx.http, |m| {
+                    m.embed(|e| pages[0].create_embed(e));
+
+                    if pages.len() > 1 {
+                        m.components(|c| page_buttons(c, 0, pages.len()));
+                    }
+
+                    m
+                })
+                .await?;
+
+            if pages.len() > 1 {
+                paginate(ctx, message, msg.author.id, Pages::Embeds(&pages)).await?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Sends a report in the given style, or the error that prevented building it.
+pub async fn send(
+    ctx: &Context,
+    msg: &Message,
+    report: Result<Report, Error>,
+    style: Style,
+) -> serenity::Result<()> {
+    match report {
+        Ok(report) => send_report(ctx, msg, &report, style).await,
+        Err(e) => {
+            msg.channel_id
+                .say(&ctx.http, format!("`There was an error retrieving data: {e}`"))
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed_length(report: &Report) -> usize {
+        report.title.chars().count()
+            + report.description.as_ref().map_or(0, |x| x.chars().count())
+            + report.footer.as_ref().map_or(0, |x| x.chars().count())
+            + report
+                .fields
+                .iter()
+                .map(|x| x.name.chars().count() + x.value.chars().count())
+                .sum::<usize>()
+    }
+
+    fn long_report(fields: usize) -> Report {
+        (0..fields).fold(
+            Report::new("Forecast").description("x".repeat(3000)).footer("Last updated"),
+            |report, i| report.section(format!("Period {i}"), "y".repeat(900)),
+        )
+    }
+
+    #[test]
+    fn keeps_embeds_within_the_total_limit() {
+        let report = long_report(10).fit_embed();
+
+        assert!(embed_length(&report) <= EMBED_LIMIT);
+        assert_eq!(report.description.unwrap().chars().count(), 3000);
+        assert_eq!(report.footer.as_deref(), Some("Last updated"));
+        assert_eq!(report.fields.len(), 4);
+        assert!(report.fields[3].value.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn keeps_embeds_within_the_field_limits() {
+        let report = (0..30)
+            .fold(Report::new("Stations"), |report, i| report.field(format!("K{i:03}"), ""))
+            .fit_embed();
+
+        assert_eq!(report.fields.len(), FIELD_LIMIT);
+        assert_eq!(report.fields[0].value, "-");
+        assert_eq!(long_report(1).fit_embed().fields[0].value.chars().count(), 900);
+    }
+
+    #[test]
+    fn pages_long_embeds() {
+        let pages = long_report(10).embed_pages();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages.iter().map(|x| x.fields.len()).sum::<usize>(), 10);
+        assert!(pages.iter().all(|x| embed_length(x) <= EMBED_LIMIT));
+        assert!(pages[1].description.is_none());
+        assert_eq!(pages[2].footer.as_deref(), Some("Last updated\nPage 3 of 3"));
+        assert_eq!(pages[0].fit_embed().fields.len(), pages[0].fields.len());
+    }
+
+    #[test]
+    fn keeps_short_embeds_on_one_page() {
+        let pages = long_report(2).embed_pages();
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].footer.as_deref(), Some("Last updated"));
+
+        let pages = (0..30)
+            .fold(Report::new("Stations"), |report, i| report.field(format!("K{i:03}"), "VFR"))
+            .embed_pages();
+
+        assert_eq!(pages.iter().map(|x| x.fields.len()).collect::<Vec<_>>(), vec![25, 5]);
+    }
+
+    #[test]
+    fn splits_messages_within_the_limit() {
+        let text = (0..200).map(|i| format!("Line {i}")).collect::<Vec<_>>().join("\n");
+        let block = format!("Summary\n```\n{text}\n```\nDone");
+        let messages = split_message(&block, 100);
+
+        assert!(messages.iter().all(|x| x.chars().count() <= 100));
+        assert!(messages.iter().skip(1).take(messages.len() - 2).all(|x| x.starts_with("```")));
+        assert!(messages.iter().take(messages.len() - 1).all(|x| x.ends_with("```")));
+        assert_eq!(messages.concat().matches("Line ").count(), 200);
+    }
+
+    #[test]
+    fn leaves_room_to_close_code_blocks() {
+        // The opening fence lands exactly at the limit, so the block must
+        // move to the next message rather than be closed past the limit
+        let text = format!("{}\n```\n{}\n```", "a".repeat(16), "b".repeat(10));
+
+        for message in split_message(&text, 20) {
+            assert!(message.chars().count() <= 20, "{:?} is over the limit", message);
+        }
+
+        let long = format!("```rust\n{}\n```", "c".repeat(500));
+
+        assert!(split_message(&long, 100).iter().all(|x| x.chars().count() <= 100));
+    }
+}
@@ -0,0 +1,522 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serenity::async_trait;
+use std::fmt;
+
+use crate::lib::{
+    config,
+    error::Error,
+    utils::{self, Location},
+};
+
+#[cfg(test)]
+pub mod fake;
+pub mod nws;
+pub mod open_meteo;
+pub mod openuv;
+
+/// A geocoded place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub name: String,
+    pub region: String,
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Current conditions in imperial units (degrees F, mph, inHg, statute miles).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conditions {
+    pub temperature: Option<f64>,
+    pub dewpoint: Option<f64>,
+    pub humidity: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub weather: Option<String>,
+    pub visibility: Option<f64>,
+    pub pressure: Option<f64>,
+    /// URL of an image of the current weather, if the provider has one.
+    pub icon: Option<String>,
+}
+
+impl Conditions {
+    /// Relative humidity, estimated from the dew point when not reported.
+    pub fn relative_humidity(&self) -> Option<f64> {
+        match (self.humidity, self.temperature, self.dewpoint) {
+            (Some(val), _, _) => Some(val),
+            (None, Some(temp), Some(dew)) => Some(relative_humidity(temp, dew)),
+            _ => None,
+        }
+    }
+
+    /// NWS heat index, reported when the temperature is at least 80 degrees F.
+    pub fn heat_index(&self) -> Option<f64> {
+        let temp = self.temperature.filter(|x| *x >= 80.0)?;
+
+        Some(heat_index(temp, self.relative_humidity()?).round())
+    }
+
+    /// NWS wind chill, reported at or below 50 degrees F with wind of at least
+    /// 3 mph.
+    pub fn wind_chill(&self) -> Option<f64> {
+        wind_chill(self.temperature?, self.wind_speed?).map(f64::round)
+    }
+
+    /// The apparent temperature: the heat index in hot weather, the wind
+    /// chill in cold weather and the air temperature otherwise.
+    pub fn feels_like(&self) -> Option<f64> {
+        let temp = self.temperature?;
+
+        Some(feels_like(temp, self.relative_humidity(), self.wind_speed).round())
+    }
+
+    /// Estimated cumulus cloud base in feet above ground, from the spread
+    /// between temperature and dew point.
+    pub fn cloud_base(&self) -> Option<f64> {
+        let spread = self.temperature? - self.dewpoint?;
+
+        Some((spread.max(0.0) / 4.4 * 1000.0 / 100.0).round() * 100.0)
+    }
+}
+
+/// Relative humidity as a percentage from the temperature and dew point in
+/// degrees F, using the Magnus approximation.
+pub fn relative_humidity(temp: f64, dew: f64) -> f64 {
+    let (temp, dew) = ((temp - 32.0) * 5.0 / 9.0, (dew - 32.0) * 5.0 / 9.0);
+    let vapor = |x: f64| (17.625 * x / (243.04 + x)).exp();
+
+    (100.0 * vapor(dew) / vapor(temp)).clamp(0.0, 100.0)
+}
+
+/// NWS wind chill in degrees F from the temperature in degrees F and wind
+/// speed in mph, defined only at or below 50 degrees F with wind of at least
+/// 3 mph.
+pub fn wind_chill(temp: f64, wind_speed: f64) -> Option<f64> {
+    if temp > 50.0 || wind_speed < 3.0 {
+        return None;
+    }
+
+    let wind = wind_speed.powf(0.16);
+
+    Some(35.74 + 0.6215 * temp - 35.75 * wind + 0.4275 * temp * wind)
+}
+
+/// The apparent temperature in degrees F: the heat index from 80 degrees F,
+/// the wind chill where it applies and the air temperature otherwise.
+pub fn feels_like(temp: f64, humidity: Option<f64>, wind_speed: Option<f64>) -> f64 {
+    match (humidity, wind_speed) {
+        (Some(humidity), _) if temp >= 80.0 => heat_index(temp, humidity),
+        (_, Some(wind_speed)) => wind_chill(temp, wind_speed).unwrap_or(temp),
+        _ => temp,
+    }
+}
+
+/// NWS heat index in degrees F from the Rothfusz regression, with the NWS
+/// adjustments for low and high humidity.
+pub fn heat_index(temp: f64, humidity: f64) -> f64 {
+    let simple = 0.5 * (temp + 61.0 + (temp - 68.0) * 1.2 + humidity * 0.094);
+
+    if (simple + temp) / 2.0 < 80.0 {
+        return simple;
+    }
+
+    let (t, rh) = (temp, humidity);
+    let index = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+        - 0.224_755_41 * t * rh
+        - 0.006_837_83 * t * t
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * t * t * rh
+        + 0.000_852_82 * t * rh * rh
+        - 0.000_001_99 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        index - (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt()
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        index + (rh - 85.0) / 10.0 * (87.0 - t) / 5.0
+    } else {
+        index
+    }
+}
+
+/// A named forecast period (e.g., "Tonight") with its high or low temperature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Period {
+    pub name: String,
+    pub label: String,
+    /// Local date the period starts on.
+    pub date: Option<NaiveDate>,
+    pub temperature: Option<i32>,
+    /// Chance of precipitation as a percentage.
+    pub precipitation: Option<f64>,
+    pub text: String,
+}
+
+/// One hour of forecast data in imperial units. Precipitation chance and sky
+/// cover are percentages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hour {
+    /// Start of the hour in the place's local time.
+    pub time: DateTime<FixedOffset>,
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub precipitation: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub sky_cover: Option<f64>,
+}
+
+impl Hour {
+    /// The apparent temperature; see [`Conditions::feels_like`].
+    pub fn feels_like(&self) -> Option<f64> {
+        Some(feels_like(self.temperature?, self.humidity, self.wind_speed).round())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weather {
+    /// When the forecast was issued, in the place's local time.
+    pub updated: DateTime<FixedOffset>,
+    /// NWS public forecast zone, used to look up active alerts.
+    pub zone: Option<String>,
+    pub current: Conditions,
+    pub periods: Vec<Period>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvIndex {
+    pub uv: f64,
+    pub uv_time: DateTime<Utc>,
+    pub uv_max: f64,
+    pub uv_max_time: DateTime<Utc>,
+    /// Safe exposure time in minutes for skin types 1 through 3.
+    pub safe_exposure: [Option<i32>; 3],
+    pub sunrise: DateTime<Utc>,
+    pub solar_noon: DateTime<Utc>,
+    pub sunset: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvReading {
+    pub uv: f64,
+    pub time: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn fetch_weather(&self, lat: f64, lon: f64) -> Result<Weather, Error>;
+
+    /// Returns hourly forecasts starting with the current hour.
+    async fn fetch_hourly(&self, lat: f64, lon: f64) -> Result<Vec<Hour>, Error>;
+}
+
+#[async_trait]
+pub trait GeocodeProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Returns places matching the query, best match first.
+    async fn geocode(&self, query: &str) -> Result<Vec<Place>, Error>;
+}
+
+#[async_trait]
+pub trait UvProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn fetch_current(&self, lat: f64, lon: f64) -> Result<UvIndex, Error>;
+
+    async fn fetch_forecast(&self, lat: f64, lon: f64) -> Result<Vec<UvReading>, Error>;
+}
+
+/// The configured providers for each kind of data. Each list is tried in
+/// order until one succeeds.
+pub struct Registry {
+    pub weather: Vec<Box<dyn WeatherProvider>>,
+    pub geocode: Vec<Box<dyn GeocodeProvider>>,
+    pub uv: Vec<Box<dyn UvProvider>>,
+}
+
+impl Registry {
+    pub fn from_config(config: &config::Config) -> Result<Self, Error> {
+        let mut registry = Self { weather: Vec::new(), geocode: Vec::new(), uv: Vec::new() };
+
+        for name in &config.providers.weather {
+            match name.as_str() {
+                "nws" => registry.weather.push(Box::new(nws::Nws::new(&config.user_agent))),
+                "open-meteo" => registry.weather.push(Box::new(open_meteo::OpenMeteo)),
+                _ => return Err(Error::Invalid(format!("Unknown weather provider {name}"))),
+            }
+        }
+
+        for name in &config.providers.geocode {
+            match name.as_str() {
+                "open-meteo" => registry.geocode.push(Box::new(open_meteo::OpenMeteo)),
+                _ => return Err(Error::Invalid(format!("Unknown geocode provider {name}"))),
+            }
+        }
+
+        for name in &config.providers.uv {
+            match name.as_str() {
+                "openuv" => registry.uv.push(Box::new(openuv::OpenUv::new(&config.openuv))),
+                _ => return Err(Error::Invalid(format!("Unknown UV provider {name}"))),
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub async fn fetch_weather(&self, lat: f64, lon: f64) -> Result<Weather, Error> {
+        let mut error = Error::NotFound("No weather provider is configured".into());
+
+        for provider in &self.weather {
+            match provider.fetch_weather(lat, lon).await {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    println!("Error retrieving weather from {}: {e}", provider.name());
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    pub async fn fetch_hourly(&self, lat: f64, lon: f64) -> Result<Vec<Hour>, Error> {
+        let mut error = Error::NotFound("No weather provider is configured".into());
+
+        for provider in &self.weather {
+            match provider.fetch_hourly(lat, lon).await {
+                Ok(data) if !data.is_empty() => return Ok(data),
+                Ok(_) => {
+                    error = Error::NotFound("There is no hourly forecast for the location".into());
+                }
+                Err(e) => {
+                    println!("Error retrieving hourly forecast from {}: {e}", provider.name());
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    pub async fn geocode(&self, query: &str) -> Result<Vec<Place>, Error> {
+        let mut error = Error::NotFound("No geocode provider is configured".into());
+
+        for provider in &self.geocode {
+            match provider.geocode(query).await {
+                Ok(data) if !data.is_empty() => return Ok(data),
+                Ok(_) => {
+                    error = Error::NotFound(format!("{query} does not match a location"));
+                }
+                Err(e) => {
+                    println!("Error retrieving location from {}: {e}", provider.name());
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Returns the places matching a location argument, best match first. Only
+    /// place names can match more than one place.
+    pub async fn search(&self, query: &str) -> Result<Vec<Place>, Error> {
+        match utils::parse_location(query)? {
+            Location::Coordinates(lat, lon) => Ok(vec![Place {
+                name: format!("{lat:.2}, {lon:.2}"),
+                region: String::new(),
+                country: String::new(),
+                latitude: lat,
+                longitude: lon,
+            }]),
+            Location::ZipCode(zip_code) => match self.geocode(&zip_code).await {
+                Ok(mut data) => {
+                    data.truncate(1);
+                    Ok(data)
+                }
+                Err(_) => Err(Error::NotFound(format!(
+                    "The zip code {zip_code} does not match a location"
+                ))),
+            },
+            Location::Station(airport) => Ok(vec![Place {
+                name: airport.name.clone(),
+                region: airport.ident.clone(),
+                country: airport.country.clone(),
+                latitude: airport.latitude,
+                longitude: airport.longitude,
+            }]),
+            Location::Name(name) => self.geocode(&name).await,
+        }
+    }
+
+    /// Returns the best match for a location argument.
+    pub async fn locate(&self, query: &str) -> Result<Place, Error> {
+        Ok(self.search(query).await?.remove(0))
+    }
+
+    pub async fn fetch_uv_current(&self, lat: f64, lon: f64) -> Result<UvIndex, Error> {
+        let mut error = Error::NotFound("No UV provider is configured".into());
+
+        for provider in &self.uv {
+            match provider.fetch_current(lat, lon).await {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    println!("Error retrieving UV index from {}: {e}", provider.name());
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    pub async fn fetch_uv_forecast(&self, lat: f64, lon: f64) -> Result<Vec<UvReading>, Error> {
+        let mut error = Error::NotFound("No UV provider is configured".into());
+
+        for provider in &self.uv {
+            match provider.fetch_forecast(lat, lon).await {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    println!("Error retrieving UV forecast from {}: {e}", provider.name());
+                    error = e;
+                }
+            }
+        }
+
+        Err(error)
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.region.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}, {}", self.name, self.region)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::lib::providers::fake::{self, Fake};
+
+    fn registry(weather: Vec<Fake>, geocode: Vec<Fake>, uv: Vec<Fake>) -> Registry {
+        Registry {
+            weather: weather.into_iter().map(|x| Box::new(x) as Box<dyn WeatherProvider>).collect(),
+            geocode: geocode.into_iter().map(|x| Box::new(x) as Box<dyn GeocodeProvider>).collect(),
+            uv: uv.into_iter().map(|x| Box::new(x) as Box<dyn UvProvider>).collect(),
+        }
+    }
+
+    fn calls(log: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        log.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_weather_provider() {
+        let log = Arc::default();
+        let registry = registry(
+            vec![Fake::failing("nws", &log), Fake::working("open-meteo", &log)],
+            Vec::new(),
+            Vec::new(),
+        );
+
+        assert_eq!(registry.fetch_weather(0.0, 0.0).await.unwrap(), fake::weather());
+        assert_eq!(calls(&log), vec!["nws: weather", "open-meteo: weather"]);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_working_provider() {
+        let log = Arc::default();
+        let registry = registry(
+            vec![Fake::working("nws", &log), Fake::working("open-meteo", &log)],
+            Vec::new(),
+            vec![Fake::working("openuv", &log)],
+        );
+
+        assert!(registry.fetch_hourly(0.0, 0.0).await.is_ok());
+        assert!(registry.fetch_uv_current(0.0, 0.0).await.is_ok());
+        assert_eq!(calls(&log), vec!["nws: hourly", "openuv: uv"]);
+    }
+
+    #[tokio::test]
+    async fn returns_the_last_error_when_every_provider_fails() {
+        let log = Arc::default();
+        let registry = registry(
+            vec![Fake::failing("nws", &log), Fake::failing("open-meteo", &log)],
+            Vec::new(),
+            Vec::new(),
+        );
+
+        match registry.fetch_weather(0.0, 0.0).await {
+            Err(e) => assert_eq!(e.to_string(), "open-meteo is unavailable"),
+            Ok(_) => panic!("every provider failed"),
+        }
+        match registry.fetch_uv_forecast(0.0, 0.0).await {
+            Err(e) => assert_eq!(e.to_string(), "No UV provider is configured"),
+            Ok(_) => panic!("no provider is configured"),
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_providers_with_empty_results() {
+        let log = Arc::default();
+        let empty = Fake {
+            name: "empty",
+            hourly: Some(Vec::new()),
+            places: Some(Vec::new()),
+            calls: Arc::clone(&log),
+            ..Fake::default()
+        };
+        let registry = registry(
+            vec![empty, Fake::working("open-meteo", &log)],
+            vec![
+                Fake {
+                    name: "empty",
+                    places: Some(Vec::new()),
+                    calls: Arc::clone(&log),
+                    ..Fake::default()
+                },
+                Fake::working("open-meteo", &log),
+            ],
+            Vec::new(),
+        );
+
+        assert_eq!(registry.fetch_hourly(0.0, 0.0).await.unwrap().len(), 48);
+        assert_eq!(registry.locate("Denver").await.unwrap(), fake::place());
+        assert_eq!(
+            calls(&log),
+            vec!["empty: hourly", "open-meteo: hourly", "empty: geocode", "open-meteo: geocode"]
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_coordinates_and_stations_without_geocoding() {
+        let log = Arc::default();
+        let registry = registry(Vec::new(), vec![Fake::working("open-meteo", &log)], Vec::new());
+        let place = registry.locate("39.74,-104.99").await.unwrap();
+
+        assert_eq!((place.latitude, place.longitude), (39.74, -104.99));
+
+        let place = registry.locate("KSFO").await.unwrap();
+
+        assert_eq!(place.region, "KSFO");
+        assert!(calls(&log).is_empty());
+    }
+
+    #[test]
+    fn computes_apparent_temperature() {
+        assert!((relative_humidity(72.0, 50.0) - 45.8).abs() < 0.1);
+        assert_eq!(wind_chill(60.0, 10.0), None);
+        assert_eq!(wind_chill(20.0, 2.0), None);
+        assert_eq!(wind_chill(20.0, 15.0).map(f64::round), Some(6.0));
+        assert_eq!(heat_index(90.0, 60.0).round(), 100.0);
+        assert_eq!(feels_like(70.0, Some(50.0), Some(10.0)), 70.0);
+    }
+}
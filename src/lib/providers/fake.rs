@@ -0,0 +1,178 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use serenity::async_trait;
+use std::sync::{Arc, Mutex};
+
+use crate::lib::{
+    error::Error,
+    providers::{
+        Conditions, GeocodeProvider, Hour, Period, Place, UvIndex, UvProvider, UvReading, Weather,
+        WeatherProvider,
+    },
+};
+
+/// A provider that returns canned data and records each call, for testing
+/// commands and the registry without network access. Data left unset fails
+/// like an unreachable service.
+#[derive(Debug, Default)]
+pub struct Fake {
+    pub name: &'static str,
+    pub weather: Option<Weather>,
+    pub hourly: Option<Vec<Hour>>,
+    pub places: Option<Vec<Place>>,
+    pub uv: Option<UvIndex>,
+    pub uv_forecast: Option<Vec<UvReading>>,
+    /// Calls made to every fake sharing this log, as "name: method".
+    pub calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Fake {
+    /// A provider that fails every request.
+    pub fn failing(name: &'static str, calls: &Arc<Mutex<Vec<String>>>) -> Self {
+        Self { name, calls: calls.clone(), ..Self::default() }
+    }
+
+    /// A provider that returns the sample data below for every request.
+    pub fn working(name: &'static str, calls: &Arc<Mutex<Vec<String>>>) -> Self {
+        Self {
+            name,
+            weather: Some(weather()),
+            hourly: Some(hours()),
+            places: Some(vec![place()]),
+            uv: Some(uv()),
+            uv_forecast: Some(Vec::new()),
+            calls: calls.clone(),
+        }
+    }
+
+    fn respond<T: Clone>(&self, method: &str, data: &Option<T>) -> Result<T, Error> {
+        self.calls.lock().unwrap().push(format!("{}: {method}", self.name));
+
+        match data {
+            Some(val) => Ok(val.clone()),
+            None => Err(Error::NotFound(format!("{} is unavailable", self.name))),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for Fake {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn fetch_weather(&self, _lat: f64, _lon: f64) -> Result<Weather, Error> {
+        self.respond("weather", &self.weather)
+    }
+
+    async fn fetch_hourly(&self, _lat: f64, _lon: f64) -> Result<Vec<Hour>, Error> {
+        self.respond("hourly", &self.hourly)
+    }
+}
+
+#[async_trait]
+impl GeocodeProvider for Fake {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn geocode(&self, _query: &str) -> Result<Vec<Place>, Error> {
+        self.respond("geocode", &self.places)
+    }
+}
+
+#[async_trait]
+impl UvProvider for Fake {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn fetch_current(&self, _lat: f64, _lon: f64) -> Result<UvIndex, Error> {
+        self.respond("uv", &self.uv)
+    }
+
+    async fn fetch_forecast(&self, _lat: f64, _lon: f64) -> Result<Vec<UvReading>, Error> {
+        self.respond("uv forecast", &self.uv_forecast)
+    }
+}
+
+pub fn place() -> Place {
+    Place {
+        name: String::from("Denver"),
+        region: String::from("CO"),
+        country: String::from("US"),
+        latitude: 39.74,
+        longitude: -104.99,
+    }
+}
+
+/// Noon on May 12, 2024 in Denver (UTC-06:00).
+fn updated() -> DateTime<FixedOffset> {
+    FixedOffset::west_opt(6 * 3600).unwrap().with_ymd_and_hms(2024, 5, 12, 12, 0, 0).unwrap()
+}
+
+fn period(name: &str, label: &str, day: u32, temperature: i32, text: &str) -> Period {
+    Period {
+        name: name.to_string(),
+        label: label.to_string(),
+        date: NaiveDate::from_ymd_opt(2024, 5, day),
+        temperature: Some(temperature),
+        precipitation: Some(20.0),
+        text: text.to_string(),
+    }
+}
+
+pub fn weather() -> Weather {
+    Weather {
+        updated: updated(),
+        zone: Some(String::from("COZ040")),
+        current: Conditions {
+            temperature: Some(72.0),
+            dewpoint: Some(50.0),
+            humidity: None,
+            wind_speed: Some(10.0),
+            wind_direction: Some(270.0),
+            wind_gust: Some(18.0),
+            weather: Some(String::from("Partly Cloudy")),
+            visibility: Some(10.0),
+            pressure: Some(30.12),
+            icon: Some(String::from("https://api.weather.gov/icons/land/day/sct")),
+        },
+        periods: vec![
+            period("This Afternoon", "High", 12, 75, "Partly sunny, with a high near 75."),
+            period("Tonight", "Low", 12, 52, "Mostly clear, with a low around 52."),
+            period("Monday", "High", 13, 78, "Sunny, with a high near 78."),
+            period("Monday Night", "Low", 13, 54, "Clear, with a low around 54."),
+            period("Tuesday", "High", 14, 80, "Sunny and hot, with a high near 80."),
+            period("Tuesday Night", "Low", 14, 55, "Clear, with a low around 55."),
+        ],
+    }
+}
+
+pub fn hours() -> Vec<Hour> {
+    (0..48)
+        .map(|i| Hour {
+            time: updated() + chrono::Duration::hours(i),
+            temperature: Some(60.0 + (i % 24) as f64),
+            humidity: Some(40.0),
+            precipitation: Some(10.0),
+            wind_speed: Some(5.0 + (i % 10) as f64),
+            wind_direction: Some(180.0),
+            sky_cover: Some(50.0),
+        })
+        .collect()
+}
+
+pub fn uv() -> UvIndex {
+    let time = Utc.with_ymd_and_hms(2024, 5, 12, 18, 0, 0).unwrap();
+
+    UvIndex {
+        uv: 6.2,
+        uv_time: time,
+        uv_max: 8.1,
+        uv_max_time: time,
+        safe_exposure: [Some(27), Some(32), Some(43)],
+        sunrise: time,
+        solar_noon: time,
+        sunset: time,
+    }
+}
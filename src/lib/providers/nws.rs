@@ -0,0 +1,420 @@
+use chrono::{prelude::*, Duration};
+use serde::{Deserialize, Deserializer};
+use serenity::async_trait;
+use std::collections::BTreeMap;
+
+use crate::lib::{
+    error::Error,
+    providers::{Conditions, Hour, Period, Weather, WeatherProvider},
+};
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct WeatherResponse {
+    creationDate: DateTime<FixedOffset>,
+    location: LocationData,
+    time: ForecastTime,
+    data: ForecastData,
+    currentobservation: CurrentData,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocationData {
+    zone: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct ForecastTime {
+    startPeriodName: Vec<String>,
+    #[serde(default)]
+    startValidTime: Vec<String>,
+    tempLabel: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastData {
+    #[serde(deserialize_with = "sentinels")]
+    temperature: Vec<Option<f64>>,
+    #[serde(default, deserialize_with = "sentinels")]
+    pop: Vec<Option<f64>>,
+    text: Vec<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct CurrentData {
+    #[serde(default, deserialize_with = "sentinel")]
+    Temp: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Dewp: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Relh: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Winds: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Windd: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Gust: Option<f64>,
+    #[serde(default, deserialize_with = "text")]
+    Weather: Option<String>,
+    #[serde(default, deserialize_with = "sentinel")]
+    Visibility: Option<f64>,
+    #[serde(default, deserialize_with = "sentinel")]
+    SLP: Option<f64>,
+    #[serde(default, deserialize_with = "text")]
+    Weatherimage: Option<String>,
+}
+
+/// A MapClick value, usually a string that is "NA" or empty when missing.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Number(f64),
+    Text(String),
+}
+
+impl RawValue {
+    fn value(self) -> Option<f64> {
+        match self {
+            Self::Number(val) => Some(val),
+            Self::Text(val) => val.trim().parse::<f64>().ok().filter(|x| x.is_finite()),
+        }
+    }
+}
+
+fn sentinel<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Ok(Option::<RawValue>::deserialize(deserializer)?.and_then(RawValue::value))
+}
+
+fn sentinels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Option<f64>>, D::Error> {
+    let values = Vec::<Option<RawValue>>::deserialize(deserializer)?;
+
+    Ok(values.into_iter().map(|x| x.and_then(RawValue::value)).collect())
+}
+
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(val) if !matches!(val.trim(), "" | "NA") => Ok(Some(val.trim().to_string())),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PointResponse {
+    properties: PointProperties,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct PointProperties {
+    forecastGridData: String,
+    forecastHourly: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HourlyResponse {
+    properties: HourlyProperties,
+}
+
+#[derive(Debug, Deserialize)]
+struct HourlyProperties {
+    periods: Vec<HourlyPeriod>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct HourlyPeriod {
+    startTime: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GridResponse {
+    properties: GridProperties,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct GridProperties {
+    temperature: GridLayer,
+    #[serde(default)]
+    relativeHumidity: GridLayer,
+    probabilityOfPrecipitation: GridLayer,
+    windSpeed: GridLayer,
+    windDirection: GridLayer,
+    skyCover: GridLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GridLayer {
+    #[serde(default)]
+    uom: String,
+    values: Vec<GridValue>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct GridValue {
+    validTime: String,
+    value: Option<f64>,
+}
+
+/// National Weather Service MapClick forecasts (U.S. only).
+pub struct Nws {
+    user_agent: String,
+}
+
+/// Parses the hours in an ISO 8601 duration such as "PT1H" or "P1DT6H".
+fn duration_hours(val: &str) -> Option<i64> {
+    let val = val.strip_prefix('P')?;
+    let (days, time) = val.split_once('T').unwrap_or((val, ""));
+    let days = match days.strip_suffix('D') {
+        Some(days) => days.parse::<i64>().ok()?,
+        None if days.is_empty() => 0,
+        None => return None,
+    };
+    let hours = match time.strip_suffix('H') {
+        Some(hours) => hours.parse::<i64>().ok()?,
+        None if time.is_empty() => 0,
+        None => return None,
+    };
+
+    Some(days * 24 + hours)
+}
+
+/// Expands a gridpoint layer, whose values each cover a span of hours (e.g.,
+/// "2024-05-01T12:00:00+00:00/PT3H"), into one value per hour.
+fn expand(layer: &GridLayer, convert: fn(f64) -> f64) -> BTreeMap<DateTime<Utc>, f64> {
+    let mut hours = BTreeMap::new();
+
+    for x in &layer.values {
+        let (start, duration) = match x.validTime.split_once('/') {
+            Some(val) => val,
+            None => continue,
+        };
+        let (start, duration, value) =
+            match (DateTime::parse_from_rfc3339(start), duration_hours(duration), x.value) {
+                (Ok(start), Some(duration), Some(value)) => {
+                    (start.with_timezone(&Utc), duration, value)
+                }
+                _ => continue,
+            };
+
+        for i in 0..duration.max(1) {
+            hours.insert(start + Duration::hours(i), convert(value));
+        }
+    }
+
+    hours
+}
+
+/// Returns the UTC offset in effect from each hour of the point's hourly
+/// forecast, since gridpoint times are all in UTC. Empty when the forecast is
+/// unavailable.
+async fn fetch_offsets(
+    client: &reqwest::Client,
+    url: &str,
+) -> BTreeMap<DateTime<Utc>, FixedOffset> {
+    let data: HourlyResponse = match client.get(url).send().await {
+        Ok(resp) => match resp.json().await {
+            Ok(data) => data,
+            Err(_) => return BTreeMap::new(),
+        },
+        Err(_) => return BTreeMap::new(),
+    };
+
+    data.properties
+        .periods
+        .iter()
+        .map(|x| (x.startTime.with_timezone(&Utc), *x.startTime.offset()))
+        .collect()
+}
+
+/// Converts a UTC time to the offset in effect at that time, falling back to
+/// the nearest known offset and then UTC.
+fn local_time(
+    offsets: &BTreeMap<DateTime<Utc>, FixedOffset>,
+    time: DateTime<Utc>,
+) -> DateTime<FixedOffset> {
+    let offset = offsets
+        .range(..=time)
+        .next_back()
+        .or_else(|| offsets.iter().next())
+        .map_or(FixedOffset::east_opt(0).unwrap(), |x| *x.1);
+
+    time.with_timezone(&offset)
+}
+
+impl Nws {
+    pub fn new(user_agent: &str) -> Self {
+        Self { user_agent: user_agent.to_string() }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for Nws {
+    fn name(&self) -> &'static str {
+        "nws"
+    }
+
+    async fn fetch_weather(&self, lat: f64, lon: f64) -> Result<Weather, Error> {
+        let url = format!(
+            "https://forecast.weather.gov/MapClick.php?lat={lat}&lon={lon}&unit=0&lg=english&FcstType=json");
+        let client = reqwest::ClientBuilder::new().user_agent(&self.user_agent).build()?;
+        let resp = client.get(&url).send().await?.json().await;
+        let data: WeatherResponse = match resp {
+            Ok(data) => data,
+            Err(_) => {
+                return Err(Error::NotFound(
+                    "The location provided is outside of NWS coverage".into(),
+                ))
+            }
+        };
+        let current = data.currentobservation;
+        let (start_times, pop) = (&data.time.startValidTime, &data.data.pop);
+        let periods = data
+            .time
+            .startPeriodName
+            .into_iter()
+            .zip(data.time.tempLabel)
+            .zip(data.data.temperature.iter().zip(data.data.text))
+            .enumerate()
+            .map(|(i, ((name, label), (temperature, text)))| Period {
+                name,
+                label,
+                date: start_times
+                    .get(i)
+                    .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+                    .map(|x| x.date_naive()),
+                temperature: temperature.map(|x| x.round() as i32),
+                precipitation: pop.get(i).copied().flatten(),
+                text,
+            })
+            .collect();
+
+        Ok(Weather {
+            updated: data.creationDate,
+            zone: Some(data.location.zone),
+            current: Conditions {
+                temperature: current.Temp,
+                dewpoint: current.Dewp,
+                humidity: current.Relh,
+                wind_speed: current.Winds,
+                wind_direction: current.Windd,
+                wind_gust: current.Gust,
+                weather: current.Weather,
+                visibility: current.Visibility,
+                pressure: current.SLP,
+                icon: current
+                    .Weatherimage
+                    .filter(|x| x != "NULL")
+                    .map(|x| format!("https://forecast.weather.gov/newimages/large/{x}")),
+            },
+            periods,
+        })
+    }
+
+    async fn fetch_hourly(&self, lat: f64, lon: f64) -> Result<Vec<Hour>, Error> {
+        let url = format!("https://api.weather.gov/points/{lat:.4},{lon:.4}");
+        let client = reqwest::ClientBuilder::new().user_agent(&self.user_agent).build()?;
+        let point: PointResponse = match client.get(&url).send().await?.json().await {
+            Ok(data) => data,
+            Err(_) => {
+                return Err(Error::NotFound(
+                    "The location provided is outside of NWS coverage".into(),
+                ))
+            }
+        };
+        let (resp, offsets) = tokio::join!(
+            client.get(&point.properties.forecastGridData).send(),
+            fetch_offsets(&client, &point.properties.forecastHourly)
+        );
+        let data: GridResponse = match resp?.json().await {
+            Ok(data) => data,
+            Err(_) => {
+                return Err(Error::NotFound(
+                    "There is no hourly forecast for the location provided".into(),
+                ))
+            }
+        };
+        let grid = data.properties;
+        let temperature = expand(
+            &grid.temperature,
+            match grid.temperature.uom.as_str() {
+                "wmoUnit:degC" => |x| x * 9.0 / 5.0 + 32.0,
+                _ => |x| x,
+            },
+        );
+        let wind_speed = expand(
+            &grid.windSpeed,
+            match grid.windSpeed.uom.as_str() {
+                "wmoUnit:km_h-1" => |x| x / 1.609_344,
+                _ => |x| x,
+            },
+        );
+        let humidity = expand(&grid.relativeHumidity, |x| x);
+        let precipitation = expand(&grid.probabilityOfPrecipitation, |x| x);
+        let wind_direction = expand(&grid.windDirection, |x| x);
+        let sky_cover = expand(&grid.skyCover, |x| x);
+        let now = Utc::now() - Duration::hours(1);
+
+        Ok(temperature
+            .range(now..)
+            .map(|(time, temperature)| Hour {
+                time: local_time(&offsets, *time),
+                temperature: Some(temperature.round()),
+                humidity: humidity.get(time).copied(),
+                precipitation: precipitation.get(time).copied(),
+                wind_speed: wind_speed.get(time).map(|x| x.round()),
+                wind_direction: wind_direction.get(time).copied(),
+                sky_cover: sky_cover.get(time).copied(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(duration_hours("PT1H"), Some(1));
+        assert_eq!(duration_hours("P1DT6H"), Some(30));
+        assert_eq!(duration_hours("P2D"), Some(48));
+        assert_eq!(duration_hours("1H"), None);
+    }
+
+    #[test]
+    fn expands_grid_layers() {
+        let layer = GridLayer {
+            uom: String::from("wmoUnit:degC"),
+            values: vec![GridValue {
+                validTime: String::from("2024-05-12T18:00:00+00:00/PT3H"),
+                value: Some(20.0),
+            }],
+        };
+        let hours = expand(&layer, |x| x * 9.0 / 5.0 + 32.0);
+
+        assert_eq!(hours.len(), 3);
+        assert!(hours.values().all(|x| *x == 68.0));
+    }
+
+    #[test]
+    fn converts_grid_times_to_local_offsets() {
+        let mdt = FixedOffset::west_opt(6 * 3600).unwrap();
+        let mst = FixedOffset::west_opt(7 * 3600).unwrap();
+        let time = |hour| Utc.with_ymd_and_hms(2024, 11, 3, hour, 0, 0).unwrap();
+        let offsets: BTreeMap<DateTime<Utc>, FixedOffset> =
+            [(time(6), mdt), (time(7), mdt), (time(8), mdt), (time(9), mst)]
+                .iter()
+                .copied()
+                .collect();
+
+        assert_eq!(local_time(&offsets, time(5)).offset(), &mdt);
+        assert_eq!(local_time(&offsets, time(8)).hour(), 2);
+        assert_eq!(local_time(&offsets, time(9)).hour(), 2);
+        assert_eq!(local_time(&offsets, time(20)).offset(), &mst);
+        assert_eq!(local_time(&BTreeMap::new(), time(9)).hour(), 9);
+    }
+}
@@ -0,0 +1,414 @@
+use chrono::{prelude::*, Duration};
+use serenity::async_trait;
+
+use crate::lib::{
+    error::Error,
+    providers::{Conditions, GeocodeProvider, Hour, Period, Place, Weather, WeatherProvider},
+    units::HPA_PER_IN_HG,
+};
+
+#[derive(Debug, Deserialize)]
+struct GeocodeResponse {
+    #[serde(default)]
+    results: Vec<GeocodeData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodeData {
+    name: String,
+    #[serde(default)]
+    admin1: String,
+    #[serde(default)]
+    country: String,
+    #[serde(default)]
+    country_code: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    utc_offset_seconds: i64,
+    current: CurrentData,
+    daily: DailyData,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentData {
+    time: String,
+    temperature_2m: Option<f64>,
+    relative_humidity_2m: Option<f64>,
+    dew_point_2m: Option<f64>,
+    weather_code: Option<u32>,
+    wind_speed_10m: Option<f64>,
+    wind_direction_10m: Option<f64>,
+    wind_gusts_10m: Option<f64>,
+    pressure_msl: Option<f64>,
+    visibility: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyData {
+    time: Vec<String>,
+    weather_code: Vec<Option<u32>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    temperature_2m_min: Vec<Option<f64>>,
+    precipitation_probability_max: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HourlyResponse {
+    utc_offset_seconds: i64,
+    hourly: HourlyData,
+}
+
+#[derive(Debug, Deserialize)]
+struct HourlyData {
+    time: Vec<String>,
+    temperature_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    relative_humidity_2m: Vec<Option<f64>>,
+    precipitation_probability: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+    wind_direction_10m: Vec<Option<f64>>,
+    cloud_cover: Vec<Option<f64>>,
+}
+
+/// Open-Meteo geocoding and forecasts, available worldwide.
+pub struct OpenMeteo;
+
+const STATES: [(&str, &str); 51] = [
+    ("AL", "Alabama"),
+    ("AK", "Alaska"),
+    ("AZ", "Arizona"),
+    ("AR", "Arkansas"),
+    ("CA", "California"),
+    ("CO", "Colorado"),
+    ("CT", "Connecticut"),
+    ("DE", "Delaware"),
+    ("DC", "District of Columbia"),
+    ("FL", "Florida"),
+    ("GA", "Georgia"),
+    ("HI", "Hawaii"),
+    ("ID", "Idaho"),
+    ("IL", "Illinois"),
+    ("IN", "Indiana"),
+    ("IA", "Iowa"),
+    ("KS", "Kansas"),
+    ("KY", "Kentucky"),
+    ("LA", "Louisiana"),
+    ("ME", "Maine"),
+    ("MD", "Maryland"),
+    ("MA", "Massachusetts"),
+    ("MI", "Michigan"),
+    ("MN", "Minnesota"),
+    ("MS", "Mississippi"),
+    ("MO", "Missouri"),
+    ("MT", "Montana"),
+    ("NE", "Nebraska"),
+    ("NV", "Nevada"),
+    ("NH", "New Hampshire"),
+    ("NJ", "New Jersey"),
+    ("NM", "New Mexico"),
+    ("NY", "New York"),
+    ("NC", "North Carolina"),
+    ("ND", "North Dakota"),
+    ("OH", "Ohio"),
+    ("OK", "Oklahoma"),
+    ("OR", "Oregon"),
+    ("PA", "Pennsylvania"),
+    ("RI", "Rhode Island"),
+    ("SC", "South Carolina"),
+    ("SD", "South Dakota"),
+    ("TN", "Tennessee"),
+    ("TX", "Texas"),
+    ("UT", "Utah"),
+    ("VT", "Vermont"),
+    ("VA", "Virginia"),
+    ("WA", "Washington"),
+    ("WV", "West Virginia"),
+    ("WI", "Wisconsin"),
+    ("WY", "Wyoming"),
+];
+
+/// Returns true when a result matches the qualifier after the comma in a query
+/// such as "Denver, CO" or "Paris, France".
+fn matches_qualifier(data: &GeocodeData, qualifier: &str) -> bool {
+    let state = STATES.iter().find(|(abbr, _)| abbr.eq_ignore_ascii_case(qualifier));
+
+    [&data.admin1, &data.country, &data.country_code]
+        .iter()
+        .any(|x| x.eq_ignore_ascii_case(qualifier))
+        || state.is_some_and(|(_, name)| data.country_code == "US" && data.admin1 == *name)
+}
+
+/// Returns the offset Open-Meteo reports for the place's time zone, which
+/// its local times are given in.
+fn utc_offset(seconds: i64) -> FixedOffset {
+    FixedOffset::east_opt(seconds as i32).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+}
+
+/// Describes a WMO weather interpretation code.
+fn weather_description(code: u32) -> &'static str {
+    match code {
+        0 => "Clear",
+        1 => "Mostly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51 | 53 | 55 => "Drizzle",
+        56 | 57 => "Freezing drizzle",
+        61 => "Light rain",
+        63 => "Rain",
+        65 => "Heavy rain",
+        66 | 67 => "Freezing rain",
+        71 => "Light snow",
+        73 => "Snow",
+        75 => "Heavy snow",
+        77 => "Snow grains",
+        80..=82 => "Rain showers",
+        85 | 86 => "Snow showers",
+        95 => "Thunderstorms",
+        96 | 99 => "Thunderstorms with hail",
+        _ => "Unknown",
+    }
+}
+
+fn period_text(code: Option<u32>, high: Option<i32>, chance: Option<f64>) -> String {
+    let mut text = code.map_or(String::from("No forecast"), |x| weather_description(x).to_string());
+
+    match high {
+        Some(val) => text.push_str(&format!(", with a high near {val}.")),
+        None => text.push('.'),
+    }
+
+    if let Some(val) = chance.filter(|x| *x > 0.0) {
+        text.push_str(&format!(" Chance of precipitation is {val:.0}%."));
+    }
+
+    text
+}
+
+#[async_trait]
+impl GeocodeProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "open-meteo"
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<Place>, Error> {
+        let url = "https://geocoding-api.open-meteo.com/v1/search";
+        let (name, qualifier) = match query.split_once(',') {
+            Some((name, qualifier)) => (name.trim(), Some(qualifier.trim())),
+            None => (query.trim(), None),
+        };
+        let mut params =
+            vec![("name", name), ("count", "10"), ("language", "en"), ("format", "json")];
+
+        // Zip codes also match postal codes in other countries
+        if name.len() == 5 && name.chars().all(|x| x.is_ascii_digit()) {
+            params.push(("countryCode", "US"));
+        }
+
+        let client = reqwest::Client::new();
+        let resp = client.get(url).query(&params).send().await?.json().await;
+
+        match resp {
+            Ok(data) => {
+                let resp: GeocodeResponse = data;
+                let mut places: Vec<Place> = Vec::new();
+
+                for x in resp.results {
+                    if qualifier.is_some_and(|qualifier| !matches_qualifier(&x, qualifier)) {
+                        continue;
+                    }
+
+                    let place = Place {
+                        name: x.name,
+                        region: x.admin1,
+                        country: x.country,
+                        latitude: x.latitude,
+                        longitude: x.longitude,
+                    };
+
+                    if !places
+                        .iter()
+                        .any(|y| y.to_string() == place.to_string() && y.country == place.country)
+                    {
+                        places.push(place);
+                    }
+                }
+
+                Ok(places)
+            }
+            Err(_) => Err(Error::NotFound(format!("{query} does not match a location"))),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "open-meteo"
+    }
+
+    async fn fetch_weather(&self, lat: f64, lon: f64) -> Result<Weather, Error> {
+        let url = "https://api.open-meteo.com/v1/forecast";
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(url)
+            .query(&[
+                ("latitude", lat.to_string()),
+                ("longitude", lon.to_string()),
+                (
+                    "current",
+                    String::from(
+                        "temperature_2m,relative_humidity_2m,dew_point_2m,weather_code,\
+                        wind_speed_10m,wind_direction_10m,wind_gusts_10m,pressure_msl,visibility",
+                    ),
+                ),
+                (
+                    "daily",
+                    String::from(
+                        "weather_code,temperature_2m_max,temperature_2m_min,\
+                        precipitation_probability_max",
+                    ),
+                ),
+                ("temperature_unit", String::from("fahrenheit")),
+                ("wind_speed_unit", String::from("mph")),
+                ("timezone", String::from("auto")),
+            ])
+            .send()
+            .await?
+            .json()
+            .await;
+        let data: ForecastResponse = match resp {
+            Ok(data) => data,
+            Err(_) => {
+                return Err(Error::NotFound(
+                    "There is no forecast for the location provided".into(),
+                ))
+            }
+        };
+        let offset = utc_offset(data.utc_offset_seconds);
+        let updated = NaiveDateTime::parse_from_str(&data.current.time, "%Y-%m-%dT%H:%M")
+            .ok()
+            .and_then(|x| offset.from_local_datetime(&x).single())
+            .unwrap_or_else(|| Utc::now().with_timezone(&offset));
+        let mut periods = Vec::new();
+
+        for (i, date) in data.daily.time.iter().enumerate() {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+            let day = match date {
+                Some(val) if i > 0 => val.format("%A").to_string(),
+                _ => String::from("Today"),
+            };
+            let code = data.daily.weather_code.get(i).copied().flatten();
+            let chance = data.daily.precipitation_probability_max.get(i).copied().flatten();
+            let high =
+                data.daily.temperature_2m_max.get(i).copied().flatten().map(|x| x.round() as i32);
+            let low =
+                data.daily.temperature_2m_min.get(i).copied().flatten().map(|x| x.round() as i32);
+
+            periods.push(Period {
+                name: day.clone(),
+                label: String::from("High"),
+                date,
+                temperature: high,
+                precipitation: chance,
+                text: period_text(code, high, chance),
+            });
+            periods.push(Period {
+                name: if i == 0 { String::from("Tonight") } else { format!("{day} Night") },
+                label: String::from("Low"),
+                date,
+                temperature: low,
+                precipitation: None,
+                text: low.map_or(String::from("No forecast."), |x| format!("Low around {x}.")),
+            });
+        }
+
+        let current = data.current;
+
+        Ok(Weather {
+            updated,
+            zone: None,
+            current: Conditions {
+                temperature: current.temperature_2m.map(f64::round),
+                dewpoint: current.dew_point_2m.map(f64::round),
+                humidity: current.relative_humidity_2m,
+                wind_speed: current.wind_speed_10m.map(f64::round),
+                wind_direction: current.wind_direction_10m,
+                wind_gust: current.wind_gusts_10m.map(f64::round),
+                weather: current.weather_code.map(|x| weather_description(x).to_string()),
+                visibility: current.visibility.map(|x| (x / 1609.344 * 100.0).round() / 100.0),
+                pressure: current.pressure_msl.map(|x| (x / HPA_PER_IN_HG * 100.0).round() / 100.0),
+                icon: None,
+            },
+            periods,
+        })
+    }
+
+    async fn fetch_hourly(&self, lat: f64, lon: f64) -> Result<Vec<Hour>, Error> {
+        let url = "https://api.open-meteo.com/v1/forecast";
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(url)
+            .query(&[
+                ("latitude", lat.to_string()),
+                ("longitude", lon.to_string()),
+                (
+                    "hourly",
+                    String::from(
+                        "temperature_2m,relative_humidity_2m,precipitation_probability,\
+                        wind_speed_10m,wind_direction_10m,cloud_cover",
+                    ),
+                ),
+                ("forecast_days", String::from("7")),
+                ("temperature_unit", String::from("fahrenheit")),
+                ("wind_speed_unit", String::from("mph")),
+                ("timezone", String::from("auto")),
+            ])
+            .send()
+            .await?
+            .json()
+            .await;
+        let data: HourlyResponse = match resp {
+            Ok(data) => data,
+            Err(_) => {
+                return Err(Error::NotFound(
+                    "There is no hourly forecast for the location provided".into(),
+                ))
+            }
+        };
+        let hourly = data.hourly;
+        let offset = utc_offset(data.utc_offset_seconds);
+        let now = Utc::now() - Duration::hours(1);
+        let mut hours = Vec::new();
+
+        for (i, time) in hourly.time.iter().enumerate() {
+            let time = match NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
+                .ok()
+                .and_then(|x| offset.from_local_datetime(&x).single())
+            {
+                Some(val) => val,
+                None => continue,
+            };
+
+            if time < now {
+                continue;
+            }
+
+            hours.push(Hour {
+                time,
+                temperature: hourly.temperature_2m.get(i).copied().flatten().map(f64::round),
+                humidity: hourly.relative_humidity_2m.get(i).copied().flatten(),
+                precipitation: hourly.precipitation_probability.get(i).copied().flatten(),
+                wind_speed: hourly.wind_speed_10m.get(i).copied().flatten().map(f64::round),
+                wind_direction: hourly.wind_direction_10m.get(i).copied().flatten(),
+                sky_cover: hourly.cloud_cover.get(i).copied().flatten(),
+            });
+        }
+
+        Ok(hours)
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::prelude::*;
+use serenity::async_trait;
+
+use crate::lib::{
+    error::Error,
+    providers::{UvIndex, UvProvider, UvReading},
+};
+
+#[derive(Debug, Deserialize)]
+struct CurrentResult {
+    result: UVCurrent,
+}
+
+#[derive(Debug, Deserialize)]
+struct UVCurrent {
+    uv: f64,
+    uv_time: DateTime<Utc>,
+    uv_max: f64,
+    uv_max_time: DateTime<Utc>,
+    safe_exposure_time: SafeExposureTime,
+    sun_info: SunInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct SafeExposureTime {
+    st1: Option<i32>,
+    st2: Option<i32>,
+    st3: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SunInfo {
+    sun_times: SunTimes,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct SunTimes {
+    sunrise: DateTime<Utc>,
+    solarNoon: DateTime<Utc>,
+    sunset: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastResult {
+    result: Vec<ForecastPeriod>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastPeriod {
+    uv: f64,
+    uv_time: DateTime<Utc>,
+}
+
+/// OpenUV index and forecasts.
+pub struct OpenUv {
+    api_key: String,
+}
+
+impl OpenUv {
+    pub fn new(api_key: &str) -> Self {
+        Self { api_key: api_key.to_string() }
+    }
+}
+
+#[async_trait]
+impl UvProvider for OpenUv {
+    fn name(&self) -> &'static str {
+        "openuv"
+    }
+
+    async fn fetch_current(&self, lat: f64, lon: f64) -> Result<UvIndex, Error> {
+        let url = format!("https://api.openuv.io/api/v1/uv?lat={lat}&lng={lon}");
+        let client = reqwest::Client::new();
+        let resp =
+            client.get(&url).header("x-access-token", &self.api_key).send().await?.json().await;
+
+        match resp {
+            Ok(data) => {
+                let resp: CurrentResult = data;
+                let data = resp.result;
+
+                Ok(UvIndex {
+                    uv: data.uv,
+                    uv_time: data.uv_time,
+                    uv_max: data.uv_max,
+                    uv_max_time: data.uv_max_time,
+                    safe_exposure: [
+                        data.safe_exposure_time.st1,
+                        data.safe_exposure_time.st2,
+                        data.safe_exposure_time.st3,
+                    ],
+                    sunrise: data.sun_info.sun_times.sunrise,
+                    solar_noon: data.sun_info.sun_times.solarNoon,
+                    sunset: data.sun_info.sun_times.sunset,
+                })
+            }
+            Err(_) => {
+                Err(Error::NotFound("UV data is not available for the location provided".into()))
+            }
+        }
+    }
+
+    async fn fetch_forecast(&self, lat: f64, lon: f64) -> Result<Vec<UvReading>, Error> {
+        let url = format!("https://api.openuv.io/api/v1/forecast?lat={lat}&lng={lon}");
+        let client = reqwest::Client::new();
+        let resp =
+            client.get(&url).header("x-access-token", &self.api_key).send().await?.json().await;
+
+        match resp {
+            Ok(data) => {
+                let resp: ForecastResult = data;
+
+                Ok(resp
+                    .result
+                    .into_iter()
+                    .map(|x| UvReading { uv: x.uv, time: x.uv_time })
+                    .collect())
+            }
+            Err(_) => {
+                Err(Error::NotFound("UV data is not available for the location provided".into()))
+            }
+        }
+    }
+}
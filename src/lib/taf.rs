@@ -0,0 +1,443 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+use crate::lib::{
+    error::Error,
+    metar::{
+        self, digits, parse_cloud, parse_time, parse_visibility, parse_weather, parse_wind, Cloud,
+        FlightCategory, ObservationTime, Visibility, Weather, Wind,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Initial,
+    From,
+    Becoming,
+    Temporary,
+    Probability(u32),
+    ProbabilityTemporary(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForecastPeriod {
+    pub kind: ChangeKind,
+    pub start: Option<ObservationTime>,
+    pub end: Option<ObservationTime>,
+    pub wind: Option<Wind>,
+    pub visibility: Option<Visibility>,
+    pub weather: Vec<Weather>,
+    pub no_significant_weather: bool,
+    pub clouds: Vec<Cloud>,
+    pub wind_shear: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Taf {
+    pub raw: String,
+    pub station: String,
+    pub issued: Option<ObservationTime>,
+    pub amendment: Option<String>,
+    pub valid_from: Option<ObservationTime>,
+    pub valid_to: Option<ObservationTime>,
+    pub periods: Vec<ForecastPeriod>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineSegment {
+    pub kind: ChangeKind,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub category: Option<FlightCategory>,
+}
+
+fn parse_day_hour(val: &str) -> Option<ObservationTime> {
+    if val.len() == 4 {
+        Some(ObservationTime { day: digits(&val[0..2])?, hour: digits(&val[2..4])?, minute: 0 })
+    } else {
+        None
+    }
+}
+
+fn parse_validity(token: &str) -> Option<(ObservationTime, ObservationTime)> {
+    let (from, to) = token.split_once('/')?;
+
+    Some((parse_day_hour(from)?, parse_day_hour(to)?))
+}
+
+fn parse_from(token: &str) -> Option<ObservationTime> {
+    let body = token.strip_prefix("FM")?;
+
+    if body.len() == 6 {
+        Some(ObservationTime {
+            day: digits(&body[0..2])?,
+            hour: digits(&body[2..4])?,
+            minute: digits(&body[4..6])?,
+        })
+    } else {
+        None
+    }
+}
+
+fn parse_wind_shear(token: &str) -> Option<String> {
+    let (height, wind) = token.strip_prefix("WS")?.split_once('/')?;
+    let height = digits(height)? * 100;
+    let wind = parse_wind(wind)?;
+
+    Some(format!("At {} ft: {wind}", metar::thousands(height)))
+}
+
+impl ForecastPeriod {
+    fn new(kind: ChangeKind, start: Option<ObservationTime>, end: Option<ObservationTime>) -> Self {
+        Self {
+            kind,
+            start,
+            end,
+            wind: None,
+            visibility: None,
+            weather: Vec::new(),
+            no_significant_weather: false,
+            clouds: Vec::new(),
+            wind_shear: None,
+        }
+    }
+
+    /// Returns the ceiling height when the period forecasts cloud layers.
+    pub fn ceiling(&self) -> Option<u32> {
+        metar::ceiling(&self.clouds)
+    }
+}
+
+impl TimelineSegment {
+    /// Returns true when the segment describes prevailing conditions rather
+    /// than a temporary fluctuation.
+    pub fn is_prevailing(&self) -> bool {
+        matches!(self.kind, ChangeKind::Initial | ChangeKind::From | ChangeKind::Becoming)
+    }
+}
+
+impl Taf {
+    pub fn parse(raw: &str) -> Result<Self, Error> {
+        let raw = raw.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let raw = raw.trim_end_matches('=').to_string();
+        let mut tokens: Vec<&str> = raw.split(' ').collect();
+        let mut amendment = None;
+
+        while let Some(token) = tokens.first() {
+            match *token {
+                "TAF" => {
+                    tokens.remove(0);
+                }
+                "AMD" | "COR" => {
+                    amendment = Some(tokens.remove(0).to_string());
+                }
+                _ => break,
+            }
+        }
+
+        let station = match tokens.first() {
+            Some(val) if val.len() == 4 && val.chars().all(|x| x.is_ascii_alphanumeric()) => {
+                val.to_string()
+            }
+            _ => return Err(Error::Invalid("The TAF report could not be decoded".into())),
+        };
+        let tokens = match tokens.iter().position(|x| *x == "RMK") {
+            Some(i) => &tokens[1..i],
+            None => &tokens[1..],
+        };
+        let mut taf = Self {
+            raw: raw.clone(),
+            station,
+            issued: None,
+            amendment,
+            valid_from: None,
+            valid_to: None,
+            periods: Vec::new(),
+        };
+        let mut period = ForecastPeriod::new(ChangeKind::Initial, None, None);
+        let mut i = 0;
+
+        while i < tokens.len() {
+            let token = tokens[i];
+            let next = tokens.get(i + 1).copied();
+
+            if taf.issued.is_none() && taf.valid_from.is_none() && parse_time(token).is_some() {
+                taf.issued = parse_time(token);
+            } else if let (None, Some((from, to))) = (taf.valid_from, parse_validity(token)) {
+                taf.valid_from = Some(from);
+                taf.valid_to = Some(to);
+                period.start = Some(from);
+            } else if let Some(start) = parse_from(token) {
+                taf.periods.push(period);
+                period = ForecastPeriod::new(ChangeKind::From, Some(start), None);
+            } else if matches!(token, "TEMPO" | "BECMG") || token.starts_with("PROB") {
+                let kind = match token {
+                    "TEMPO" => ChangeKind::Temporary,
+                    "BECMG" => ChangeKind::Becoming,
+                    _ => {
+                        let chance = digits(&token[4..]).unwrap_or(0);
+
+                        if next == Some("TEMPO") {
+                            i += 1;
+                            ChangeKind::ProbabilityTemporary(chance)
+                        } else {
+                            ChangeKind::Probability(chance)
+                        }
+                    }
+                };
+                let (start, end) = match tokens.get(i + 1).and_then(|x| parse_validity(x)) {
+                    Some((start, end)) => {
+                        i += 1;
+                        (Some(start), Some(end))
+                    }
+                    None => (None, None),
+                };
+
+                taf.periods.push(period);
+                period = ForecastPeriod::new(kind, start, end);
+            } else if let Some(wind) = parse_wind(token) {
+                period.wind = Some(wind);
+            } else if period.visibility.is_none()
+                && token.len() == 1
+                && digits(token).is_some()
+                && next.is_some_and(|x| x.ends_with("SM") && x.contains('/'))
+            {
+                period.visibility = parse_visibility(next.unwrap_or_default(), Some(token));
+                i += 1;
+            } else if let Some(visibility) = parse_visibility(token, None) {
+                period.visibility = Some(visibility);
+            } else if let Some(cloud) = parse_cloud(token) {
+                period.clouds.push(cloud);
+            } else if let Some(weather) = parse_weather(token) {
+                period.weather.push(weather);
+            } else if token == "NSW" {
+                period.no_significant_weather = true;
+            } else if let Some(wind_shear) = parse_wind_shear(token) {
+                period.wind_shear = Some(wind_shear);
+            }
+
+            i += 1;
+        }
+
+        taf.periods.push(period);
+
+        // FM groups run until the next FM group or the end of the forecast
+        let mut end = taf.valid_to;
+
+        for period in taf.periods.iter_mut().rev() {
+            if matches!(period.kind, ChangeKind::Initial | ChangeKind::From) {
+                period.end = end;
+                end = period.start;
+            }
+        }
+
+        Ok(taf)
+    }
+}
+
+impl Taf {
+    /// Splits the forecast into time segments with the flight category expected
+    /// during each. Temporary and probability groups inherit any elements they
+    /// don't forecast from the prevailing conditions.
+    pub fn timeline(&self, reference: DateTime<Utc>) -> Vec<TimelineSegment> {
+        let mut prevailing: Vec<TimelineSegment> = Vec::new();
+        let mut temporary = Vec::new();
+        let mut visibility = None;
+        let mut clouds = Vec::new();
+
+        for period in &self.periods {
+            let (start, end) = match (
+                period.start.and_then(|x| x.resolve(reference)),
+                period.end.and_then(|x| x.resolve(reference)),
+            ) {
+                (Some(start), Some(end)) if start < end => (start, end),
+                _ => continue,
+            };
+            let period_visibility = period.visibility.or(visibility);
+            let period_clouds = if period.clouds.is_empty() { &clouds } else { &period.clouds };
+            let category = FlightCategory::from_conditions(
+                metar::ceiling(period_clouds),
+                period_visibility.map(|x| x.statute_miles()),
+                !period_clouds.is_empty(),
+            );
+
+            match period.kind {
+                ChangeKind::Initial | ChangeKind::From => {
+                    visibility = period.visibility;
+                    clouds.clone_from(&period.clouds);
+                    prevailing.push(TimelineSegment { kind: period.kind, start, end, category });
+                }
+                ChangeKind::Becoming => {
+                    let clouds_changed = !period.clouds.is_empty();
+
+                    visibility = period_visibility;
+                    if clouds_changed {
+                        clouds.clone_from(&period.clouds);
+                    }
+
+                    if let Some(last) = prevailing.last_mut() {
+                        let end = last.end;
+
+                        last.end = start;
+                        prevailing.push(TimelineSegment {
+                            kind: period.kind,
+                            start,
+                            end,
+                            category,
+                        });
+                    }
+                }
+                _ => temporary.push(TimelineSegment { kind: period.kind, start, end, category }),
+            }
+        }
+
+        prevailing.retain(|x| x.start < x.end);
+        prevailing.extend(temporary);
+        prevailing
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Initial => write!(f, "Initial"),
+            Self::From => write!(f, "From"),
+            Self::Becoming => write!(f, "Becoming"),
+            Self::Temporary => write!(f, "Temporarily"),
+            Self::Probability(val) => write!(f, "{val}% chance"),
+            Self::ProbabilityTemporary(val) => write!(f, "{val}% chance temporarily"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const KSFO: &str = "TAF KSFO 121720Z 1218/1324 28012KT P6SM FEW010 SCT200
+      TEMPO 1218/1220 BKN008
+      FM130200 29008KT P6SM SKC
+      PROB30 TEMPO 1310/1314 2SM BR OVC005
+      FM131800 30015G25KT P6SM FEW020
+      BECMG 1320/1322 BKN025=";
+
+    type Segment = (ChangeKind, DateTime<Utc>, DateTime<Utc>, Option<FlightCategory>);
+
+    fn time(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_header() {
+        let taf = Taf::parse(KSFO).expect("report should parse");
+
+        assert_eq!(taf.station, "KSFO");
+        assert_eq!(taf.issued, Some(ObservationTime { day: 12, hour: 17, minute: 20 }));
+        assert_eq!(taf.valid_from, Some(ObservationTime { day: 12, hour: 18, minute: 0 }));
+        assert_eq!(taf.valid_to, Some(ObservationTime { day: 13, hour: 24, minute: 0 }));
+        assert_eq!(taf.amendment, None);
+        assert!(!taf.raw.contains('\n'));
+    }
+
+    #[test]
+    fn parses_change_groups() {
+        let taf = Taf::parse(KSFO).expect("report should parse");
+        let kinds: Vec<ChangeKind> = taf.periods.iter().map(|x| x.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ChangeKind::Initial,
+                ChangeKind::Temporary,
+                ChangeKind::From,
+                ChangeKind::ProbabilityTemporary(30),
+                ChangeKind::From,
+                ChangeKind::Becoming,
+            ]
+        );
+
+        // FM groups end where the next one starts
+        assert_eq!(taf.periods[0].end, Some(ObservationTime { day: 13, hour: 2, minute: 0 }));
+        assert_eq!(taf.periods[2].end, Some(ObservationTime { day: 13, hour: 18, minute: 0 }));
+        assert_eq!(taf.periods[4].end, taf.valid_to);
+
+        let prob = &taf.periods[3];
+
+        assert_eq!(prob.start, Some(ObservationTime { day: 13, hour: 10, minute: 0 }));
+        assert_eq!(prob.end, Some(ObservationTime { day: 13, hour: 14, minute: 0 }));
+        assert_eq!(prob.visibility.map(|x| x.statute_miles()), Some(2.0));
+        assert_eq!(prob.weather.len(), 1);
+        assert_eq!(prob.ceiling(), Some(500));
+        assert_eq!(taf.periods[4].wind.as_ref().and_then(|x| x.gust), Some(25));
+    }
+
+    #[test]
+    fn builds_timeline() {
+        let taf = Taf::parse(KSFO).expect("report should parse");
+        let timeline: Vec<Segment> = taf
+            .timeline(time(12, 18))
+            .into_iter()
+            .map(|x| (x.kind, x.start, x.end, x.category))
+            .collect();
+
+        assert_eq!(
+            timeline,
+            vec![
+                (ChangeKind::Initial, time(12, 18), time(13, 2), Some(FlightCategory::Vfr)),
+                (ChangeKind::From, time(13, 2), time(13, 18), Some(FlightCategory::Vfr)),
+                (ChangeKind::From, time(13, 18), time(13, 20), Some(FlightCategory::Vfr)),
+                (ChangeKind::Becoming, time(13, 20), time(14, 0), Some(FlightCategory::Mvfr)),
+                (ChangeKind::Temporary, time(12, 18), time(12, 20), Some(FlightCategory::Ifr)),
+                (
+                    ChangeKind::ProbabilityTemporary(30),
+                    time(13, 10),
+                    time(13, 14),
+                    Some(FlightCategory::Ifr)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn temporary_groups_inherit_prevailing_conditions() {
+        let taf =
+            Taf::parse("TAF KBOS 121730Z 1218/1318 18010KT 1/2SM FG OVC002 TEMPO 1220/1222 BKN030")
+                .expect("report should parse");
+        let timeline = taf.timeline(time(12, 18));
+
+        assert_eq!(timeline[0].category, Some(FlightCategory::Lifr));
+        assert!(!timeline[1].is_prevailing());
+        // Fog still limits visibility when only the clouds lift
+        assert_eq!(timeline[1].category, Some(FlightCategory::Lifr));
+    }
+
+    #[test]
+    fn parses_amendments_probability_and_wind_shear() {
+        let taf = Taf::parse(
+            "TAF AMD KJFK 121900Z 1219/1324 22015KT P6SM BKN040 WS020/24045KT \
+             PROB40 1302/1306 1 1/2SM TSRA OVC015CB RMK NXT FCST BY 00Z",
+        )
+        .expect("report should parse");
+
+        assert_eq!(taf.amendment.as_deref(), Some("AMD"));
+        assert_eq!(taf.periods.len(), 2);
+        assert_eq!(
+            taf.periods[0].wind_shear.as_deref(),
+            Some("At 2,000 ft: From 240\u{b0} (WSW) at 45 kt")
+        );
+
+        let prob = &taf.periods[1];
+
+        assert_eq!(prob.kind, ChangeKind::Probability(40));
+        assert_eq!(prob.visibility.map(|x| x.statute_miles()), Some(1.5));
+        assert_eq!(prob.weather[0].to_string(), "thunderstorm with rain");
+        assert_eq!(prob.clouds[0].kind.as_deref(), Some("CB"));
+    }
+
+    #[test]
+    fn rejects_invalid_reports() {
+        assert!(Taf::parse("TAF").is_err());
+        assert!(Taf::parse("").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr};
+
+use crate::lib::error::Error;
+
+/// Miles per hour in a knot.
+pub const MPH_PER_KNOT: f64 = 1.150_779;
+
+/// Hectopascals in an inch of mercury.
+pub const HPA_PER_IN_HG: f64 = 33.863_886;
+
+/// A user's preferred unit system. Values are converted from the imperial
+/// units the providers return. Mixed uses Celsius and hectopascals with miles
+/// and miles per hour, as in the U.K.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    #[default]
+    Imperial,
+    Metric,
+    Mixed,
+}
+
+impl Units {
+    /// Converts degrees Fahrenheit.
+    pub fn temperature(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial => val,
+            Self::Metric | Self::Mixed => (val - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn temperature_unit(self) -> &'static str {
+        match self {
+            Self::Imperial => "\u{b0}F",
+            Self::Metric | Self::Mixed => "\u{b0}C",
+        }
+    }
+
+    /// Converts miles per hour.
+    pub fn speed(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial | Self::Mixed => val,
+            Self::Metric => val * 1.609_344,
+        }
+    }
+
+    /// Converts knots, as reported in METARs.
+    pub fn speed_from_knots(self, val: f64) -> f64 {
+        self.speed(val * MPH_PER_KNOT)
+    }
+
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            Self::Imperial | Self::Mixed => "mph",
+            Self::Metric => "km/h",
+        }
+    }
+
+    /// Converts inches of mercury.
+    pub fn pressure(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial => val,
+            Self::Metric | Self::Mixed => val * HPA_PER_IN_HG,
+        }
+    }
+
+    /// Converts hectopascals, as stored for METAR altimeter settings.
+    pub fn pressure_from_hpa(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial => val / HPA_PER_IN_HG,
+            Self::Metric | Self::Mixed => val,
+        }
+    }
+
+    pub fn pressure_unit(self) -> &'static str {
+        match self {
+            Self::Imperial => "inHg",
+            Self::Metric | Self::Mixed => "hPa",
+        }
+    }
+
+    /// Converts statute miles.
+    pub fn distance(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial | Self::Mixed => val,
+            Self::Metric => val * 1.609_344,
+        }
+    }
+
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            Self::Imperial | Self::Mixed => "mi",
+            Self::Metric => "km",
+        }
+    }
+
+    /// Converts feet.
+    pub fn height(self, val: f64) -> f64 {
+        match self {
+            Self::Imperial | Self::Mixed => val,
+            Self::Metric => val * 0.3048,
+        }
+    }
+
+    pub fn height_unit(self) -> &'static str {
+        match self {
+            Self::Imperial | Self::Mixed => "ft",
+            Self::Metric => "m",
+        }
+    }
+
+    pub fn format_temperature(self, val: Option<f64>) -> String {
+        match val {
+            Some(val) => format!("{:.0}{}", self.temperature(val), self.temperature_unit()),
+            None => String::from("-"),
+        }
+    }
+
+    pub fn format_speed(self, val: Option<f64>) -> String {
+        match val {
+            Some(val) => format!("{:.0} {}", self.speed(val), self.speed_unit()),
+            None => String::from("-"),
+        }
+    }
+
+    pub fn format_knots(self, val: Option<f64>) -> String {
+        self.format_speed(val.map(|x| x * MPH_PER_KNOT))
+    }
+
+    pub fn format_pressure(self, val: Option<f64>) -> String {
+        match (val, self) {
+            (Some(val), Self::Imperial) => format!("{:.2} {}", val, self.pressure_unit()),
+            (Some(val), _) => format!("{:.0} {}", self.pressure(val), self.pressure_unit()),
+            (None, _) => String::from("-"),
+        }
+    }
+
+    pub fn format_height(self, val: Option<f64>) -> String {
+        match val {
+            Some(val) => format!("{:.0} {}", self.height(val), self.height_unit()),
+            None => String::from("-"),
+        }
+    }
+
+    pub fn format_distance(self, val: Option<f64>) -> String {
+        match val {
+            Some(val) => {
+                format!("{} {}", (self.distance(val) * 10.0).round() / 10.0, self.distance_unit())
+            }
+            None => String::from("-"),
+        }
+    }
+}
+
+impl FromStr for Units {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "imperial" => Ok(Self::Imperial),
+            "metric" => Ok(Self::Metric),
+            "mixed" => Ok(Self::Mixed),
+            _ => Err(Error::Invalid("Units must be metric, imperial or mixed".into())),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Imperial => write!(f, "imperial"),
+            Self::Metric => write!(f, "metric"),
+            Self::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_knots() {
+        assert_eq!(Units::Imperial.format_knots(Some(10.0)), "12 mph");
+        assert_eq!(Units::Mixed.format_knots(Some(10.0)), "12 mph");
+        assert_eq!(Units::Metric.format_knots(Some(10.0)), "19 km/h");
+        assert!((Units::Metric.speed_from_knots(1.0) - 1.852).abs() < 0.001);
+    }
+
+    #[test]
+    fn converts_hectopascals() {
+        assert!((Units::Imperial.pressure_from_hpa(1013.25) - 29.92).abs() < 0.005);
+        assert_eq!(Units::Metric.pressure_from_hpa(1013.25), 1013.25);
+        assert_eq!(Units::Metric.format_pressure(Some(29.92)), "1013 hPa");
+    }
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(" Metric ".parse::<Units>().unwrap(), Units::Metric);
+        assert!("kelvin".parse::<Units>().is_err());
+        assert_eq!(Units::Mixed.to_string(), "mixed");
+    }
+}
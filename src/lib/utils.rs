@@ -1,40 +1,310 @@
+use chrono::{DateTime, FixedOffset};
 use serenity::{framework::standard::Args, model::prelude::*, prelude::*};
+use std::{io::Cursor, time::Duration};
 
-use crate::{error::Error, lib::db, Database};
+use crate::{
+    error::Error,
+    lib::{
+        airports::{self, Airport},
+        db,
+        output::Style,
+        providers::{Place, Registry},
+        units::Units,
+    },
+    Database, Providers,
+};
 
-#[derive(Debug, Deserialize)]
-pub struct GeocodeResponse {
-    pub results: Vec<GeocodeData>,
+/// A location argument as typed by the user.
+#[derive(Debug, Clone)]
+pub enum Location {
+    Coordinates(f64, f64),
+    ZipCode(String),
+    Station(&'static Airport),
+    Name(String),
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GeocodeData {
-    pub name: String,
-    pub admin1: String,
-    pub latitude: f64,
-    pub longitude: f64,
+/// Returns the five-digit zip code for a zip code or ZIP+4 code, keeping any
+/// leading zeros.
+pub fn check_zip_code(arg: &str) -> Result<String, Error> {
+    let (zip_code, plus_four) = match arg.split_once('-') {
+        Some((zip_code, plus_four)) => (zip_code, Some(plus_four)),
+        None => (arg, None),
+    };
+    let is_digits = |x: &str, len: usize| x.len() == len && x.chars().all(|x| x.is_ascii_digit());
+
+    if is_digits(zip_code, 5) && plus_four.is_none_or(|x| is_digits(x, 4)) {
+        Ok(zip_code.to_string())
+    } else {
+        Err(Error::Invalid("The zip code provided is invalid".into()))
+    }
 }
 
-pub fn check_zip_code(arg: &str) -> Result<i32, Error> {
-    if arg.len() == 5 {
-        match arg.parse::<i32>() {
-            Ok(zip_code) => Ok(zip_code),
-            Err(_) => Err(Error::Invalid("The zip code provided is invalid".into())),
-        }
+fn check_coordinates(arg: &str) -> Option<Result<(f64, f64), Error>> {
+    let (lat, lon) = arg.split_once(',')?;
+    let (lat, lon) = (lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?);
+
+    if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+        Some(Ok((lat, lon)))
+    } else {
+        Some(Err(Error::Invalid("The coordinates provided are out of range".into())))
+    }
+}
+
+/// Classifies a location argument as coordinates ("39.74,-104.99"), a zip code,
+/// a known ICAO station or a place name (e.g., "Denver, CO").
+pub fn parse_location(arg: &str) -> Result<Location, Error> {
+    let arg = arg.trim();
+
+    if arg.is_empty() {
+        return Err(Error::Invalid(
+            "Provide a zip code, place name, coordinates or station code".into(),
+        ));
+    }
+
+    if let Some(coordinates) = check_coordinates(arg) {
+        let (lat, lon) = coordinates?;
+        return Ok(Location::Coordinates(lat, lon));
+    }
+
+    if let Ok(zip_code) = check_zip_code(arg) {
+        return Ok(Location::ZipCode(zip_code));
+    }
+
+    match airports::find(&arg.to_uppercase()) {
+        Some(airport) => Ok(Location::Station(airport)),
+        None => Ok(Location::Name(arg.to_string())),
+    }
+}
+
+fn is_single_token(arg: &str) -> bool {
+    check_zip_code(arg).is_ok()
+        || matches!(check_coordinates(arg), Some(Ok(_)))
+        || airports::find(&arg.to_uppercase()).is_some()
+        || (matches!(arg.len(), 3 | 4)
+            && arg.chars().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()))
+}
+
+/// Splits command arguments into location queries. A list of zip codes,
+/// coordinates or station codes is several locations; anything else is a
+/// single place name (e.g., "Denver, CO").
+pub fn split_locations(args: &str) -> Vec<String> {
+    let args: Vec<&str> = args.split_whitespace().collect();
+
+    if !args.is_empty() && args.iter().all(|x| is_single_token(x)) {
+        args.iter().map(ToString::to_string).collect()
     } else {
-        Err(Error::Invalid("The zip code provided is not five digits".into()))
+        vec![args.join(" ")]
     }
 }
 
-pub fn check_station_code(station: &str) -> Result<(), Error> {
-    if station.len() == 4 {
-        if station.starts_with('K') {
-            Ok(())
-        } else {
-            Err(Error::Invalid("U.S. station codes only (e.g., KSFO, KJFK)".into()))
+/// Splits a trailing number (e.g., hours or a radius) from a location query.
+/// A lone number is a count, leaving the query empty for the saved location.
+pub fn split_count(args: &str) -> (String, Option<String>) {
+    let args: Vec<&str> = args.split_whitespace().collect();
+
+    match args.split_last() {
+        Some((last, rest))
+            if !rest.last().is_some_and(|x| x.ends_with(','))
+                && check_zip_code(last).is_err()
+                && last.parse::<f64>().is_ok() =>
+        {
+            (rest.join(" "), Some(last.to_string()))
         }
+        _ => (args.join(" "), None),
+    }
+}
+
+/// Returns the location to save for a query. Place names are saved as the
+/// matched place so later lookups don't prompt again.
+pub fn stored_location(query: &str, place: &Place) -> String {
+    match parse_location(query) {
+        Ok(Location::Name(_)) => place.to_string(),
+        _ => query.trim().to_string(),
+    }
+}
+
+/// Resolves a location query to a single place. When the geocoder returns
+/// several matches, the user is asked to pick one by replying with its number.
+pub async fn resolve_location(ctx: &Context, msg: &Message, query: &str) -> Result<Place, Error> {
+    let providers = {
+        let data = ctx.data.read().await;
+        data.get::<Providers>().expect("Error retrieving providers").clone()
+    };
+    let mut places = providers.search(query).await?;
+
+    if places.len() == 1 {
+        return Ok(places.remove(0));
+    }
+
+    places.truncate(5);
+
+    let list: Vec<String> = places
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            format!(
+                "{}. {}, {} (lat: {:.2}, lon: {:.2})",
+                i + 1,
+                x,
+                x.country,
+                x.latitude,
+                x.longitude
+            )
+        })
+        .collect();
+
+    msg.channel_id
+        .say(
+            &ctx.http,
+            format!(
+                "```\nMultiple locations match {query}\n\n{}\n\nReply with a number to choose one\n```",
+                list.join("\n")
+            ),
+        )
+        .await?;
+
+    let reply = msg
+        .author
+        .await_reply(ctx)
+        .channel_id(msg.channel_id)
+        .timeout(Duration::from_secs(30))
+        .await;
+
+    match reply.and_then(|x| x.content.trim().parse::<usize>().ok()) {
+        Some(i) if (1..=places.len()).contains(&i) => Ok(places.remove(i - 1)),
+        _ => Err(Error::Invalid(format!("No location was selected for {query}"))),
+    }
+}
+
+/// Returns true when tgftp publishes observations for a station, so codes
+/// missing from the airport table are still accepted.
+async fn has_observations(station: &str) -> bool {
+    let url = format!("https://tgftp.nws.noaa.gov/data/observations/metar/stations/{station}.TXT");
+
+    match reqwest::get(&url).await {
+        Ok(resp) => resp.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+fn unknown_station(station: &str) -> Error {
+    let suggestions: Vec<String> = airports::suggest(station)
+        .iter()
+        .map(|x| format!("{} ({}, {})", x.ident, x.name, x.country))
+        .collect();
+
+    if suggestions.is_empty() {
+        Error::Invalid(format!("The station code {station} is not a known ICAO station"))
+    } else {
+        Error::Invalid(format!(
+            "The station code {station} is not a known ICAO station. Did you mean {}?",
+            suggestions.join(", ")
+        ))
+    }
+}
+
+pub async fn check_station_code(station: &str) -> Result<(), Error> {
+    if airports::find(station).is_some() || has_observations(station).await {
+        Ok(())
     } else {
-        Err(Error::Invalid("The station code provided is invalid".into()))
+        Err(unknown_station(station))
+    }
+}
+
+fn is_station_code(station: &str) -> bool {
+    station.len() == 4 && station.chars().all(|x| x.is_ascii_alphanumeric())
+}
+
+/// Returns the station for arguments that name one directly, or None when the
+/// argument is a location to be resolved. Four-character codes missing from
+/// the airport table are tried against tgftp before being geocoded.
+async fn station_code(arg: &str) -> Result<Option<String>, Error> {
+    let station = arg.trim().to_uppercase();
+
+    if station.len() == 3 && station.chars().all(|x| x.is_ascii_alphanumeric()) {
+        return Err(unknown_station(&station));
+    }
+
+    match parse_location(arg)? {
+        Location::Station(airport) => Ok(Some(airport.ident.clone())),
+        _ if is_station_code(&station) && has_observations(&station).await => Ok(Some(station)),
+        _ => Ok(None),
+    }
+}
+
+/// Falls back to treating a four-letter argument that didn't resolve to a
+/// place as a station code, so unknown codes get suggestions.
+fn unresolved_station(arg: &str, error: Error) -> Error {
+    let station = arg.trim().to_uppercase();
+
+    if is_station_code(&station) {
+        unknown_station(&station)
+    } else {
+        error
+    }
+}
+
+fn nearest_station(place: &Place) -> Result<String, Error> {
+    match airports::nearest(place.latitude, place.longitude, 100.0).first() {
+        Some((airport, _, _)) => Ok(airport.ident.clone()),
+        None => Err(Error::NotFound(format!("No stations found within 100 miles of {place}"))),
+    }
+}
+
+/// Resolves a station argument to an ICAO identifier. Other locations are
+/// mapped to the nearest known station.
+pub async fn check_station(ctx: &Context, msg: &Message, arg: &str) -> Result<String, Error> {
+    if let Some(station) = station_code(arg).await? {
+        return Ok(station);
+    }
+
+    match resolve_location(ctx, msg, arg).await {
+        Ok(place) => nearest_station(&place),
+        Err(e) => Err(unresolved_station(arg, e)),
+    }
+}
+
+/// Resolves a station argument like `check_station`, taking the first match
+/// for place names instead of asking the user to choose.
+pub async fn find_station(providers: &Registry, arg: &str) -> Result<String, Error> {
+    if let Some(station) = station_code(arg).await? {
+        return Ok(station);
+    }
+
+    match providers.locate(arg).await {
+        Ok(place) => nearest_station(&place),
+        Err(e) => Err(unresolved_station(arg, e)),
+    }
+}
+
+/// Returns the user's preferred units, or imperial when none are set.
+pub async fn fetch_units(ctx: &Context, user_id: UserId) -> Units {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+
+    match db::fetch_units(&pool, user_id).await {
+        Ok(val) => val.parse().unwrap_or_default(),
+        Err(_) => Units::default(),
+    }
+}
+
+/// Returns the guild's output style, or code blocks in direct messages and
+/// guilds that haven't chosen one.
+pub async fn fetch_style(ctx: &Context, guild_id: Option<GuildId>) -> Style {
+    let guild_id = match guild_id {
+        Some(val) => val,
+        None => return Style::default(),
+    };
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<Database>().expect("Error retrieving database pool").clone()
+    };
+
+    match db::fetch_output(&pool, guild_id).await {
+        Ok(val) => val.parse().unwrap_or_default(),
+        Err(_) => Style::default(),
     }
 }
 
@@ -44,7 +314,7 @@ pub async fn check_location(ctx: &Context, msg: &Message, args: &Args) -> Result
             let data = ctx.data.read().await;
             data.get::<Database>().expect("Error retrieving database pool").clone()
         };
-        let zip_code = db::fetch_location(&pool, msg).await?;
+        let zip_code = db::fetch_location(&pool, msg.author.id).await?;
 
         Ok(zip_code)
     } else {
@@ -52,21 +322,20 @@ pub async fn check_location(ctx: &Context, msg: &Message, args: &Args) -> Result
     }
 }
 
-pub async fn fetch_location(zip_code: i32) -> Result<GeocodeResponse, Error> {
-    let url = format!("https://geocoding-api.open-meteo.com/v1/search?name={zip_code}&count=1&language=en&format=json");
-    let resp = reqwest::get(&url).await?.json().await;
+/// Formats a time in its own offset, followed by that offset (e.g., "Sun 12:00
+/// PM (UTC-06:00)").
+pub fn format_local(time: DateTime<FixedOffset>, fmt: &str) -> String {
+    format!("{} (UTC{})", time.format(fmt), time.format("%:z"))
+}
 
-    match resp {
-        Ok(data) => {
-            let resp: GeocodeResponse = data;
-            Ok(resp)
-        }
-        Err(_) => Err(Error::NotFound("The zip code provided does not match a location".into())),
+/// Returns the 16-point compass direction for a bearing in degrees, or "-" when
+/// the bearing is not finite.
+pub fn cardinal_direction(val: f64) -> String {
+    if !val.is_finite() {
+        return String::from("-");
     }
-}
 
-pub fn cardinal_direction(val: &str) -> String {
-    let val: f64 = val.parse::<f64>().unwrap();
+    let val = val.rem_euclid(360.0);
     let directions = [
         "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
         "NW", "NNW", "N",
@@ -75,3 +344,59 @@ pub fn cardinal_direction(val: &str) -> String {
 
     String::from(directions[index])
 }
+
+/// Encodes an RGB buffer drawn with `BitMapBackend::with_buffer` as a PNG.
+pub fn encode_png(buffer: Vec<u8>, (width, height): (u32, u32)) -> Result<Vec<u8>, Error> {
+    let image = image::RgbImage::from_raw(width, height, buffer)
+        .ok_or_else(|| Error::Plot("The chart buffer does not match its size".into()))?;
+    let mut data = Vec::new();
+
+    image.write_to(&mut Cursor::new(&mut data), image::ImageOutputFormat::Png)?;
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_trailing_counts() {
+        assert_eq!(split_count("Denver 12"), (String::from("Denver"), Some(String::from("12"))));
+        assert_eq!(split_count("KSFO 48"), (String::from("KSFO"), Some(String::from("48"))));
+        assert_eq!(split_count("12"), (String::new(), Some(String::from("12"))));
+        assert_eq!(split_count(""), (String::new(), None));
+    }
+
+    #[test]
+    fn keeps_locations_that_end_in_numbers() {
+        assert_eq!(split_count("80202"), (String::from("80202"), None));
+        assert_eq!(split_count("Denver 80202"), (String::from("Denver 80202"), None));
+        assert_eq!(split_count("39.74, -104.99"), (String::from("39.74, -104.99"), None));
+    }
+
+    #[test]
+    fn names_compass_directions() {
+        assert_eq!(cardinal_direction(0.0), "N");
+        assert_eq!(cardinal_direction(247.0), "WSW");
+        assert_eq!(cardinal_direction(355.0), "N");
+        assert_eq!(cardinal_direction(-90.0), "W");
+        assert_eq!(cardinal_direction(f64::NAN), "-");
+    }
+
+    #[test]
+    fn classifies_locations() {
+        assert!(matches!(parse_location("39.74,-104.99"), Ok(Location::Coordinates(_, _))));
+        assert!(matches!(parse_location("80202-1234"), Ok(Location::ZipCode(x)) if x == "80202"));
+        assert!(matches!(parse_location("ksfo"), Ok(Location::Station(x)) if x.ident == "KSFO"));
+        assert!(matches!(parse_location("Denver, CO"), Ok(Location::Name(_))));
+        assert!(parse_location("91,0").is_err());
+        assert!(parse_location(" ").is_err());
+    }
+
+    #[test]
+    fn splits_lists_of_locations() {
+        assert_eq!(split_locations("KSFO KPTK 80202"), vec!["KSFO", "KPTK", "80202"]);
+        assert_eq!(split_locations("San Francisco"), vec!["San Francisco"]);
+    }
+}
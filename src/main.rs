@@ -5,80 +5,189 @@
 #[macro_use]
 extern crate serde;
 
-use chrono::{DateTime, Local, NaiveTime};
+use chrono::{DateTime, Local, NaiveTime, SecondsFormat, Utc};
 use once_cell::sync::OnceCell;
 use serenity::{
     async_trait,
     framework::standard::{macros::group, StandardFramework},
-    model::{channel::Message, gateway::Ready, id::UserId},
+    model::{application::interaction::Interaction, channel::Message, gateway::Ready, id::UserId},
     prelude::*,
 };
 use sqlx::Sqlite;
-use std::{fs, time::Duration};
-
-use std::time;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 
 mod commands {
+    pub mod airports;
     pub mod alerts;
     pub mod atis;
+    pub mod flightcat;
     pub mod location;
     pub mod meta;
     pub mod metar;
+    pub mod output;
+    pub mod slash;
+    pub mod subscribe;
     pub mod taf;
+    pub mod units;
     pub mod uv;
     pub mod wx;
 }
 
 mod lib {
+    pub mod airports;
+    pub mod atis;
     pub mod config;
     pub mod db;
     pub mod error;
+    pub mod metar;
+    pub mod output;
+    pub mod providers;
+    pub mod taf;
+    pub mod units;
     pub mod utils;
 }
 
 #[allow(clippy::wildcard_imports)]
-use commands::{alerts::*, atis::*, location::*, meta::*, metar::*, taf::*, uv::*, wx::*};
-use lib::{config, db, error, utils};
+use commands::{
+    airports::*, alerts::*, atis::*, flightcat::*, location::*, meta::*, metar::*, output::*,
+    subscribe::*, taf::*, units::*, uv::*, wx::*,
+};
+use lib::{config, db, error, output, providers};
 
 static CELL: OnceCell<()> = OnceCell::new();
 
 struct Handler;
 
 impl Handler {
-    async fn run_background_tasks(ctx: &Context) -> Result<(), error::Error> {
-        let config = config::Config::load_config()?;
-        let start_time = NaiveTime::from_hms_opt(8, 30, 0).unwrap();
-        let end_time = NaiveTime::from_hms_opt(8, 31, 0).unwrap();
-        let current_time = Local::now().time();
-
-        if (current_time >= start_time)
-            && (current_time < end_time)
-            && !config.alert_zip_codes.is_empty()
-        {
-            for zip_code in config.alert_zip_codes {
-                let alert_zone =
-                    utils::fetch_location(zip_code).await.expect("Error retrieving alert zone");
-                let data = commands::alerts::parse_alerts(zip_code, alert_zone).await;
-                for user in &config.alert_users {
-                    if let Err(e) = Self::message_user(ctx, *user, &data).await {
-                        println!("Error sending message to user: {e}");
+    /// Sends daily UV and forecast subscriptions whose time has passed at their
+    /// location and that haven't been sent there today. Each location's UTC
+    /// offset is refreshed after sending to follow daylight saving time.
+    async fn send_subscriptions(ctx: &Context) -> Result<(), error::Error> {
+        let (providers, pool) = {
+            let data = ctx.data.read().await;
+            (
+                data.get::<Providers>().expect("Error retrieving providers").clone(),
+                data.get::<Database>().expect("Error retrieving database pool").clone(),
+            )
+        };
+
+        for kind in [Kind::Uv, Kind::Forecast] {
+            for subscription in db::fetch_subscriptions(&pool, &kind.to_string()).await? {
+                let now = Utc::now()
+                    .with_timezone(&commands::subscribe::subscription_offset(&subscription));
+                let today = now.date_naive().to_string();
+                let due = subscription
+                    .time
+                    .as_deref()
+                    .and_then(|x| NaiveTime::parse_from_str(x, "%H:%M").ok())
+                    .is_some_and(|x| x <= now.time());
+
+                if !due || subscription.last_sent.as_deref() == Some(today.as_str()) {
+                    continue;
+                }
+
+                let user = subscription.user_id.parse::<u64>().unwrap_or_default();
+                let (report, place) = match providers.locate(&subscription.location).await {
+                    Ok(place) => {
+                        let report = match kind {
+                            Kind::Uv => commands::uv::parse_forecast(&providers, &place).await,
+                            _ => {
+                                let units = lib::utils::fetch_units(ctx, UserId(user)).await;
+                                commands::wx::parse_forecast(&providers, &place, units).await
+                            }
+                        };
+
+                        (report, Some(place))
                     }
-                    tokio::time::sleep(time::Duration::from_secs(3)).await;
+                    Err(e) => (Err(e), None),
+                };
+                let data = match report {
+                    Ok(report) => report.to_code_block(),
+                    Err(e) => format!("`There was an error retrieving data: {e}`"),
+                };
+
+                if let Err(e) = Self::message_user(ctx, user, &data).await {
+                    println!("Error sending message to user: {e}");
+                }
+                db::update_subscription_sent(&pool, subscription.id, &today).await?;
+
+                if let Some(place) = &place {
+                    let offset = commands::subscribe::utc_offset(&providers, place).await;
+
+                    db::update_subscription_offset(
+                        &pool,
+                        subscription.id,
+                        offset.local_minus_utc(),
+                    )
+                    .await?;
                 }
             }
         }
 
-        if (current_time >= start_time)
-            && (current_time < end_time)
-            && !config.uv_zip_codes.is_empty()
-        {
-            for zip_code in config.uv_zip_codes {
-                let data = commands::uv::parse_forecast(zip_code).await;
-                for user in &config.uv_users {
+        Ok(())
+    }
+
+    /// Pushes alert changes for each subscribed location to its subscribers.
+    /// Places are cached between polls since saved locations don't move.
+    async fn check_alerts(
+        ctx: &Context,
+        places: &mut HashMap<String, providers::Place>,
+    ) -> Result<(), error::Error> {
+        let (providers, pool) = {
+            let data = ctx.data.read().await;
+            (
+                data.get::<Providers>().expect("Error retrieving providers").clone(),
+                data.get::<Database>().expect("Error retrieving database pool").clone(),
+            )
+        };
+        let mut locations: BTreeMap<String, Vec<(u64, AlertFilter)>> = BTreeMap::new();
+
+        for subscription in db::fetch_subscriptions(&pool, &Kind::Alerts.to_string()).await? {
+            let user = subscription.user_id.parse::<u64>().unwrap_or_default();
+            let filter = subscription
+                .filter
+                .as_deref()
+                .and_then(|x| AlertFilter::parse(x).ok())
+                .map(|(_, filter)| filter)
+                .unwrap_or_default();
+
+            locations.entry(subscription.location).or_default().push((user, filter));
+        }
+
+        db::delete_unsubscribed_alerts(&pool).await?;
+
+        for (location, users) in locations {
+            let place = match places.get(&location) {
+                Some(val) => val.clone(),
+                None => match providers.locate(&location).await {
+                    Ok(val) => places.entry(location.clone()).or_insert(val).clone(),
+                    Err(e) => {
+                        println!("Error locating {location} for alerts: {e}");
+                        continue;
+                    }
+                },
+            };
+            let changes = match commands::alerts::fetch_changes(&pool, &location, &place).await {
+                Ok(val) => val,
+                Err(e) => {
+                    println!("Error checking alerts for {location}: {e}");
+                    continue;
+                }
+            };
+
+            for change in changes {
+                let data = change.report.to_code_block();
+
+                for (user, _) in
+                    users.iter().filter(|(_, x)| x.matches(&change.event, &change.severity))
+                {
                     if let Err(e) = Self::message_user(ctx, *user, &data).await {
                         println!("Error sending message to user: {e}");
                     }
-                    tokio::time::sleep(time::Duration::from_secs(3)).await;
                 }
             }
         }
@@ -86,6 +195,58 @@ impl Handler {
         Ok(())
     }
 
+    async fn check_atis_watches(ctx: &Context) -> Result<(), error::Error> {
+        let pool = {
+            let data = ctx.data.read().await;
+            data.get::<Database>().expect("Error retrieving database pool").clone()
+        };
+        let watches = db::fetch_atis_watches(&pool).await?;
+        let mut stations: Vec<&str> = watches.iter().map(|x| x.station.as_str()).collect();
+
+        stations.sort_unstable();
+        stations.dedup();
+
+        for station in stations {
+            let (code, data) = match commands::atis::fetch_update(station).await {
+                Ok(val) => val,
+                Err(e) => {
+                    println!("Error retrieving ATIS for {station}: {e}");
+                    continue;
+                }
+            };
+
+            for watch in watches.iter().filter(|x| x.station == station && x.code != code) {
+                let user = watch.user_id.parse::<u64>().unwrap_or_default();
+                let data = format!("`ATIS information {code} is now current at {station}`\n{data}");
+
+                if let Err(e) = Self::message_user(ctx, user, &data).await {
+                    println!("Error sending message to user: {e}");
+                }
+                db::update_atis_watch(&pool, watch.id, &code).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn archive_observations(ctx: &Context) -> Result<(), error::Error> {
+        let config = config::Config::load_config()?;
+        let pool = {
+            let data = ctx.data.read().await;
+            data.get::<Database>().expect("Error retrieving database pool").clone()
+        };
+
+        for station in &config.metar_stations {
+            if let Err(e) = commands::metar::archive_report(&pool, &station.to_uppercase()).await {
+                println!("Error archiving METAR for {station}: {e}");
+            }
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+
+        db::delete_observations(&pool, &cutoff.to_rfc3339_opts(SecondsFormat::Secs, true)).await
+    }
+
     async fn healthcheck() -> Result<(), error::Error> {
         let config = config::Config::load_config()?;
 
@@ -104,11 +265,9 @@ impl Handler {
     }
 
     async fn message_user(ctx: &Context, user: u64, data: &str) -> Result<(), error::Error> {
-        UserId(user)
-            .create_dm_channel(&ctx.http)
-            .await?
-            .send_message(&ctx.http, |m| m.content(data))
-            .await?;
+        let channel = UserId(user).create_dm_channel(&ctx.http).await?;
+
+        output::send_text(&ctx.http, channel.id, data).await?;
 
         Ok(())
     }
@@ -119,44 +278,85 @@ impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected.", ready.user.name);
 
+        if let Err(e) = commands::slash::register(&ctx).await {
+            println!("Error registering slash commands: {e}");
+        }
+
         CELL.get_or_init(|| {
+            let alerts_ctx = ctx.clone();
+            let archive_ctx = ctx.clone();
+
             tokio::spawn(async move {
                 let mut interval = tokio::time::interval(Duration::from_secs(60));
 
                 loop {
-                    Self::run_background_tasks(&ctx).await.expect("Error running background tasks");
+                    if let Err(e) = Self::send_subscriptions(&ctx).await {
+                        println!("Error sending subscriptions: {e}");
+                    }
+
+                    if let Err(e) = Self::check_atis_watches(&ctx).await {
+                        println!("Error checking ATIS watches: {e}");
+                    }
 
                     interval.tick().await;
                 }
             });
 
-            tokio::spawn(async {
+            tokio::spawn(async move {
                 let mut interval = tokio::time::interval(Duration::from_secs(60));
+                let mut places = HashMap::new();
 
                 loop {
-                    Self::healthcheck().await.expect("Error running healthcheck background");
+                    if let Err(e) = Self::check_alerts(&alerts_ctx, &mut places).await {
+                        println!("Error checking alerts: {e}");
+                    }
 
                     interval.tick().await;
                 }
             });
-        });
 
-        tokio::spawn(async {
-            loop {
-                if fs::metadata("./attachments").is_ok() {
-                    match fs::remove_dir_all("./attachments") {
-                        Ok(()) => {}
-                        Err(e) => println!("Error deleting directory: {e}"),
+            // METARs are issued hourly with specials in between, so poll often
+            // and let the archive ignore reports it already has
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(300));
+
+                loop {
+                    if let Err(e) = Self::archive_observations(&archive_ctx).await {
+                        println!("Error archiving observations: {e}");
                     }
+
+                    interval.tick().await;
                 }
-                fs::create_dir_all("./attachments")
-                    .expect("Error creating ./attachments directory");
+            });
 
-                tokio::time::sleep(time::Duration::from_secs(86400)).await;
-            }
+            tokio::spawn(async {
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+                loop {
+                    Self::healthcheck().await.expect("Error running healthcheck background");
+
+                    interval.tick().await;
+                }
+            });
         });
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let result = match interaction {
+            Interaction::ApplicationCommand(command) => {
+                commands::slash::handle_command(&ctx, &command).await
+            }
+            Interaction::Autocomplete(autocomplete) => {
+                commands::slash::handle_autocomplete(&ctx, &autocomplete).await
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            println!("Error handling interaction: {e}");
+        }
+    }
+
     async fn message(&self, ctx: Context, msg: Message) {
         if ctx.cache.current_user_id() != msg.author.id.0 {
             tokio::spawn(async move {
@@ -183,6 +383,12 @@ impl TypeMapKey for Database {
     type Value = sqlx::Pool<Sqlite>;
 }
 
+struct Providers;
+
+impl TypeMapKey for Providers {
+    type Value = Arc<providers::Registry>;
+}
+
 struct Uptime;
 
 impl TypeMapKey for Uptime {
@@ -193,13 +399,25 @@ impl TypeMapKey for Uptime {
 struct Admin;
 
 #[group]
-#[commands(alerts)]
+#[commands(airports)]
+struct Airports;
+
+#[group]
+#[prefixes("alerts")]
+#[default_command(alerts)]
+#[commands(alerts_detail)]
 struct Alerts;
 
 #[group]
-#[commands(atis)]
+#[prefixes("atis")]
+#[default_command(atis)]
+#[commands(atis_watch, atis_unwatch)]
 struct Atis;
 
+#[group]
+#[commands(flightcat)]
+struct FlightCat;
+
 #[group]
 #[prefixes("location")]
 #[commands(location_set, location_list, location_delete)]
@@ -210,13 +428,33 @@ struct Location;
 struct Meta;
 
 #[group]
-#[commands(metar)]
+#[prefixes("metar")]
+#[default_command(metar)]
+#[commands(metar_decode, metar_history, metar_graph)]
 struct METAR;
 
 #[group]
-#[commands(taf)]
+#[prefixes("output")]
+#[default_command(output)]
+#[commands(output_set)]
+struct Output;
+
+#[group]
+#[commands(subscribe, unsubscribe)]
+struct Subscribe;
+
+#[group]
+#[prefixes("taf")]
+#[default_command(taf)]
+#[commands(taf_decode, taf_graph)]
 struct TAF;
 
+#[group]
+#[prefixes("units")]
+#[default_command(units)]
+#[commands(units_set)]
+struct Units;
+
 #[group]
 #[prefixes("uv")]
 #[commands(uv_current, uv_forecast)]
@@ -224,7 +462,7 @@ struct UV;
 
 #[group]
 #[prefixes("wx")]
-#[commands(wx_current, wx_forecast, wx_graph)]
+#[commands(wx_current, wx_forecast, wx_hourly, wx_feels, wx_graph)]
 struct WX;
 
 #[tokio::main]
@@ -237,6 +475,9 @@ async fn main() {
             "!"
         }
     };
+    let registry = providers::Registry::from_config(&config).expect("Error loading providers");
+
+    println!("Loaded {} stations", lib::airports::load());
     let pool = sqlx::sqlite::SqlitePoolOptions::new()
         .max_connections(5)
         .connect_with(
@@ -252,12 +493,17 @@ async fn main() {
     let framework = StandardFramework::new()
         .configure(|c| c.prefix(prefix))
         .group(&ADMIN_GROUP)
+        .group(&AIRPORTS_GROUP)
         .group(&ALERTS_GROUP)
         .group(&ATIS_GROUP)
+        .group(&FLIGHTCAT_GROUP)
         .group(&LOCATION_GROUP)
         .group(&META_GROUP)
         .group(&METAR_GROUP)
+        .group(&OUTPUT_GROUP)
+        .group(&SUBSCRIBE_GROUP)
         .group(&TAF_GROUP)
+        .group(&UNITS_GROUP)
         .group(&UV_GROUP)
         .group(&WX_GROUP);
     let mut client = Client::builder(&config.discord, intents)
@@ -268,6 +514,22 @@ async fn main() {
 
     db::create_log_table(&pool).await.expect("Error creating database table");
 
+    if !(config.alert_users.is_empty() && config.uv_users.is_empty()) {
+        match commands::subscribe::import_legacy(&pool, &config).await {
+            Ok(Some(count)) => println!(
+                "Imported {count} subscriptions from the alert_users, alert_zip_codes, uv_users \
+                 and uv_zip_codes config fields. They are no longer read and can be removed \
+                 from config.json."
+            ),
+            Ok(None) => println!(
+                "The alert_users, alert_zip_codes, uv_users and uv_zip_codes config fields \
+                 were already imported as subscriptions and are ignored. Remove them from \
+                 config.json."
+            ),
+            Err(e) => println!("Error importing subscriptions from config.json: {e}"),
+        }
+    }
+
     {
         let mut data = client.data.write().await;
         data.insert::<BotAdmin>(config.admin);
@@ -278,6 +540,11 @@ async fn main() {
         data.insert::<Database>(pool);
     }
 
+    {
+        let mut data = client.data.write().await;
+        data.insert::<Providers>(Arc::new(registry));
+    }
+
     {
         let mut data = client.data.write().await;
         data.insert::<Uptime>(Local::now());